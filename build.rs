@@ -0,0 +1,32 @@
+//! Records the version of the linked onecode crate for `--version-info`,
+//! taken from Cargo.lock ("unknown" when building without one).
+
+use std::path::Path;
+
+fn onecode_version(lock: &str) -> Option<String> {
+    let package = lock
+        .split("[[package]]")
+        .find(|package| package.lines().any(|line| line.trim() == "name = \"onecode\""))?;
+    let field = |key: &str| {
+        package.lines().find_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+        })
+    };
+    let version = field("version")?;
+    // Git sources end in #<commit>
+    match field("source").as_deref().and_then(|source| source.rsplit_once('#')) {
+        Some((_, commit)) => Some(format!("{} (git {})", version, &commit[..commit.len().min(7)])),
+        None => Some(version),
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    let version = std::fs::read_to_string(lock)
+        .ok()
+        .and_then(|lock| onecode_version(&lock))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=ONEVIEW_ONECODE_VERSION={}", version);
+}
@@ -0,0 +1,164 @@
+//! Base-level reconstruction of alignments from tracepoints and sequence.
+//!
+//! Tracepoints pin the alignment path at every segment boundary, so each
+//! segment can be aligned on its own with a small unit-cost global
+//! alignment, and the segment paths concatenated.
+
+use crate::AlignmentData;
+use crate::fasta::Sequences;
+use crate::trace;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Match,
+    Mismatch,
+    /// Base present in the query only
+    Insertion,
+    /// Base present in the target only
+    Deletion,
+}
+
+/// Global edit-distance alignment of two short sequences (case-insensitive).
+pub fn align_segment(query: &[u8], target: &[u8]) -> Vec<Op> {
+    let rows = query.len() + 1;
+    let cols = target.len() + 1;
+    let mut dp = vec![0u32; rows * cols];
+    for i in 0..rows {
+        dp[i * cols] = i as u32;
+    }
+    for (j, cell) in dp.iter_mut().enumerate().take(cols) {
+        *cell = j as u32;
+    }
+    for i in 1..rows {
+        for j in 1..cols {
+            let same = query[i - 1].eq_ignore_ascii_case(&target[j - 1]);
+            let diagonal = dp[(i - 1) * cols + j - 1] + u32::from(!same);
+            let up = dp[(i - 1) * cols + j] + 1;
+            let left = dp[i * cols + j - 1] + 1;
+            dp[i * cols + j] = diagonal.min(up).min(left);
+        }
+    }
+
+    // Trace back, preferring the diagonal so gaps stay compact
+    let mut ops = Vec::with_capacity(rows.max(cols));
+    let (mut i, mut j) = (query.len(), target.len());
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let same = query[i - 1].eq_ignore_ascii_case(&target[j - 1]);
+            if dp[i * cols + j] == dp[(i - 1) * cols + j - 1] + u32::from(!same) {
+                ops.push(if same { Op::Match } else { Op::Mismatch });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && dp[i * cols + j] == dp[(i - 1) * cols + j] + 1 {
+            ops.push(Op::Insertion);
+            i -= 1;
+        } else {
+            ops.push(Op::Deletion);
+            j -= 1;
+        }
+    }
+    ops.reverse();
+    ops
+}
+
+/// Exact edit composition of a base-level alignment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EditCounts {
+    pub matches: i64,
+    pub mismatches: i64,
+    pub insertions: i64,
+    pub deletions: i64,
+    pub gap_opens: i64,
+}
+
+impl EditCounts {
+    pub fn from_ops(ops: &[Op]) -> Self {
+        let mut counts = EditCounts::default();
+        let mut previous = None;
+        for &op in ops {
+            match op {
+                Op::Match => counts.matches += 1,
+                Op::Mismatch => counts.mismatches += 1,
+                Op::Insertion => counts.insertions += 1,
+                Op::Deletion => counts.deletions += 1,
+            }
+            if matches!(op, Op::Insertion | Op::Deletion) && previous != Some(op) {
+                counts.gap_opens += 1;
+            }
+            previous = Some(op);
+        }
+        counts
+    }
+
+    /// Edit distance (SAM NM)
+    pub fn edit_distance(&self) -> i64 {
+        self.mismatches + self.insertions + self.deletions
+    }
+
+    /// Alignment columns, including gaps (minimap2's PAF block length)
+    pub fn columns(&self) -> i64 {
+        self.matches + self.mismatches + self.insertions + self.deletions
+    }
+
+    /// Gap-compressed divergence as in minimap2's de:f tag
+    pub fn gap_compressed_divergence(&self) -> f64 {
+        let denominator = self.matches + self.mismatches + self.gap_opens;
+        if denominator == 0 {
+            return 0.0;
+        }
+        (self.mismatches + self.gap_opens) as f64 / denominator as f64
+    }
+}
+
+/// Reconstruct the base-level path of `aln` segment by segment.
+///
+/// Returns `Ok(None)` when the FASTA inputs or a usable trace are missing.
+pub fn reconstruct(
+    aln: &AlignmentData,
+    sequences: &Sequences,
+) -> io::Result<Option<Vec<Op>>> {
+    Ok(reconstruct_with_bases(aln, sequences)?.map(|(_, _, ops)| ops))
+}
+
+/// [`reconstruct`], also returning the query bases and the target bases in
+/// alignment orientation that the path runs over.
+pub fn reconstruct_with_bases(
+    aln: &AlignmentData,
+    sequences: &Sequences,
+) -> io::Result<Option<(String, String, Vec<Op>)>> {
+    let (Some(query_fasta), Some(target_fasta)) = (&sequences.query, &sequences.target) else {
+        return Ok(None);
+    };
+    let query_contig_start = aln.query_start - aln.query_offset;
+    let query_contig_end = aln.query_end - aln.query_offset;
+    let Some(segments) = trace::segments(
+        query_contig_start,
+        query_contig_end,
+        aln.trace_spacing,
+        &aln.tracepoints,
+        &aln.trace_diffs,
+    ) else {
+        return Ok(None);
+    };
+
+    let query = query_fasta.fetch(aln.query_id, aln.query_start, aln.query_end, '+')?;
+    // Target offsets in the trace run along the alignment orientation
+    let target = target_fasta.fetch(aln.target_id, aln.target_start, aln.target_end, aln.strand)?;
+    let (query_bytes, target_bytes) = (query.as_bytes(), target.as_bytes());
+
+    let mut ops = Vec::with_capacity(query_bytes.len().max(target_bytes.len()));
+    for segment in segments {
+        let query_range = segment.query_start as usize..segment.query_end as usize;
+        let target_range = segment.target_start as usize..segment.target_end as usize;
+        let (Some(query_part), Some(target_part)) = (query_bytes.get(query_range), target_bytes.get(target_range))
+        else {
+            return Ok(None);
+        };
+        ops.extend(align_segment(query_part, target_part));
+    }
+    Ok(Some((query, target, ops)))
+}
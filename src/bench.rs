@@ -0,0 +1,126 @@
+//! The hidden `bench` subcommand: throughput of the real parsing and
+//! formatting paths, for reporting performance regressions.
+//!
+//! Formatted output goes to a sink, so the numbers cover parsing and
+//! formatting but not terminal or disk I/O.
+
+use crate::coords::CoordBase;
+use crate::fai::FaiMetadata;
+use crate::fasta::Sequences;
+use crate::mapq::MapqMode;
+use crate::names::{NamePolicy, NameSanitizer};
+use crate::resolve::ResolveOptions;
+use crate::score::ScoringModel;
+use crate::{
+    ExcludedSequences, LengthSource, OutputConfig, OutputFormat, PafTag, ParseContext, ParseWarnings, for_each_alignment,
+    get_file_metadata, print_alignment,
+};
+use clap::Parser;
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+#[command(name = "oneview-rs bench")]
+#[command(about = "Measure parsing and formatting throughput on a .1aln file", long_about = None)]
+pub struct BenchArgs {
+    /// Input .1aln file path
+    #[arg(value_name = "FILE")]
+    input: String,
+
+    /// Number of passes over the file per mode
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    iterations: usize,
+}
+
+fn output_config(format: OutputFormat) -> OutputConfig {
+    OutputConfig {
+        format,
+        pansn: None,
+        names: NameSanitizer::new(NamePolicy::FirstWord),
+        coord_base: CoordBase::Zero,
+        template: None,
+        tags: vec![PafTag::Df, PafTag::Tp, PafTag::Nm],
+        mapq_mode: MapqMode::Constant(255),
+        compat_alntopaf: false,
+        transpose: false,
+        trace_values: None,
+        trace_limit: None,
+        sections: None,
+        print_offsets: false,
+        collapsed: false,
+        empty_traces: true,
+        style: crate::style::Style::default(),
+        pretty: None,
+        split: None,
+        annotations: None,
+        sequences: Sequences::default(),
+        scoring: ScoringModel {
+            match_score: 2,
+            mismatch: 4,
+            gap_open: 4,
+            gap_extend: 2,
+        },
+    }
+}
+
+pub fn run(args: BenchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.iterations == 0 {
+        return Err("--iterations must be at least 1".into());
+    }
+    let (metadata, trace_spacing, _) = get_file_metadata(&args.input, None, false, &ResolveOptions::default(), FaiMetadata::default())?;
+    let sequences = Sequences::default();
+
+    let modes = [
+        ("parse", None),
+        ("paf", Some(output_config(OutputFormat::Paf))),
+        ("human", Some(output_config(OutputFormat::Human))),
+    ];
+
+    println!("mode\titerations\talignments\tmean_seconds\talignments_per_sec");
+    for (label, output) in &modes {
+        let mut elapsed = Duration::ZERO;
+        let mut alignments = 0;
+        for _ in 0..args.iterations {
+            let mut ctx = ParseContext {
+                metadata: &metadata,
+                length_source: LengthSource::Metadata,
+                read_traces: true,
+                trace_spacing,
+                sequences: &sequences,
+                exact_identity: false,
+                strict: false,
+                skip_missing_metadata: false,
+                allow_anonymous: false,
+                trim_ends: None,
+                clip: None,
+                respace: None,
+                explain: false,
+                stream: None,
+                excluded: ExcludedSequences::default(),
+                representatives: None,
+                warnings: ParseWarnings::default(),
+            };
+            let mut sink = io::sink();
+            let start = Instant::now();
+            alignments = for_each_alignment(&args.input, &mut ctx, |aln| {
+                if let Some(output) = output {
+                    print_alignment(&mut sink, &aln, output)?;
+                }
+                Ok(())
+            })?;
+            elapsed += start.elapsed();
+        }
+
+        let mean_seconds = elapsed.as_secs_f64() / args.iterations as f64;
+        let rate = if mean_seconds > 0.0 {
+            alignments as f64 / mean_seconds
+        } else {
+            0.0
+        };
+        println!(
+            "{}\t{}\t{}\t{:.4}\t{:.0}",
+            label, args.iterations, alignments, mean_seconds, rate
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Greedy chaining of collinear alignments for `--chain-score`.
+//!
+//! Alignments are grouped by query, target and strand, and each group is
+//! sorted by query start. Walking that order, an alignment extends the open
+//! chain where it adds the most score, or starts a new chain when it fits
+//! none. It fits a chain when it follows the chain's last alignment on both
+//! sequences without overlap (on the '-' strand the target runs backwards)
+//! and neither gap exceeds the maximum.
+//!
+//! A chain's score is the sum of its alignments' query spans, minus the
+//! larger of the query and target gaps between each consecutive pair. An
+//! extension is only taken when it adds more span than it costs in gap.
+
+use crate::AlignmentData;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+struct Chain {
+    alignments: usize,
+    query_start: i64,
+    query_end: i64,
+    target_start: i64,
+    target_end: i64,
+    /// Sum of the member query spans
+    span: i64,
+    score: i64,
+    /// Target end of the last member in alignment orientation: its end on
+    /// '+', its start on '-'
+    target_tip: i64,
+}
+
+/// The members of one query/target/strand group, as query and target intervals.
+type Member = (i64, i64, i64, i64);
+
+#[derive(Default)]
+pub struct Chainer {
+    groups: BTreeMap<(String, String, char), Vec<Member>>,
+}
+
+impl Chainer {
+    pub fn add(&mut self, query: &str, target: &str, aln: &AlignmentData) {
+        self.groups
+            .entry((query.to_string(), target.to_string(), aln.strand))
+            .or_default()
+            .push((aln.query_start, aln.query_end, aln.target_start, aln.target_end));
+    }
+
+    fn chain_group(members: &mut [Member], strand: char, max_gap: i64) -> Vec<Chain> {
+        members.sort_unstable();
+        let mut chains: Vec<Chain> = Vec::new();
+        for &(query_start, query_end, target_start, target_end) in members.iter() {
+            let span = query_end - query_start;
+            let mut best: Option<(usize, i64)> = None;
+            for (i, chain) in chains.iter().enumerate() {
+                let query_gap = query_start - chain.query_end;
+                let target_gap = if strand == '-' {
+                    chain.target_tip - target_end
+                } else {
+                    target_start - chain.target_tip
+                };
+                if query_gap < 0 || target_gap < 0 || query_gap.max(target_gap) > max_gap {
+                    continue;
+                }
+                let gain = span - query_gap.max(target_gap);
+                if gain > 0 && best.is_none_or(|(_, best_gain)| gain > best_gain) {
+                    best = Some((i, gain));
+                }
+            }
+
+            match best {
+                Some((i, gain)) => {
+                    let chain = &mut chains[i];
+                    chain.alignments += 1;
+                    chain.query_end = query_end;
+                    chain.target_start = chain.target_start.min(target_start);
+                    chain.target_end = chain.target_end.max(target_end);
+                    chain.span += span;
+                    chain.score += gain;
+                    chain.target_tip = if strand == '-' { target_start } else { target_end };
+                }
+                None => chains.push(Chain {
+                    alignments: 1,
+                    query_start,
+                    query_end,
+                    target_start,
+                    target_end,
+                    span,
+                    score: span,
+                    target_tip: if strand == '-' { target_start } else { target_end },
+                }),
+            }
+        }
+        chains
+    }
+
+    /// Chain every group and write one TSV line per chain, best score first within a group.
+    pub fn write<W: Write>(mut self, out: &mut W, max_gap: i64) -> io::Result<()> {
+        writeln!(
+            out,
+            "#query\ttarget\tstrand\talignments\tquery_start\tquery_end\ttarget_start\ttarget_end\tspan\tscore"
+        )?;
+        for ((query, target, strand), members) in &mut self.groups {
+            let mut chains = Self::chain_group(members, *strand, max_gap);
+            chains.sort_by(|a, b| b.score.cmp(&a.score).then(a.query_start.cmp(&b.query_start)));
+            for chain in chains {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    query,
+                    target,
+                    strand,
+                    chain.alignments,
+                    chain.query_start,
+                    chain.query_end,
+                    chain.target_start,
+                    chain.target_end,
+                    chain.span,
+                    chain.score
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
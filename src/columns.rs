@@ -0,0 +1,90 @@
+//! Registry of the per-alignment values that user-selected output can refer to.
+
+use crate::{AlignmentData, OutputConfig};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Column {
+    Index,
+    QueryName,
+    QueryLength,
+    QueryStart,
+    QueryEnd,
+    TargetName,
+    TargetLength,
+    TargetStart,
+    TargetEnd,
+    Strand,
+    Differences,
+    Matches,
+    BlockLength,
+    Identity,
+    Score,
+    IndelBalance,
+    TracepointCount,
+}
+
+/// Column names as written by users, with a short description each.
+pub const COLUMNS: &[(&str, Column, &str)] = &[
+    ("index", Column::Index, "alignment index in the file (0-based)"),
+    ("qname", Column::QueryName, "query sequence name"),
+    ("qlen", Column::QueryLength, "query sequence length"),
+    ("qstart", Column::QueryStart, "query start"),
+    ("qend", Column::QueryEnd, "query end"),
+    ("tname", Column::TargetName, "target sequence name"),
+    ("tlen", Column::TargetLength, "target sequence length"),
+    ("tstart", Column::TargetStart, "target start"),
+    ("tend", Column::TargetEnd, "target end"),
+    ("strand", Column::Strand, "relative strand (+ or -)"),
+    ("diffs", Column::Differences, "number of differences (D line)"),
+    ("matches", Column::Matches, "estimated matches (PAF column 10)"),
+    ("block", Column::BlockLength, "alignment block length (PAF column 11)"),
+    ("identity", Column::Identity, "estimated identity"),
+    ("score", Column::Score, "alignment score under the --score-* model"),
+    ("ib", Column::IndelBalance, "net indel balance: query span minus target span"),
+    ("ntp", Column::TracepointCount, "number of tracepoints"),
+];
+
+/// The value of a column for one alignment.
+pub enum Value<'a> {
+    Str(&'a str),
+    Char(char),
+    Int(i64),
+    Float(f64),
+}
+
+impl Column {
+    pub fn from_name(name: &str) -> Option<Column> {
+        COLUMNS
+            .iter()
+            .find(|(column_name, _, _)| *column_name == name)
+            .map(|&(_, column, _)| column)
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, Column::Identity)
+    }
+
+    /// Coordinates are converted to `--coord-base`; everything else is as stored.
+    pub fn value<'a>(self, aln: &'a AlignmentData, output: &OutputConfig) -> Value<'a> {
+        let coord_base = output.coord_base;
+        match self {
+            Column::Index => Value::Int(aln.index as i64),
+            Column::QueryName => Value::Str(&aln.query_name),
+            Column::QueryLength => Value::Int(aln.query_length),
+            Column::QueryStart => Value::Int(coord_base.interval(aln.query_start, aln.query_end).0),
+            Column::QueryEnd => Value::Int(coord_base.interval(aln.query_start, aln.query_end).1),
+            Column::TargetName => Value::Str(&aln.target_name),
+            Column::TargetLength => Value::Int(aln.target_length),
+            Column::TargetStart => Value::Int(coord_base.interval(aln.target_start, aln.target_end).0),
+            Column::TargetEnd => Value::Int(coord_base.interval(aln.target_start, aln.target_end).1),
+            Column::Strand => Value::Char(aln.strand),
+            Column::Differences => Value::Int(aln.differences),
+            Column::Matches => Value::Int(aln.matches()),
+            Column::BlockLength => Value::Int(aln.block_length()),
+            Column::Identity => Value::Float(aln.identity()),
+            Column::Score => Value::Int(output.scoring.score_alignment(aln)),
+            Column::IndelBalance => Value::Int(aln.indel_balance()),
+            Column::TracepointCount => Value::Int(aln.tracepoints.len() as i64),
+        }
+    }
+}
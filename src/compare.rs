@@ -0,0 +1,142 @@
+//! Differences between two alignment files (`--compare`).
+//!
+//! Alignments are matched on their coordinates: query and target names and
+//! intervals. The second file is loaded into a map by that key, then the
+//! input is streamed against it. A matched pair whose strand or difference
+//! count disagrees is reported as changed; unmatched alignments are removed
+//! (input only) or added (second file only). Repeated coordinates are
+//! matched one to one, preferring an identical partner.
+
+use crate::AlignmentData;
+use crate::coords::CoordBase;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// How many examples of each kind the report lists.
+const EXAMPLES_SHOWN: usize = 5;
+
+type Key = (String, i64, i64, String, i64, i64);
+
+struct Record {
+    index: usize,
+    strand: char,
+    differences: i64,
+}
+
+fn key(aln: &AlignmentData) -> Key {
+    (
+        aln.query_name.clone(),
+        aln.query_start,
+        aln.query_end,
+        aln.target_name.clone(),
+        aln.target_start,
+        aln.target_end,
+    )
+}
+
+pub struct Comparison {
+    coord_base: CoordBase,
+    /// Records of the second file not matched yet
+    other: HashMap<Key, Vec<Record>>,
+    unchanged: usize,
+    changed: Vec<(Key, Record, Record)>,
+    removed: Vec<(Key, Record)>,
+}
+
+impl Comparison {
+    pub fn new(coord_base: CoordBase) -> Self {
+        Comparison {
+            coord_base,
+            other: HashMap::new(),
+            unchanged: 0,
+            changed: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    /// Load one alignment of the second file.
+    pub fn add_other(&mut self, aln: &AlignmentData) {
+        self.other.entry(key(aln)).or_default().push(Record {
+            index: aln.index,
+            strand: aln.strand,
+            differences: aln.differences,
+        });
+    }
+
+    /// Match one alignment of the input against the second file.
+    pub fn add(&mut self, aln: &AlignmentData) {
+        let key = key(aln);
+        let record = Record {
+            index: aln.index,
+            strand: aln.strand,
+            differences: aln.differences,
+        };
+        let Some(candidates) = self.other.get_mut(&key) else {
+            self.removed.push((key, record));
+            return;
+        };
+        let same = candidates
+            .iter()
+            .position(|other| other.strand == record.strand && other.differences == record.differences);
+        let other = candidates.remove(same.unwrap_or(0));
+        if candidates.is_empty() {
+            self.other.remove(&key);
+        }
+        if same.is_some() {
+            self.unchanged += 1;
+        } else {
+            self.changed.push((key, record, other));
+        }
+    }
+
+    fn location(&self, key: &Key) -> String {
+        let (query_start, query_end) = self.coord_base.interval(key.1, key.2);
+        let (target_start, target_end) = self.coord_base.interval(key.4, key.5);
+        format!("{}:{}-{} {}:{}-{}", key.0, query_start, query_end, key.3, target_start, target_end)
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W, input: &str, other: &str) -> io::Result<()> {
+        let mut added: Vec<(&Key, &Record)> = self
+            .other
+            .iter()
+            .flat_map(|(key, records)| records.iter().map(move |record| (key, record)))
+            .collect();
+        added.sort_by_key(|(_, record)| record.index);
+
+        writeln!(out, "Comparing {} (input) with {}", input, other)?;
+        writeln!(out, "Unchanged: {}", self.unchanged)?;
+        writeln!(out, "Changed (strand or differences): {}", self.changed.len())?;
+        writeln!(out, "Removed (input only): {}", self.removed.len())?;
+        writeln!(out, "Added ({} only): {}", other, added.len())?;
+
+        if !self.changed.is_empty() {
+            writeln!(out, "\nFirst changed alignments (input index -> other index):")?;
+            for (key, record, other) in self.changed.iter().take(EXAMPLES_SHOWN) {
+                writeln!(
+                    out,
+                    "  #{} -> #{}  {}  strand {} -> {}, differences {} -> {}",
+                    record.index,
+                    other.index,
+                    self.location(key),
+                    record.strand,
+                    other.strand,
+                    record.differences,
+                    other.differences
+                )?;
+            }
+        }
+        if !self.removed.is_empty() {
+            writeln!(out, "\nFirst removed alignments (input index):")?;
+            for (key, record) in self.removed.iter().take(EXAMPLES_SHOWN) {
+                writeln!(out, "  #{}  {}  {}", record.index, self.location(key), record.strand)?;
+            }
+        }
+        if !added.is_empty() {
+            writeln!(out, "\nFirst added alignments (other index):")?;
+            for (key, record) in added.iter().take(EXAMPLES_SHOWN) {
+                writeln!(out, "  #{}  {}  {}", record.index, self.location(key), record.strand)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+//! Default options from a TOML configuration file.
+//!
+//! The file is `--config PATH`, else `.oneview.toml` in the working
+//! directory, else `.oneview.toml` in the home directory; only the first
+//! found is read. Keys are long option names (`gdb-dir` or `gdb_dir`) and
+//! values what the option takes:
+//!
+//! ```toml
+//! paf = true
+//! gdb-dir = ["/data/gdbs", "/scratch/gdbs"]
+//! min-length = 5000
+//! ```
+//!
+//! Each key becomes the equivalent command-line option, placed before the
+//! real command line and left out when the command line gives that option
+//! itself, so the precedence is: command line, configuration file,
+//! `ONEVIEW_DEFAULT_FORMAT`, built-in defaults. Choosing any output format
+//! or report on the command line drops every output format and report from
+//! the file, so they can't conflict. A flag set to `true` in the file can't
+//! be unset from the command line; `--no-config` skips the file instead.
+//!
+//! The `liftover`, `index` and `bench` subcommands read the same file and
+//! take the options they share with the main command, such as `gdb-dir`,
+//! ignoring the others.
+
+use crate::Args;
+use clap::CommandFactory;
+use clap::parser::ValueSource;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = ".oneview.toml";
+
+/// Options choosing the output format or a report; one on the command line replaces all from the file.
+const OUTPUT_FORMATS: &[&str] = &[
+    "paf",
+    "maf",
+    "bedpe",
+    "format",
+    "compat_alntopaf",
+    "metadata",
+    "schema",
+    "pretty",
+    "explain",
+    "extract_fasta",
+    "write_1aln",
+    "interactive",
+    "section_by",
+    "stats",
+    "stats_approx",
+    "stats_pairs",
+    "per_target_counts",
+    "chain_score",
+    "stats_per_target",
+    "stats_per_query",
+    "query_covered_bed",
+    "query_summary",
+    "orientation_stats",
+    "identity_bins",
+    "identity_track",
+    "call_vcf",
+    "target_depth_bedgraph",
+    "depth_hist",
+    "gene_coverage",
+    "validate",
+    "verify_diffs",
+    "compare",
+];
+
+fn config_path(explicit: Option<&str>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(PathBuf::from(path));
+    }
+    let home = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(FILE_NAME));
+    [Some(PathBuf::from(FILE_NAME)), home].into_iter().flatten().find(|path| path.is_file())
+}
+
+/// `cli` (the process arguments) with the configuration file's options
+/// inserted before them, for the main command or a subcommand `C`.
+pub fn args_with_config<C: CommandFactory>(cli: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn std::error::Error>> {
+    let command = C::command();
+    // Errors are reported by the real parse; this one only finds what the command line sets
+    let matches = C::command().ignore_errors(true).try_get_matches_from(&cli)?;
+    // Subcommands have neither option
+    if matches.try_get_one::<bool>("no_config").ok().flatten() == Some(&true) {
+        return Ok(cli);
+    }
+    let explicit = matches.try_get_one::<String>("config").ok().flatten().map(String::as_str);
+    let Some(path) = config_path(explicit) else {
+        return Ok(cli);
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read config {}: {}", path.display(), e))?;
+    let table: toml::Table = text.parse().map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+
+    let on_command_line = |id: &str| matches!(matches.value_source(id), Some(ValueSource::CommandLine));
+    let format_on_command_line = OUTPUT_FORMATS
+        .iter()
+        .any(|id| command.get_arguments().any(|arg| arg.get_id() == id) && on_command_line(id));
+    let main_command = Args::command();
+
+    let mut options: Vec<OsString> = Vec::new();
+    for (key, value) in &table {
+        let long = key.replace('_', "-");
+        let long_is = |arg: &clap::Arg| arg.get_long() == Some(long.as_str());
+        let Some(arg) = command.get_arguments().find(|arg| long_is(arg)) else {
+            // An option of the main command that this subcommand doesn't take
+            if main_command.get_arguments().any(long_is) {
+                continue;
+            }
+            return Err(format!("Unknown option '{}' in config {}", key, path.display()).into());
+        };
+        let id = arg.get_id().as_str();
+        if id == "config" || id == "no_config" {
+            return Err(format!("Config {} cannot choose which config file is read", path.display()).into());
+        }
+        if on_command_line(id) || (format_on_command_line && OUTPUT_FORMATS.contains(&id)) {
+            continue;
+        }
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                // Options like --emit-empty-traces take the boolean as their value
+                toml::Value::Boolean(value) if arg.get_action().takes_values() => Some(value.to_string()),
+                toml::Value::Boolean(true) => None,
+                toml::Value::Boolean(false) => continue,
+                toml::Value::String(value) => Some(value.clone()),
+                toml::Value::Integer(value) => Some(value.to_string()),
+                toml::Value::Float(value) => Some(value.to_string()),
+                _ => return Err(format!("Unsupported value for '{}' in config {}", key, path.display()).into()),
+            };
+            options.push(match value {
+                Some(value) => format!("--{}={}", long, value).into(),
+                None => format!("--{}", long).into(),
+            });
+        }
+    }
+
+    let mut args = cli;
+    let rest = args.split_off(args.len().min(1));
+    args.extend(options);
+    args.extend(rest);
+    Ok(args)
+}
@@ -0,0 +1,36 @@
+//! Coordinate conventions for user-facing output.
+//!
+//! Internally every interval is 0-based and half-open, which is also what
+//! PAF requires. Formats that follow `--coord-base` convert through
+//! [`CoordBase::interval`] so they can't drift apart.
+
+use clap::ValueEnum;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum CoordBase {
+    /// 0-based, half-open [start, end)
+    #[default]
+    #[value(name = "0")]
+    Zero,
+    /// 1-based, end-inclusive [start, end]
+    #[value(name = "1")]
+    One,
+}
+
+impl CoordBase {
+    /// Convert an internal 0-based half-open interval to this convention.
+    pub fn interval(self, start: i64, end: i64) -> (i64, i64) {
+        match self {
+            CoordBase::Zero => (start, end),
+            CoordBase::One => (start + 1, end),
+        }
+    }
+
+    /// Convert an interval given in this convention to 0-based half-open.
+    pub fn to_internal(self, start: i64, end: i64) -> (i64, i64) {
+        match self {
+            CoordBase::Zero => (start, end),
+            CoordBase::One => (start - 1, end),
+        }
+    }
+}
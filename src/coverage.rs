@@ -0,0 +1,112 @@
+//! Per-feature coverage by alignments (`--gene-coverage`).
+//!
+//! Alignments are streamed once; each one records its overlap with every
+//! feature it touches. At the end the overlaps of a feature are merged into
+//! the bases covered by any alignment, and the layout says how they were
+//! covered:
+//!
+//! * `none`: no alignment reaches the feature;
+//! * `contiguous`: one alignment covers every covered base;
+//! * `split-alignments`: it takes several alignments, all from one query sequence;
+//! * `split-queries`: it takes alignments from several query sequences.
+
+use crate::AlignmentData;
+use crate::gff::FeatureIndex;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+struct Overlap {
+    start: i64,
+    end: i64,
+    identity: f64,
+    query: String,
+}
+
+pub struct GeneCoverage {
+    features: FeatureIndex,
+    /// Overlaps by feature ID
+    overlaps: HashMap<usize, Vec<Overlap>>,
+}
+
+impl GeneCoverage {
+    pub fn new(features: FeatureIndex) -> Self {
+        GeneCoverage {
+            features,
+            overlaps: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, aln: &AlignmentData, query: &str, target: &str) {
+        let identity = aln.identity();
+        for feature in self.features.overlapping_features(target, aln.target_start, aln.target_end) {
+            self.overlaps.entry(feature.id).or_default().push(Overlap {
+                start: feature.start.max(aln.target_start),
+                end: feature.end.min(aln.target_end),
+                identity,
+                query: query.to_string(),
+            });
+        }
+    }
+
+    pub fn write<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "#feature\tsequence\tstart\tend\tcovered_bp\tcovered_fraction\tweighted_identity\talignments\tquery_sequences\tlayout"
+        )?;
+        for (sequence, feature) in self.features.features() {
+            let mut overlaps = self.overlaps.remove(&feature.id).unwrap_or_default();
+            overlaps.sort_by_key(|overlap| (overlap.start, overlap.end));
+
+            // Union of the overlaps
+            let mut covered = 0;
+            let mut run: Option<(i64, i64)> = None;
+            for overlap in &overlaps {
+                run = match run {
+                    Some((start, end)) if overlap.start <= end => Some((start, end.max(overlap.end))),
+                    Some((start, end)) => {
+                        covered += end - start;
+                        Some((overlap.start, overlap.end))
+                    }
+                    None => Some((overlap.start, overlap.end)),
+                };
+            }
+            if let Some((start, end)) = run {
+                covered += end - start;
+            }
+
+            let overlap_bp: i64 = overlaps.iter().map(|overlap| overlap.end - overlap.start).sum();
+            let weighted_identity = if overlap_bp > 0 {
+                overlaps.iter().map(|overlap| overlap.identity * (overlap.end - overlap.start) as f64).sum::<f64>()
+                    / overlap_bp as f64
+            } else {
+                0.0
+            };
+            let queries: HashSet<&str> = overlaps.iter().map(|overlap| overlap.query.as_str()).collect();
+            let layout = if overlaps.is_empty() {
+                "none"
+            } else if overlaps.iter().any(|overlap| overlap.end - overlap.start == covered) {
+                "contiguous"
+            } else if queries.len() > 1 {
+                "split-queries"
+            } else {
+                "split-alignments"
+            };
+
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{:.6}\t{:.6}\t{}\t{}\t{}",
+                feature.label,
+                sequence,
+                feature.start,
+                feature.end,
+                covered,
+                covered as f64 / (feature.end - feature.start) as f64,
+                weighted_identity,
+                overlaps.len(),
+                queries.len(),
+                layout
+            )?;
+        }
+        Ok(())
+    }
+}
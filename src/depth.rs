@@ -0,0 +1,83 @@
+//! Per-base alignment depth along the target, as bedGraph
+//! (`--target-depth-bedgraph`), or as a histogram (`--depth-hist`).
+//!
+//! Each alignment contributes a +1 event at its target start and a -1 at its
+//! target end, whatever its strand. Sweeping the sorted events of a sequence
+//! gives runs of constant depth; runs of depth 0 are left out.
+//!
+//! `--depth-hist` sums the same runs, along the target or the query, into
+//! bases per depth, with depth 0 taken from the sequence lengths.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+#[derive(Default)]
+pub struct DepthTrack {
+    events: BTreeMap<String, Vec<(i64, i64)>>,
+}
+
+impl DepthTrack {
+    pub fn add(&mut self, name: &str, start: i64, end: i64) {
+        if start >= end {
+            return;
+        }
+        let events = self.events.entry(name.to_string()).or_default();
+        events.push((start, 1));
+        events.push((end, -1));
+    }
+
+    /// Visit the runs of constant non-zero depth, sequence by sequence.
+    fn for_each_run<F>(&mut self, mut visit: F) -> io::Result<()>
+    where
+        F: FnMut(&str, i64, i64, i64) -> io::Result<()>,
+    {
+        for (name, events) in &mut self.events {
+            events.sort_unstable();
+            let mut depth = 0;
+            let mut run_start = 0;
+            let mut i = 0;
+            while i < events.len() {
+                let pos = events[i].0;
+                let mut next_depth = depth;
+                while i < events.len() && events[i].0 == pos {
+                    next_depth += events[i].1;
+                    i += 1;
+                }
+                // Events cancelling out at a position don't break the run
+                if next_depth != depth {
+                    if depth > 0 && pos > run_start {
+                        visit(name, run_start, pos, depth)?;
+                    }
+                    run_start = pos;
+                    depth = next_depth;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        self.for_each_run(|name, start, end, depth| writeln!(out, "{}\t{}\t{}\t{}", name, start, end, depth))
+    }
+
+    /// Bases at each depth from 0 to `max_depth`, the last counting every
+    /// depth at or above it, for every sequence of `lengths` and any other
+    /// sequence with alignments. Depth 0 is what the runs leave of a
+    /// sequence's length, so it needs the length to be known.
+    pub fn histogram(&mut self, lengths: &HashMap<String, i64>, max_depth: usize) -> io::Result<BTreeMap<String, Vec<i64>>> {
+        let mut histogram: BTreeMap<String, Vec<i64>> = lengths
+            .keys()
+            .map(|name| (name.clone(), vec![0; max_depth + 1]))
+            .collect();
+        self.for_each_run(|name, start, end, depth| {
+            let bins = histogram.entry(name.to_string()).or_insert_with(|| vec![0; max_depth + 1]);
+            bins[(depth as usize).min(max_depth)] += end - start;
+            Ok(())
+        })?;
+        for (name, bins) in &mut histogram {
+            let covered: i64 = bins.iter().sum();
+            bins[0] = (lengths.get(name).copied().unwrap_or(0) - covered).max(0);
+        }
+        Ok(histogram)
+    }
+}
@@ -0,0 +1,132 @@
+//! `--explain`: how one stored record becomes the reported alignment.
+//!
+//! Everything shown comes from what `parse_alignment` read: the A line as
+//! stored, the metadata entries it looked up, and the arithmetic that turns
+//! contig coordinates into scaffold coordinates.
+
+use crate::{AlignmentData, OutputConfig, print_alignment_paf};
+use std::io::{self, Write};
+
+/// The stored form of a record, kept only when `--explain` asks for it.
+#[derive(Debug, Clone)]
+pub struct RawRecord {
+    /// A line fields: query ID, start, end, target ID, start, end
+    pub a_line: [i64; 6],
+    /// `(offset in scaffold, contig length)` looked up for each side
+    pub query_contig: (i64, i64),
+    pub target_contig: (i64, i64),
+    /// The record's L line, if any
+    pub record_lengths: Option<(i64, i64)>,
+    /// Whether the target interval was flipped out of the reverse-complement frame
+    pub target_flipped: bool,
+}
+
+const PAF_COLUMNS: [&str; 12] = [
+    "query name",
+    "query length",
+    "query start",
+    "query end",
+    "strand",
+    "target name",
+    "target length",
+    "target start",
+    "target end",
+    "matches",
+    "block length",
+    "mapping quality",
+];
+
+pub fn write(out: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    let Some(raw) = &aln.raw else {
+        return writeln!(out, "No stored record was kept for alignment {}", aln.index);
+    };
+    let [query_id, query_start, query_end, target_id, target_start, target_end] = raw.a_line;
+    let (query_offset, query_contig_len) = raw.query_contig;
+    let (target_offset, target_contig_len) = raw.target_contig;
+
+    writeln!(out, "=== EXPLAIN: alignment {} ===\n", aln.index)?;
+    writeln!(out, "Stored A line (contig coordinates, 0-based half-open):")?;
+    writeln!(out, "  query:  ID {}, {}-{}", query_id, query_start, query_end)?;
+    writeln!(out, "  target: ID {}, {}-{}", target_id, target_start, target_end)?;
+    match aln.strand {
+        '-' => writeln!(out, "  R line present: reverse strand; target coordinates are on the reverse complement")?,
+        _ => writeln!(out, "  no R line: forward strand")?,
+    }
+    match raw.record_lengths {
+        Some((query_len, target_len)) => writeln!(out, "  L line: query length {}, target length {}", query_len, target_len)?,
+        None => writeln!(out, "  no L line")?,
+    }
+
+    writeln!(out, "\nMetadata lookups:")?;
+    if aln.anonymous {
+        writeln!(out, "  (no metadata for at least one side: placeholder names, offset 0, length unknown)")?;
+    }
+    writeln!(
+        out,
+        "  query ID {} -> {} (length {}), contig at offset {}, contig length {}",
+        query_id, aln.query_name, aln.query_length, query_offset, query_contig_len
+    )?;
+    writeln!(
+        out,
+        "  target ID {} -> {} (length {}), contig at offset {}, contig length {}",
+        target_id, aln.target_name, aln.target_length, target_offset, target_contig_len
+    )?;
+
+    let (mut forward_start, mut forward_end) = (target_start, target_end);
+    if raw.target_flipped {
+        forward_start = target_contig_len - target_end;
+        forward_end = target_contig_len - target_start;
+        writeln!(out, "\nStrand flip (target contig length {}):", target_contig_len)?;
+        writeln!(
+            out,
+            "  start = {} - {} = {}",
+            target_contig_len, target_end, forward_start
+        )?;
+        writeln!(
+            out,
+            "  end   = {} - {} = {}",
+            target_contig_len, target_start, forward_end
+        )?;
+    }
+
+    writeln!(out, "\nScaffold coordinates (contig offset + contig coordinate):")?;
+    writeln!(
+        out,
+        "  query:  {} + {} = {}, {} + {} = {}",
+        query_offset,
+        query_start,
+        query_offset + query_start,
+        query_offset,
+        query_end,
+        query_offset + query_end
+    )?;
+    writeln!(
+        out,
+        "  target: {} + {} = {}, {} + {} = {}",
+        target_offset,
+        forward_start,
+        target_offset + forward_start,
+        target_offset,
+        forward_end,
+        target_offset + forward_end
+    )?;
+    if (aln.query_start, aln.query_end, aln.target_start, aln.target_end)
+        != (query_offset + query_start, query_offset + query_end, target_offset + forward_start, target_offset + forward_end)
+    {
+        writeln!(
+            out,
+            "  later adjusted (--trim-ends) to query {}-{}, target {}-{}",
+            aln.query_start, aln.query_end, aln.target_start, aln.target_end
+        )?;
+    }
+
+    let mut paf = Vec::new();
+    print_alignment_paf(&mut paf, aln, output)?;
+    let paf = String::from_utf8_lossy(&paf);
+    writeln!(out, "\nPAF line:")?;
+    for (i, value) in paf.trim_end().split('\t').enumerate() {
+        let label = PAF_COLUMNS.get(i).copied().unwrap_or("tag");
+        writeln!(out, "  {:>2}  {:<16} {}", i + 1, label, value)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Sequence metadata from a samtools `.fai` index, for genomes whose GDB is
+//! unavailable (`--query-fai`/`--target-fai`).
+//!
+//! Alignment records refer to sequences by GDB contig ID. A `.fai` knows
+//! nothing of contigs, so each of its sequences is taken to be a single
+//! contig covering it (offset 0). IDs are matched to sequences either
+//!
+//! * by order: the sequence on line N of the `.fai` (0-based) has ID N, or
+//! * through an ID map, a two-column TSV of `name<TAB>id`; sequences the map
+//!   does not mention get no ID.
+//!
+//! Both rules are only correct when FastGA did not split any sequence at
+//! runs of N; otherwise IDs and coordinates need the real GDB.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/// Names, lengths and `(offset, contig length)` by contig ID, as loaded from a GDB.
+pub type SequenceTables = (HashMap<i64, String>, HashMap<i64, i64>, HashMap<i64, (i64, i64)>);
+
+/// Fallback tables for each side, used only when no GDB or skeleton provides one.
+#[derive(Debug, Default)]
+pub struct FaiMetadata {
+    pub query: Option<SequenceTables>,
+    pub target: Option<SequenceTables>,
+}
+
+/// `(name, length)` for every line of a `.fai`, in file order.
+fn read_lengths(path: &str) -> Result<Vec<(String, i64)>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read FASTA index {}: {}", path, e))?;
+    let mut sequences = Vec::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let name = fields.next().unwrap_or_default();
+        let length = fields.next().and_then(|field| field.parse::<i64>().ok());
+        match length {
+            Some(length) if !name.is_empty() && length >= 0 => sequences.push((name.to_string(), length)),
+            _ => return Err(format!("{}:{}: malformed .fai line", path, line_idx + 1).into()),
+        }
+    }
+    Ok(sequences)
+}
+
+fn read_id_map(path: &str) -> Result<HashMap<String, i64>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read ID map {}: {}", path, e))?;
+    let mut map = HashMap::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let id = match fields.as_slice() {
+            [name, id] if !name.is_empty() => id.parse::<i64>().ok().filter(|&id| id >= 0),
+            _ => None,
+        };
+        let Some(id) = id else {
+            return Err(format!("{}:{}: expected two tab-separated columns (name, ID)", path, line_idx + 1).into());
+        };
+        if map.insert(fields[0].to_string(), id).is_some() {
+            return Err(format!("{}:{}: {} is listed more than once", path, line_idx + 1, fields[0]).into());
+        }
+    }
+    Ok(map)
+}
+
+/// Build metadata tables from the `.fai` at `path`, numbering sequences by
+/// order unless `id_map` is given.
+pub fn read_tables(path: &str, id_map: Option<&str>) -> Result<SequenceTables, Box<dyn std::error::Error>> {
+    let sequences = read_lengths(path)?;
+    let ids: Vec<Option<i64>> = match id_map {
+        Some(map_path) => {
+            let map = read_id_map(map_path)?;
+            let known: HashSet<&str> = sequences.iter().map(|(name, _)| name.as_str()).collect();
+            if let Some(name) = map.keys().find(|name| !known.contains(name.as_str())) {
+                return Err(format!("ID map {} names {}, which is not in {}", map_path, name, path).into());
+            }
+            sequences.iter().map(|(name, _)| map.get(name).copied()).collect()
+        }
+        None => (0..sequences.len() as i64).map(Some).collect(),
+    };
+
+    let (mut names, mut lengths, mut offsets) = (HashMap::new(), HashMap::new(), HashMap::new());
+    for ((name, length), id) in sequences.into_iter().zip(ids) {
+        let Some(id) = id else { continue };
+        if let Some(previous) = names.insert(id, name.clone()) {
+            return Err(format!("Sequences {} and {} in {} have the same ID {}", previous, name, path, id).into());
+        }
+        lengths.insert(id, length);
+        offsets.insert(id, (0, length));
+    }
+    Ok((names, lengths, offsets))
+}
@@ -0,0 +1,263 @@
+//! Random access to FASTA files through a samtools-style `.fai` index.
+//!
+//! Nothing is read until the first fetch: the index is then loaded from
+//! `PATH.fai`, or built by scanning the FASTA once when no index exists.
+//! Each fetch then seeks to just the bases it needs, unless the genome was
+//! preloaded into memory. Compressed FASTA is not supported.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+#[derive(Debug, Clone)]
+struct FaiRecord {
+    length: i64,
+    offset: u64,
+    line_bases: u64,
+    line_width: u64,
+}
+
+struct Loaded {
+    index: HashMap<String, FaiRecord>,
+    file: File,
+    /// Every sequence by index name, once `preload` has run
+    bases: Option<HashMap<String, Vec<u8>>>,
+}
+
+pub struct IndexedFasta {
+    path: String,
+    loaded: RefCell<Option<Loaded>>,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_fai(path: &str) -> io::Result<HashMap<String, FaiRecord>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut index = HashMap::new();
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        let parse = |i: usize| -> io::Result<u64> {
+            fields
+                .get(i)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| invalid_data(format!("{}:{}: malformed .fai line", path, line_idx + 1)))
+        };
+        let record = FaiRecord {
+            length: parse(1)? as i64,
+            offset: parse(2)?,
+            line_bases: parse(3)?,
+            line_width: parse(4)?,
+        };
+        index.insert(fields[0].to_string(), record);
+    }
+    Ok(index)
+}
+
+/// Build the equivalent of a `.fai` by scanning the FASTA.
+fn build_fai(path: &str) -> io::Result<HashMap<String, FaiRecord>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut index = HashMap::new();
+    let mut current: Option<(String, FaiRecord)> = None;
+    let mut offset = 0u64;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)? as u64;
+        if read == 0 {
+            break;
+        }
+        if line[0] == b'>' {
+            if let Some((name, record)) = current.take() {
+                index.insert(name, record);
+            }
+            let header = String::from_utf8_lossy(&line[1..]);
+            let name = header.split_whitespace().next().unwrap_or("").to_string();
+            current = Some((
+                name,
+                FaiRecord {
+                    length: 0,
+                    offset: offset + read,
+                    line_bases: 0,
+                    line_width: 0,
+                },
+            ));
+        } else if let Some((_, record)) = current.as_mut() {
+            let bases = line
+                .iter()
+                .filter(|&&b| b != b'\n' && b != b'\r')
+                .count() as u64;
+            if record.line_bases == 0 {
+                record.line_bases = bases;
+                record.line_width = read;
+            }
+            record.length += bases as i64;
+        }
+        offset += read;
+    }
+    if let Some((name, record)) = current {
+        index.insert(name, record);
+    }
+    Ok(index)
+}
+
+/// Bases `[start, end)` of `record`, without line breaks.
+fn read_bases(file: &mut File, record: &FaiRecord, start: i64, end: i64) -> io::Result<Vec<u8>> {
+    if start == end {
+        return Ok(Vec::new());
+    }
+    let byte_offset = |pos: u64| {
+        record.offset + (pos / record.line_bases) * record.line_width + pos % record.line_bases
+    };
+    let first = byte_offset(start as u64);
+    let last = byte_offset(end as u64 - 1) + 1;
+
+    let mut raw = vec![0u8; (last - first) as usize];
+    file.seek(SeekFrom::Start(first))?;
+    file.read_exact(&mut raw)?;
+    raw.retain(|&b| b != b'\n' && b != b'\r');
+    Ok(raw)
+}
+
+pub fn reverse_complement(seq: &str) -> String {
+    seq.bytes()
+        .rev()
+        .map(|b| match b {
+            b'A' => 'T',
+            b'C' => 'G',
+            b'G' => 'C',
+            b'T' => 'A',
+            b'a' => 't',
+            b'c' => 'g',
+            b'g' => 'c',
+            b't' => 'a',
+            other => other as char,
+        })
+        .collect()
+}
+
+impl IndexedFasta {
+    /// Remember `path`; the file is only touched on the first fetch.
+    pub fn new(path: &str) -> Self {
+        IndexedFasta {
+            path: path.to_string(),
+            loaded: RefCell::new(None),
+        }
+    }
+
+    fn load(&self) -> io::Result<Loaded> {
+        if self.path.ends_with(".gz") {
+            return Err(invalid_data(format!(
+                "Compressed FASTA is not supported: {}",
+                self.path
+            )));
+        }
+        let fai_path = format!("{}.fai", self.path);
+        let index = if std::path::Path::new(&fai_path).exists() {
+            read_fai(&fai_path)?
+        } else {
+            eprintln!("No index at {}; scanning {} to build one in memory", fai_path, self.path);
+            build_fai(&self.path)?
+        };
+        Ok(Loaded {
+            index,
+            file: File::open(&self.path)?,
+            bases: None,
+        })
+    }
+
+    /// Read the whole genome into memory, so fetches no longer touch the file.
+    pub fn preload(&self) -> io::Result<()> {
+        let mut loaded = self.loaded.borrow_mut();
+        if loaded.is_none() {
+            *loaded = Some(self.load()?);
+        }
+        let Loaded { index, file, bases } = loaded.as_mut().expect("FASTA index loaded above");
+        if bases.is_none() {
+            let mut all = HashMap::with_capacity(index.len());
+            for (name, record) in index.iter() {
+                all.insert(name.clone(), read_bases(file, record, 0, record.length)?);
+            }
+            *bases = Some(all);
+        }
+        Ok(())
+    }
+
+    /// Bases `[start, end)` of sequence `name` on the forward strand,
+    /// reverse-complemented when `strand` is '-'. Names are matched exactly
+    /// first, then by their first word (GDBs keep full FASTA headers).
+    pub fn fetch_subseq(&self, name: &str, start: i64, end: i64, strand: char) -> io::Result<String> {
+        if start == end {
+            return Ok(String::new());
+        }
+        let mut loaded = self.loaded.borrow_mut();
+        if loaded.is_none() {
+            *loaded = Some(self.load()?);
+        }
+        let Loaded { index, file, bases } = loaded.as_mut().expect("FASTA index loaded above");
+
+        let (name, record) = index
+            .get_key_value(name)
+            .or_else(|| name.split_whitespace().next().and_then(|word| index.get_key_value(word)))
+            .ok_or_else(|| invalid_data(format!("Sequence {} not found in {}", name, self.path)))?;
+        if start < 0 || end > record.length || start > end || record.line_bases == 0 {
+            return Err(invalid_data(format!(
+                "Interval {}-{} is outside sequence {} (length {})",
+                start, end, name, record.length
+            )));
+        }
+
+        let raw = match bases.as_ref().and_then(|bases| bases.get(name)) {
+            Some(sequence) => sequence[start as usize..end as usize].to_vec(),
+            None => read_bases(file, record, start, end)?,
+        };
+        let seq: String = raw.into_iter().map(char::from).collect();
+
+        Ok(if strand == '-' {
+            reverse_complement(&seq)
+        } else {
+            seq
+        })
+    }
+}
+
+/// A FASTA for one side of the alignments, looked up by metadata sequence ID.
+///
+/// Names are captured before any renaming, so lookups keep working with
+/// `--rename-*` and prefix options.
+pub struct GenomeFasta {
+    fasta: IndexedFasta,
+    names: HashMap<i64, String>,
+}
+
+impl GenomeFasta {
+    pub fn new(path: &str, names: HashMap<i64, String>) -> Self {
+        GenomeFasta {
+            fasta: IndexedFasta::new(path),
+            names,
+        }
+    }
+
+    pub fn preload(&self) -> io::Result<()> {
+        self.fasta.preload()
+    }
+
+    pub fn fetch(&self, seq_id: i64, start: i64, end: i64, strand: char) -> io::Result<String> {
+        let name = self
+            .names
+            .get(&seq_id)
+            .ok_or_else(|| invalid_data(format!("Sequence ID {} not found in metadata", seq_id)))?;
+        self.fasta.fetch_subseq(name, start, end, strand)
+    }
+}
+
+/// Optional FASTA inputs for formats that can use real bases.
+#[derive(Default)]
+pub struct Sequences {
+    pub query: Option<GenomeFasta>,
+    pub target: Option<GenomeFasta>,
+}
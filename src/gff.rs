@@ -0,0 +1,153 @@
+//! Target features from a GFF3 file, for `--annotate` and `--gene-coverage`.
+//!
+//! Features of one type are kept per sequence, sorted by start, with the
+//! running maximum of their ends, so the features overlapping an alignment
+//! are found by a binary search and a short backward scan instead of a walk
+//! over the whole file.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+pub struct Feature {
+    /// Position in the file among the loaded features
+    pub id: usize,
+    /// 0-based half-open
+    pub start: i64,
+    pub end: i64,
+    /// The ID attribute, else Name, else the location
+    pub label: String,
+}
+
+#[derive(Default)]
+struct SequenceFeatures {
+    features: Vec<Feature>,
+    max_end: Vec<i64>,
+}
+
+pub struct FeatureIndex {
+    sequences: HashMap<String, SequenceFeatures>,
+}
+
+fn attribute<'a>(attributes: &'a str, key: &str) -> Option<&'a str> {
+    attributes
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+impl FeatureIndex {
+    /// Load the features of `feature_type` from the GFF3 at `path`.
+    pub fn read(path: &str, feature_type: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path).map_err(|e| format!("Cannot read GFF {}: {}", path, e))?;
+        let mut sequences: HashMap<String, SequenceFeatures> = HashMap::new();
+        let mut count = 0;
+        for (line_idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            // Sequences may follow the annotation; they hold no features
+            if line.starts_with("##FASTA") {
+                break;
+            }
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 9 {
+                return Err(format!("{}:{}: expected 9 tab-separated GFF3 columns", path, line_idx + 1).into());
+            }
+            if fields[2] != feature_type {
+                continue;
+            }
+            let (Ok(start), Ok(end)) = (fields[3].parse::<i64>(), fields[4].parse::<i64>()) else {
+                return Err(format!("{}:{}: invalid start or end", path, line_idx + 1).into());
+            };
+            if start < 1 || end < start {
+                return Err(format!("{}:{}: invalid interval {}-{}", path, line_idx + 1, start, end).into());
+            }
+            let label = attribute(fields[8], "ID")
+                .or_else(|| attribute(fields[8], "Name"))
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{}:{}-{}", fields[0], start, end));
+            sequences.entry(fields[0].to_string()).or_default().features.push(Feature {
+                id: count,
+                start: start - 1,
+                end,
+                label,
+            });
+            count += 1;
+        }
+
+        for sequence in sequences.values_mut() {
+            sequence.features.sort_by_key(|feature| (feature.start, feature.end));
+            let mut max_end = i64::MIN;
+            sequence.max_end = sequence
+                .features
+                .iter()
+                .map(|feature| {
+                    max_end = max_end.max(feature.end);
+                    max_end
+                })
+                .collect();
+        }
+        Ok(FeatureIndex { sequences })
+    }
+
+    pub fn feature_count(&self) -> usize {
+        self.sequences.values().map(|sequence| sequence.features.len()).sum()
+    }
+
+    /// Warn about GFF sequences that none of `names` matches.
+    pub fn report_unknown_sequences<'a>(&self, names: impl Iterator<Item = &'a String>) {
+        let known: std::collections::HashSet<&String> = names.collect();
+        let mut unknown: Vec<&String> = self.sequences.keys().filter(|seqid| !known.contains(seqid)).collect();
+        if unknown.is_empty() {
+            return;
+        }
+        unknown.sort();
+        eprintln!(
+            "Warning: {} GFF sequence(s) match no target sequence name and will never be reported, e.g. {}",
+            unknown.len(),
+            unknown.iter().take(5).map(|seqid| seqid.as_str()).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    /// Every feature with its sequence, in file order.
+    pub fn features(&self) -> Vec<(&str, &Feature)> {
+        let mut features: Vec<(&str, &Feature)> = self
+            .sequences
+            .iter()
+            .flat_map(|(name, sequence)| sequence.features.iter().map(move |feature| (name.as_str(), feature)))
+            .collect();
+        features.sort_by_key(|(_, feature)| feature.id);
+        features
+    }
+
+    /// Features on `sequence` overlapping `[start, end)`, in start order.
+    pub fn overlapping_features(&self, sequence: &str, start: i64, end: i64) -> Vec<&Feature> {
+        let Some(sequence) = self.sequences.get(sequence) else {
+            return Vec::new();
+        };
+        let last = sequence.features.partition_point(|feature| feature.start < end);
+        let mut hits: Vec<&Feature> = (0..last)
+            .rev()
+            .take_while(|&i| sequence.max_end[i] > start)
+            .map(|i| &sequence.features[i])
+            .filter(|feature| feature.end > start)
+            .collect();
+        hits.reverse();
+        hits
+    }
+
+    /// [`Self::overlapping_features`] by label, each with the fraction of it
+    /// the interval covers.
+    pub fn overlapping(&self, sequence: &str, start: i64, end: i64) -> Vec<(&str, f64)> {
+        self.overlapping_features(sequence, start, end)
+            .into_iter()
+            .map(|feature| {
+                let covered = feature.end.min(end) - feature.start.max(start);
+                (feature.label.as_str(), covered as f64 / (feature.end - feature.start) as f64)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,335 @@
+//! The terminal viewer (`--interactive`).
+//!
+//! A list of alignments, one line each, that can be scrolled and narrowed by
+//! typing part of a sequence name; Enter opens the selected alignment in the
+//! human view, followed by the base-level view when `--fasta` gives both
+//! genomes. Records are read through the binary index as the list needs
+//! them: the viewer remembers which alignments match the current filter up
+//! to the furthest point scrolled to, and caches the lines it has shown, so
+//! it never reads the whole file unless asked to jump to the end.
+//!
+//! Keys: arrows or j/k to move, PgUp/PgDn, Home/End (g/G), `/` to type a
+//! filter (Enter or Esc to stop typing), Enter to open, q or Esc to go back
+//! or quit.
+
+use crate::pretty::{self, PrettyOptions};
+use crate::{AlignmentData, AlignmentFilter, OneFile, OutputConfig, ParseContext, align, open_seekable, visit_objects};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Cached list lines beyond this are dropped and read again when needed.
+const CACHE_LIMIT: usize = 10_000;
+
+/// Raw mode and the alternate screen, undone on drop so the terminal is
+/// restored however the viewer exits.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        if let Err(e) = execute!(io::stdout(), EnterAlternateScreen, Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(e);
+        }
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// What the list shows of one alignment.
+struct Row {
+    /// Both sequence names, lowercased, for the typed filter
+    names: String,
+    text: String,
+}
+
+struct Viewer<'c, 'a> {
+    file: OneFile,
+    count: i64,
+    ctx: &'c mut ParseContext<'a>,
+    filter: &'c AlignmentFilter,
+    output: &'c OutputConfig,
+    /// Typed filter, matched case-insensitively against both sequence names
+    pattern: String,
+    typing: bool,
+    /// Alignments matching `pattern` and the command-line filters, in file order,
+    /// up to `scanned`
+    matches: Vec<i64>,
+    scanned: i64,
+    rows: HashMap<i64, Option<Row>>,
+    selected: usize,
+    top: usize,
+}
+
+fn summary(aln: &AlignmentData) -> String {
+    format!(
+        "{:>9}  {}:{}-{}  {}  {}:{}-{}  {:.4}",
+        aln.index,
+        aln.query_name,
+        aln.query_start,
+        aln.query_end,
+        aln.strand,
+        aln.target_name,
+        aln.target_start,
+        aln.target_end,
+        aln.identity()
+    )
+}
+
+impl Viewer<'_, '_> {
+    /// Read alignment `idx` with its traces when `traces` is set.
+    fn read(&mut self, idx: i64, traces: bool) -> Result<Option<AlignmentData>, Box<dyn std::error::Error>> {
+        let read_traces = self.ctx.read_traces;
+        self.ctx.read_traces = traces;
+        let mut found = None;
+        let result = visit_objects(&mut self.file, self.ctx, [idx], |aln| {
+            found = Some(aln);
+            Ok(())
+        });
+        self.ctx.read_traces = read_traces;
+        result?;
+        Ok(found.filter(|aln| self.filter.accepts(aln)))
+    }
+
+    fn row(&mut self, idx: i64) -> Result<Option<&Row>, Box<dyn std::error::Error>> {
+        if !self.rows.contains_key(&idx) {
+            if self.rows.len() >= CACHE_LIMIT {
+                self.rows.clear();
+            }
+            let row = self.read(idx, false)?.map(|aln| Row {
+                names: format!("{}\t{}", aln.query_name, aln.target_name).to_lowercase(),
+                text: summary(&aln),
+            });
+            self.rows.insert(idx, row);
+        }
+        Ok(self.rows[&idx].as_ref())
+    }
+
+    /// Extend `matches` until it holds `wanted` entries or the file ends.
+    fn scan_to(&mut self, wanted: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let pattern = self.pattern.to_lowercase();
+        while self.matches.len() < wanted && self.scanned < self.count {
+            let idx = self.scanned;
+            self.scanned += 1;
+            if self.row(idx)?.is_some_and(|row| row.names.contains(&pattern)) {
+                self.matches.push(idx);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_pattern(&mut self, pattern: String) {
+        self.pattern = pattern;
+        self.matches.clear();
+        self.scanned = 0;
+        self.selected = 0;
+        self.top = 0;
+    }
+
+    fn draw_list(&mut self, out: &mut impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        let (width, height) = terminal::size()?;
+        let (width, lines) = (width as usize, (height as usize).saturating_sub(1).max(1));
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + lines {
+            self.top = self.selected + 1 - lines;
+        }
+        self.scan_to(self.top + lines)?;
+
+        queue!(out, Clear(ClearType::All))?;
+        for line in 0..lines {
+            let Some(&idx) = self.matches.get(self.top + line) else {
+                break;
+            };
+            let text = self.row(idx)?.map(|row| row.text.clone()).unwrap_or_default();
+            queue!(out, MoveTo(0, line as u16))?;
+            if self.top + line == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(out, Print(text.chars().take(width).collect::<String>()), SetAttribute(Attribute::Reset))?;
+        }
+        let total = if self.scanned < self.count {
+            format!("{}+", self.matches.len())
+        } else {
+            self.matches.len().to_string()
+        };
+        let status = if self.typing {
+            format!("/{}", self.pattern)
+        } else {
+            format!(
+                " {} of {} alignments{}  (/ filter, Enter open, q quit)",
+                total,
+                self.count,
+                if self.pattern.is_empty() { String::new() } else { format!(" matching '{}'", self.pattern) }
+            )
+        };
+        queue!(
+            out,
+            MoveTo(0, lines as u16),
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{:<width$}", status, width = width).chars().take(width).collect::<String>()),
+            SetAttribute(Attribute::Reset)
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// The human view of alignment `idx`, then the base-level view when the
+    /// sequences are available.
+    fn detail(&mut self, idx: i64) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let Some(aln) = self.read(idx, true)? else {
+            return Ok(vec![format!("Alignment {} could not be read", idx)]);
+        };
+        let mut text: Vec<u8> = Vec::new();
+        crate::print_alignment_human(&mut text, &aln, self.output)?;
+        if let Some((query, target, ops)) = align::reconstruct_with_bases(&aln, &self.output.sequences)? {
+            let options = PrettyOptions {
+                width: (terminal::size()?.0 as usize).saturating_sub(30).max(10),
+                region: None,
+                color: false,
+                coord_base: self.output.coord_base,
+            };
+            pretty::render(&mut text, &aln, query.as_bytes(), target.as_bytes(), &ops, &options)?;
+        }
+        Ok(String::from_utf8_lossy(&text).lines().map(str::to_string).collect())
+    }
+
+    fn show_detail(&mut self, out: &mut impl Write, idx: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let lines = self.detail(idx)?;
+        let mut top = 0;
+        loop {
+            let (width, height) = terminal::size()?;
+            let (width, height) = (width as usize, (height as usize).saturating_sub(1).max(1));
+            top = top.min(lines.len().saturating_sub(height));
+            queue!(out, Clear(ClearType::All))?;
+            for (row, line) in lines.iter().skip(top).take(height).enumerate() {
+                queue!(out, MoveTo(0, row as u16), Print(line.chars().take(width).collect::<String>()))?;
+            }
+            let status = format!(" Alignment {}  (arrows scroll, q back)", idx);
+            queue!(
+                out,
+                MoveTo(0, height as u16),
+                SetAttribute(Attribute::Reverse),
+                Print(format!("{:<width$}", status, width = width).chars().take(width).collect::<String>()),
+                SetAttribute(Attribute::Reset)
+            )?;
+            out.flush()?;
+
+            let Some(key) = next_key()? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => top = top.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => top += 1,
+                KeyCode::PageUp => top = top.saturating_sub(height),
+                KeyCode::PageDown | KeyCode::Char(' ') => top += height,
+                KeyCode::Home | KeyCode::Char('g') => top = 0,
+                KeyCode::End | KeyCode::Char('G') => top = lines.len(),
+                _ => {}
+            }
+        }
+    }
+
+    fn run(&mut self, out: &mut impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            self.draw_list(out)?;
+            let Some(key) = next_key()? else {
+                continue;
+            };
+            let page = (terminal::size()?.1 as usize).saturating_sub(1).max(1);
+            if self.typing {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => self.typing = false,
+                    KeyCode::Backspace => {
+                        let mut pattern = self.pattern.clone();
+                        pattern.pop();
+                        self.set_pattern(pattern);
+                    }
+                    KeyCode::Char(c) => {
+                        let pattern = format!("{}{}", self.pattern, c);
+                        self.set_pattern(pattern);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('/') => self.typing = true,
+                KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => self.selected += 1,
+                KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+                KeyCode::PageDown | KeyCode::Char(' ') => self.selected += page,
+                KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+                // Reads every remaining record to count the matches
+                KeyCode::End | KeyCode::Char('G') => self.selected = usize::MAX,
+                KeyCode::Enter => {
+                    if let Some(&idx) = self.matches.get(self.selected) {
+                        self.show_detail(out, idx)?;
+                    }
+                }
+                _ => {}
+            }
+            // Clamp to what exists, reading ahead only as far as the selection
+            if self.selected >= self.matches.len() {
+                self.scan_to(self.selected.saturating_add(1))?;
+                self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+            }
+        }
+    }
+}
+
+/// The next key press, or `None` for other events (resizes redraw).
+fn next_key() -> io::Result<Option<KeyEvent>> {
+    match event::read()? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => Ok(Some(key)),
+        _ => Ok(None),
+    }
+}
+
+pub fn run(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((file, count)) = open_seekable(path, ctx)? else {
+        return Err(format!(
+            "--interactive reads records on demand, which needs a binary index (.1idx) and a single trace spacing; {} cannot be read that way",
+            path
+        )
+        .into());
+    };
+    let mut viewer = Viewer {
+        file,
+        count,
+        ctx,
+        filter,
+        output,
+        pattern: String::new(),
+        typing: false,
+        matches: Vec::new(),
+        scanned: 0,
+        rows: HashMap::new(),
+        selected: 0,
+        top: 0,
+    };
+    let guard = TerminalGuard::enter()?;
+    let mut out = io::BufWriter::new(io::stdout());
+    let result = viewer.run(&mut out);
+    drop(out);
+    drop(guard);
+    result
+}
@@ -0,0 +1,648 @@
+//! The `liftover` subcommand: map BED intervals from one aligned genome to
+//! the other.
+//!
+//! Alignments are indexed by their interval on the source genome. An
+//! interval lifts through an alignment that contains it; with `--split`,
+//! it is first cut at the ends of the alignments overlapping it and each
+//! piece lifts on its own. Where several alignments qualify, the one with
+//! the highest identity is used.
+//!
+//! Positions inside an alignment are placed by interpolating within the
+//! tracepoint segment that holds them, or exactly by realigning that segment
+//! when `--fasta` is given for both genomes. An interval maps through its
+//! first and last bases.
+//!
+//! Lifted records are BED6 plus two columns. In order after the name:
+//!
+//! * a mapping-quality-like confidence as the score: 60 when a single
+//!   alignment qualifies, otherwise 60 scaled by how far the best identity is
+//!   ahead of the runner-up;
+//! * the input strand, flipped by '-' alignments (the alignment strand when
+//!   the input has none);
+//! * the identity of the alignment used;
+//! * how many alignments qualified.
+
+use crate::align::{self, Op};
+use crate::fai::{self, FaiMetadata};
+use crate::fasta::Sequences;
+use crate::resolve::ResolveOptions;
+use crate::track::Space;
+use crate::{
+    AlignmentData, ExcludedSequences, LengthSource, ParseContext, ParseWarnings, flush_after, for_each_alignment, get_file_metadata,
+    open_fasta, trace,
+};
+use clap::Parser;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+#[derive(Parser, Debug)]
+#[command(name = "oneview-rs liftover")]
+#[command(about = "Map BED intervals from one genome of a .1aln file to the other", long_about = None)]
+pub struct LiftoverArgs {
+    /// Input .1aln file path
+    #[arg(value_name = "FILE")]
+    alignments: String,
+
+    /// BED file of intervals to lift ('-' for stdin)
+    #[arg(long = "input", value_name = "BED")]
+    bed: String,
+
+    /// Genome the intervals are on
+    #[arg(long, value_enum, value_name = "SIDE", default_value = "query")]
+    from: Space,
+
+    /// Genome to lift them to
+    #[arg(long, value_enum, value_name = "SIDE", default_value = "target")]
+    to: Space,
+
+    /// Write intervals that could not be lifted to FILE, each after a comment line giving the reason
+    #[arg(long, value_name = "FILE")]
+    unmapped: Option<String>,
+
+    /// Cut intervals at alignment ends and lift each piece, instead of requiring one alignment to
+    /// contain the whole interval
+    #[arg(long)]
+    split: bool,
+
+    /// FASTA files with the aligned genomes, as query=PATH and/or target=PATH, for exact positions
+    /// within tracepoint segments (needs both)
+    #[arg(long, value_name = "SIDE=PATH", num_args = 1..=2)]
+    fasta: Vec<String>,
+
+    /// Extra directory to look for referenced GDBs in, by file name (repeatable)
+    #[arg(long, value_name = "DIR")]
+    gdb_dir: Vec<String>,
+
+    /// Query names and lengths from a .fai when no query GDB can be loaded (IDs by line order)
+    #[arg(long, value_name = "PATH")]
+    query_fai: Option<String>,
+
+    /// Target names and lengths from a .fai when neither a target GDB nor an embedded skeleton is available
+    #[arg(long, value_name = "PATH")]
+    target_fai: Option<String>,
+}
+
+/// Highest confidence, given to intervals only one alignment can lift.
+const UNIQUE_CONFIDENCE: f64 = 60.0;
+
+/// One alignment, as indexed by its source interval.
+struct Block {
+    aln: AlignmentData,
+    identity: f64,
+    /// `(query offset, target offset)` at each tracepoint segment boundary,
+    /// in alignment orientation; just the two ends without a usable trace
+    anchors: Vec<(i64, i64)>,
+    source_start: i64,
+    source_end: i64,
+}
+
+impl Block {
+    fn new(mut aln: AlignmentData, from: Space) -> Self {
+        let mut anchors = vec![(0, 0)];
+        if let Some(segments) = trace::segments(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        ) {
+            anchors.extend(segments.iter().map(|segment| (segment.query_end, segment.target_end)));
+        }
+        if anchors.last() != Some(&(aln.query_span(), aln.target_span())) {
+            // No trace, or one that disagrees with the coordinates: interpolate over the whole alignment
+            anchors = vec![(0, 0), (aln.query_span(), aln.target_span())];
+        }
+        aln.tracepoints = Vec::new();
+        aln.trace_diffs = Vec::new();
+
+        let (source_start, source_end) = match from {
+            Space::Query => (aln.query_start, aln.query_end),
+            Space::Target => (aln.target_start, aln.target_end),
+        };
+        Block {
+            identity: aln.identity(),
+            aln,
+            anchors,
+            source_start,
+            source_end,
+        }
+    }
+
+    /// Offset of the forward-strand position `position` along the alignment on `side`.
+    fn offset(&self, side: Space, position: i64) -> i64 {
+        match side {
+            Space::Query => position - self.aln.query_start,
+            Space::Target if self.aln.strand == '-' => self.aln.target_end - 1 - position,
+            Space::Target => position - self.aln.target_start,
+        }
+    }
+
+    /// Forward-strand position `offset` bases along the alignment on `side`.
+    fn position(&self, side: Space, offset: i64) -> i64 {
+        match side {
+            Space::Query => self.aln.query_start + offset,
+            Space::Target if self.aln.strand == '-' => self.aln.target_end - 1 - offset,
+            Space::Target => self.aln.target_start + offset,
+        }
+    }
+
+    /// The base on the other genome that `position` on `from` aligns to; a
+    /// base in a gap maps next to where the gap is.
+    fn map_base(&self, from: Space, position: i64, sequences: &Sequences) -> io::Result<i64> {
+        let pick = |anchor: &(i64, i64)| match from {
+            Space::Query => (anchor.0, anchor.1),
+            Space::Target => (anchor.1, anchor.0),
+        };
+        let offset = self.offset(from, position);
+        // The segment holding the offset; the one before it never ends at or after it
+        let k = self.anchors.partition_point(|anchor| pick(anchor).0 <= offset).clamp(1, self.anchors.len() - 1) - 1;
+        let (source_start, dest_start) = pick(&self.anchors[k]);
+        let (source_end, dest_end) = pick(&self.anchors[k + 1]);
+
+        let within = match self.realign_segment(k, sequences)? {
+            Some(ops) => dest_offset_in_ops(&ops, from, offset - source_start),
+            None if source_end > source_start => {
+                (offset - source_start) * (dest_end - dest_start) / (source_end - source_start)
+            }
+            None => 0,
+        };
+        let to = match from {
+            Space::Query => Space::Target,
+            Space::Target => Space::Query,
+        };
+        let dest_last = match to {
+            Space::Query => self.aln.query_span(),
+            Space::Target => self.aln.target_span(),
+        } - 1;
+        Ok(self.position(to, (dest_start + within).clamp(0, dest_last.max(0))))
+    }
+
+    /// The base-level path of segment `k`, when both genomes have FASTA.
+    fn realign_segment(&self, k: usize, sequences: &Sequences) -> io::Result<Option<Vec<Op>>> {
+        let (Some(query_fasta), Some(target_fasta)) = (&sequences.query, &sequences.target) else {
+            return Ok(None);
+        };
+        let (query_start, target_start) = self.anchors[k];
+        let (query_end, target_end) = self.anchors[k + 1];
+        let aln = &self.aln;
+        let query = query_fasta.fetch(aln.query_id, aln.query_start + query_start, aln.query_start + query_end, '+')?;
+        let (from, to) = if aln.strand == '-' {
+            (aln.target_end - target_end, aln.target_end - target_start)
+        } else {
+            (aln.target_start + target_start, aln.target_start + target_end)
+        };
+        let target = target_fasta.fetch(aln.target_id, from, to, aln.strand)?;
+        Ok(Some(align::align_segment(query.as_bytes(), target.as_bytes())))
+    }
+}
+
+/// Offset on the other sequence of the base `offset` along `from` in `ops`.
+fn dest_offset_in_ops(ops: &[Op], from: Space, offset: i64) -> i64 {
+    let (mut source, mut dest) = (0, 0);
+    for &op in ops {
+        let (source_step, dest_step) = match (from, op) {
+            (_, Op::Match | Op::Mismatch) => (1, 1),
+            (Space::Query, Op::Insertion) | (Space::Target, Op::Deletion) => (1, 0),
+            (Space::Query, Op::Deletion) | (Space::Target, Op::Insertion) => (0, 1),
+        };
+        if source_step == 1 && source == offset {
+            return dest;
+        }
+        source += source_step;
+        dest += dest_step;
+    }
+    dest
+}
+
+/// Blocks on one source sequence, sorted by start, with the running maximum
+/// of their ends for overlap queries.
+#[derive(Default)]
+struct SequenceIndex {
+    blocks: Vec<Block>,
+    max_end: Vec<i64>,
+}
+
+impl SequenceIndex {
+    fn finish(&mut self) {
+        self.blocks.sort_by_key(|block| (block.source_start, block.source_end, block.aln.index));
+        let mut max_end = i64::MIN;
+        self.max_end = self
+            .blocks
+            .iter()
+            .map(|block| {
+                max_end = max_end.max(block.source_end);
+                max_end
+            })
+            .collect();
+    }
+
+    /// Blocks overlapping `[start, end)`.
+    fn overlapping(&self, start: i64, end: i64) -> Vec<&Block> {
+        let last = self.blocks.partition_point(|block| block.source_start < end);
+        let mut hits: Vec<&Block> = (0..last)
+            .rev()
+            .take_while(|&i| self.max_end[i] > start)
+            .map(|i| &self.blocks[i])
+            .filter(|block| block.source_end > start)
+            .collect();
+        hits.reverse();
+        hits
+    }
+}
+
+/// One BED record: its interval and the fields around it.
+struct BedRecord {
+    chrom: String,
+    start: i64,
+    end: i64,
+    name: String,
+    strand: Option<char>,
+    /// Columns after the strand, passed through to unmapped output
+    rest: Vec<String>,
+}
+
+fn parse_bed_line(line: &str, line_number: usize) -> Result<Option<BedRecord>, Box<dyn std::error::Error>> {
+    if line.trim().is_empty() || line.starts_with('#') || line.starts_with("track") || line.starts_with("browser") {
+        return Ok(None);
+    }
+    let fields: Vec<&str> = line.split('\t').collect();
+    let interval = match fields.as_slice() {
+        [chrom, start, end, ..] => start.parse::<i64>().ok().zip(end.parse::<i64>().ok()).map(|bounds| (chrom, bounds)),
+        _ => None,
+    };
+    let Some((chrom, (start, end))) = interval.filter(|&(_, (start, end))| start >= 0 && start <= end) else {
+        return Err(format!("BED line {}: expected chrom, start and end with 0 <= start <= end", line_number).into());
+    };
+    Ok(Some(BedRecord {
+        chrom: chrom.to_string(),
+        start,
+        end,
+        name: fields.get(3).unwrap_or(&".").to_string(),
+        strand: fields.get(5).and_then(|strand| strand.chars().next()).filter(|strand| matches!(strand, '+' | '-')),
+        rest: fields.iter().skip(6).map(|field| field.to_string()).collect(),
+    }))
+}
+
+fn confidence(candidates: &[&Block], best: &Block) -> u32 {
+    let runner_up = candidates
+        .iter()
+        .filter(|block| !std::ptr::eq(**block, best))
+        .map(|block| block.identity)
+        .fold(None, |max: Option<f64>, identity| Some(max.map_or(identity, |max| max.max(identity))));
+    match runner_up {
+        None => UNIQUE_CONFIDENCE as u32,
+        Some(_) if best.identity <= 0.0 => 0,
+        Some(second) => (UNIQUE_CONFIDENCE * ((best.identity - second) / best.identity)).round().clamp(0.0, UNIQUE_CONFIDENCE) as u32,
+    }
+}
+
+struct Lifter<'a> {
+    index: HashMap<String, SequenceIndex>,
+    from: Space,
+    sequences: &'a Sequences,
+    split: bool,
+    lifted: Cell<usize>,
+    unmapped: Cell<usize>,
+}
+
+impl Lifter<'_> {
+    fn lift<W: Write>(&self, record: &BedRecord, out: &mut W, unmapped: &mut Option<BufWriter<File>>) -> io::Result<()> {
+        let Some(index) = self.index.get(&record.chrom) else {
+            return self.reject(record, record.start, record.end, "no alignment on this sequence", unmapped);
+        };
+        // Zero-length intervals are treated as the base at their position
+        let end = record.end.max(record.start + 1);
+        let hits = index.overlapping(record.start, end);
+        if hits.is_empty() {
+            return self.reject(record, record.start, record.end, "no alignment covers the interval", unmapped);
+        }
+
+        let mut pieces = vec![(record.start, end)];
+        if self.split {
+            let mut cuts: Vec<i64> = hits
+                .iter()
+                .flat_map(|block| [block.source_start, block.source_end])
+                .filter(|&cut| cut > record.start && cut < end)
+                .collect();
+            cuts.extend([record.start, end]);
+            cuts.sort_unstable();
+            cuts.dedup();
+            pieces = cuts.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        }
+
+        // Adjacent pieces lifted through the same alignment are written as one
+        let mut pending: Option<(i64, i64, &Block, u32, usize)> = None;
+        for (start, end) in pieces {
+            let candidates: Vec<&Block> =
+                hits.iter().copied().filter(|block| block.source_start <= start && block.source_end >= end).collect();
+            let Some(best) = candidates
+                .iter()
+                .copied()
+                .reduce(|best, block| if block.identity > best.identity { block } else { best })
+            else {
+                if let Some(piece) = pending.take() {
+                    self.write_lifted(record, piece, out)?;
+                }
+                let reason = if self.split { "no alignment covers this part" } else { "no single alignment contains the interval" };
+                self.reject(record, start, end.min(record.end), reason, unmapped)?;
+                continue;
+            };
+            let score = confidence(&candidates, best);
+            pending = match pending {
+                Some((first, _, block, previous_score, count)) if std::ptr::eq(block, best) => {
+                    Some((first, end, block, previous_score.min(score), count.max(candidates.len())))
+                }
+                Some(piece) => {
+                    self.write_lifted(record, piece, out)?;
+                    Some((start, end, best, score, candidates.len()))
+                }
+                None => Some((start, end, best, score, candidates.len())),
+            };
+        }
+        if let Some(piece) = pending {
+            self.write_lifted(record, piece, out)?;
+        }
+        Ok(())
+    }
+
+    fn write_lifted<W: Write>(&self, record: &BedRecord, piece: (i64, i64, &Block, u32, usize), out: &mut W) -> io::Result<()> {
+        let (start, end, block, score, count) = piece;
+        let first = block.map_base(self.from, start, self.sequences)?;
+        let last = block.map_base(self.from, end - 1, self.sequences)?;
+        let (chrom, strand) = match self.from {
+            Space::Query => (&block.aln.target_name, block.aln.strand),
+            Space::Target => (&block.aln.query_name, block.aln.strand),
+        };
+        let strand = match (record.strand, strand) {
+            (Some('+'), '-') => '-',
+            (Some(_), '-') => '+',
+            (Some(input), _) => input,
+            (None, strand) => strand,
+        };
+        let lifted_end = if record.end == record.start { first.min(last) } else { first.max(last) + 1 };
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{:.4}\t{}",
+            chrom,
+            first.min(last),
+            lifted_end,
+            record.name,
+            score,
+            strand,
+            block.identity,
+            count
+        )?;
+        self.lifted.set(self.lifted.get() + 1);
+        Ok(())
+    }
+
+    fn reject(
+        &self,
+        record: &BedRecord,
+        start: i64,
+        end: i64,
+        reason: &str,
+        unmapped: &mut Option<BufWriter<File>>,
+    ) -> io::Result<()> {
+        self.unmapped.set(self.unmapped.get() + 1);
+        let Some(out) = unmapped else { return Ok(()) };
+        writeln!(out, "#{}", reason)?;
+        write!(out, "{}\t{}\t{}\t{}", record.chrom, start, end, record.name)?;
+        if record.strand.is_some() || !record.rest.is_empty() {
+            write!(out, "\t0\t{}", record.strand.unwrap_or('.'))?;
+        }
+        for field in &record.rest {
+            write!(out, "\t{}", field)?;
+        }
+        writeln!(out)
+    }
+}
+
+pub fn run(args: LiftoverArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.from == args.to {
+        return Err("--from and --to must name different genomes".into());
+    }
+    let resolve_options = ResolveOptions {
+        gdb_dirs: args.gdb_dir.clone(),
+        ..ResolveOptions::default()
+    };
+    let fai_metadata = FaiMetadata {
+        query: args.query_fai.as_deref().map(|path| fai::read_tables(path, None)).transpose()?,
+        target: args.target_fai.as_deref().map(|path| fai::read_tables(path, None)).transpose()?,
+    };
+    let (metadata, trace_spacing, _) = get_file_metadata(&args.alignments, None, false, &resolve_options, fai_metadata)?;
+    let sequences = open_fasta(&args.fasta, &metadata)?;
+    if sequences.query.is_some() != sequences.target.is_some() {
+        eprintln!("Warning: exact liftover needs --fasta for both genomes; interpolating within segments");
+    }
+
+    let mut ctx = ParseContext {
+        metadata: &metadata,
+        length_source: LengthSource::Metadata,
+        read_traces: true,
+        trace_spacing,
+        sequences: &sequences,
+        exact_identity: false,
+        strict: false,
+        skip_missing_metadata: true,
+        allow_anonymous: false,
+        trim_ends: None,
+        clip: None,
+        respace: None,
+        explain: false,
+        stream: None,
+        excluded: ExcludedSequences::default(),
+        representatives: None,
+        warnings: ParseWarnings::default(),
+    };
+    let mut index: HashMap<String, SequenceIndex> = HashMap::new();
+    let mut out_of_bounds = 0;
+    for_each_alignment(&args.alignments, &mut ctx, |aln| {
+        if aln.out_of_bounds {
+            out_of_bounds += 1;
+            return Ok(());
+        }
+        let name = match args.from {
+            Space::Query => aln.query_name.clone(),
+            Space::Target => aln.target_name.clone(),
+        };
+        index.entry(name).or_default().blocks.push(Block::new(aln, args.from));
+        Ok(())
+    })?;
+    ctx.warnings.report();
+    if out_of_bounds > 0 {
+        eprintln!("Warning: {} alignments with invalid coordinates were not indexed", out_of_bounds);
+    }
+    for sequence in index.values_mut() {
+        sequence.finish();
+    }
+    let sequences = if sequences.query.is_some() && sequences.target.is_some() { sequences } else { Sequences::default() };
+
+    let reader: Box<dyn BufRead> = if args.bed == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let file = File::open(&args.bed).map_err(|e| format!("Cannot read BED file {}: {}", args.bed, e))?;
+        Box::new(BufReader::new(file))
+    };
+    let mut unmapped = args
+        .unmapped
+        .as_deref()
+        .map(|path| File::create(path).map(BufWriter::new).map_err(|e| format!("Cannot create {}: {}", path, e)))
+        .transpose()?;
+
+    let lifter = Lifter {
+        index,
+        from: args.from,
+        sequences: &sequences,
+        split: args.split,
+        lifted: Cell::new(0),
+        unmapped: Cell::new(0),
+    };
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut result: Result<(), Box<dyn std::error::Error>> = Ok(());
+    for (line_idx, line) in reader.lines().enumerate() {
+        let record = line.map_err(Into::into).and_then(|line| parse_bed_line(&line, line_idx + 1));
+        match record {
+            Ok(Some(record)) => {
+                if let Err(e) = lifter.lift(&record, &mut out, &mut unmapped) {
+                    result = Err(e.into());
+                    break;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    flush_after(&mut out, result)?;
+    if let Some(unmapped) = &mut unmapped {
+        unmapped.flush()?;
+    }
+    eprintln!("Lifted {} records; {} intervals or pieces could not be lifted", lifter.lifted.get(), lifter.unmapped.get());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// q:100-300 on t:1000-1198 at trace spacing 100, T 90,108.
+    fn forward() -> Block {
+        Block::new(
+            AlignmentData {
+                query_start: 100,
+                query_end: 300,
+                target_start: 1000,
+                target_end: 1198,
+                strand: '+',
+                trace_spacing: 100,
+                tracepoints: vec![90, 108],
+                trace_diffs: vec![0, 0],
+                ..Default::default()
+            },
+            Space::Query,
+        )
+    }
+
+    /// q:0-100 on t:400-500, reverse strand, without a trace.
+    fn reverse() -> Block {
+        Block::new(
+            AlignmentData {
+                query_start: 0,
+                query_end: 100,
+                target_start: 400,
+                target_end: 500,
+                strand: '-',
+                ..Default::default()
+            },
+            Space::Query,
+        )
+    }
+
+    fn with_identity(identity: f64) -> Block {
+        Block {
+            aln: AlignmentData::default(),
+            identity,
+            anchors: Vec::new(),
+            source_start: 0,
+            source_end: 0,
+        }
+    }
+
+    #[test]
+    fn anchors_follow_the_trace() {
+        assert_eq!(forward().anchors, vec![(0, 0), (100, 90), (200, 198)]);
+        assert_eq!(reverse().anchors, vec![(0, 0), (100, 100)]);
+    }
+
+    #[test]
+    fn reverse_strand_offsets_count_from_the_target_end() {
+        let block = reverse();
+        assert_eq!(block.offset(Space::Target, 499), 0);
+        assert_eq!(block.offset(Space::Target, 400), 99);
+        assert_eq!(block.position(Space::Target, 0), 499);
+        assert_eq!(block.position(Space::Target, 99), 400);
+        assert_eq!(block.offset(Space::Query, 10), 10);
+
+        let block = forward();
+        assert_eq!(block.offset(Space::Target, 1010), 10);
+        assert_eq!(block.position(Space::Target, 10), 1010);
+    }
+
+    #[test]
+    fn map_base_interpolates_within_a_segment() {
+        let block = forward();
+        let none = Sequences::default();
+        // Offset 50 of the first segment: 50 * 90 / 100
+        assert_eq!(block.map_base(Space::Query, 150, &none).unwrap(), 1045);
+        // Offset 50 of the second: 90 + 50 * 108 / 100
+        assert_eq!(block.map_base(Space::Query, 250, &none).unwrap(), 1144);
+        // Target offset 95 is 5 into the second segment: 100 + 5 * 100 / 108
+        assert_eq!(block.map_base(Space::Target, 1095, &none).unwrap(), 204);
+
+        let block = reverse();
+        assert_eq!(block.map_base(Space::Query, 10, &none).unwrap(), 489);
+        assert_eq!(block.map_base(Space::Target, 489, &none).unwrap(), 10);
+    }
+
+    #[test]
+    fn map_base_clamps_to_the_alignment() {
+        let block = forward();
+        let none = Sequences::default();
+        assert_eq!(block.map_base(Space::Query, 300, &none).unwrap(), 1197);
+        assert_eq!(block.map_base(Space::Query, 100, &none).unwrap(), 1000);
+    }
+
+    #[test]
+    fn gaps_map_next_to_where_they_are() {
+        use Op::{Deletion as D, Insertion as I, Match as M};
+        // Query 0 1 2 3 4 . 5 over target 0 1 . . 2 3 4
+        let ops = [M, M, I, I, M, D, M];
+        assert_eq!(dest_offset_in_ops(&ops, Space::Query, 1), 1);
+        assert_eq!(dest_offset_in_ops(&ops, Space::Query, 2), 2);
+        assert_eq!(dest_offset_in_ops(&ops, Space::Query, 3), 2);
+        assert_eq!(dest_offset_in_ops(&ops, Space::Query, 5), 4);
+        assert_eq!(dest_offset_in_ops(&ops, Space::Target, 2), 4);
+        assert_eq!(dest_offset_in_ops(&ops, Space::Target, 3), 5);
+        // Past the end: the end of the other sequence
+        assert_eq!(dest_offset_in_ops(&ops, Space::Query, 9), 5);
+    }
+
+    #[test]
+    fn confidence_scales_with_the_lead_over_the_runner_up() {
+        let (best, second, tied) = (with_identity(0.99), with_identity(0.98), with_identity(0.99));
+        assert_eq!(confidence(&[&best], &best), 60);
+        // 60 * 0.01 / 0.99
+        assert_eq!(confidence(&[&best, &second], &best), 1);
+        assert_eq!(confidence(&[&best, &tied], &best), 0);
+        let (zero, other) = (with_identity(0.0), with_identity(0.0));
+        assert_eq!(confidence(&[&zero, &other], &zero), 0);
+        let (far, low) = (with_identity(0.9), with_identity(0.45));
+        assert_eq!(confidence(&[&low, &far], &far), 30);
+    }
+}
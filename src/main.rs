@@ -1,11 +1,69 @@
+mod align;
+mod bench;
+mod chain;
+mod columns;
+mod compare;
+mod config;
+mod coords;
+mod coverage;
+mod depth;
+mod explain;
+mod fai;
+mod fasta;
+mod gff;
+mod interactive;
+mod liftover;
+mod mapq;
+mod names;
+mod pager;
+mod pansn;
+mod pretty;
+mod redundancy;
+mod region;
+mod rename;
+mod resolve;
+mod schema;
+mod score;
+mod split;
+mod stats;
+mod style;
+mod template;
+mod trace;
+mod track;
+mod validate;
+mod vcf;
+mod verify;
+mod writer;
+
+use align::EditCounts;
+use coords::CoordBase;
+use fasta::{GenomeFasta, Sequences};
 use onecode::OneFile;
-use std::collections::HashMap;
-use std::io::{self, Write};
-use clap::Parser;
+use mapq::MapqMode;
+use names::{NamePolicy, NameSanitizer};
+use pansn::PanSn;
+use resolve::{ResolveOptions, TargetSource};
+use score::ScoringModel;
+use stats::{IdentityBins, OrientationStats, PairStats, QuerySummary, SequenceStats, SummaryStats};
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use template::Template;
+use clap::{Parser, ValueEnum};
+
+/// Exit status when the input ends before the alignment count in its header
+const EXIT_TRUNCATED: i32 = 3;
 
 #[derive(Parser, Debug)]
 #[command(name = "oneview-rs")]
 #[command(about = "View alignments from ONE format files", long_about = None)]
+#[command(after_help = "Run `oneview-rs liftover --help` to map BED intervals between the aligned genomes,\nand `oneview-rs index --help` to build the region index --target-region seeks with.\n\n\
+Default options are read from --config, else ./.oneview.toml, else ~/.oneview.toml, with long option\n\
+names as keys (paf = true, gdb-dir = [\"/data\"]). ONEVIEW_DEFAULT_FORMAT=human|paf|maf|bedpe picks the\n\
+alignment format when neither gives one; ONEVIEW_DEFAULT_FORMAT=json only applies to --stats and --stats-approx,\n\
+as if --json were given, and leaves other output human-readable. Precedence: command line, config file,\n\
+ONEVIEW_DEFAULT_FORMAT.")]
 struct Args {
     /// Input .1aln file path
     #[arg(value_name = "FILE")]
@@ -14,6 +72,33 @@ struct Args {
     /// Alignment number to read (0-indexed)
     #[arg(short, long, value_name = "NUM")]
     alignment: Option<usize>,
+
+    /// Read the alignments whose numbers (0-indexed, one per line) are listed in FILE, seeking to
+    /// each through the binary index; printed in file order unless --preserve-order
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["alignment", "metadata"])]
+    select_alignments: Option<String>,
+
+    /// Browse the alignments in a terminal viewer: a scrollable list filtered by typing part of a
+    /// sequence name, with Enter opening the human view (and the base-level view with --fasta).
+    /// Reads records on demand through the binary index
+    #[arg(long, conflicts_with_all = ["alignment", "metadata", "select_alignments", "dedup"])]
+    interactive: bool,
+
+    /// Don't page human output through $PAGER (default less -FRX) when stdout is a terminal
+    #[arg(long)]
+    no_pager: bool,
+
+    /// Color human output and --pretty: auto colors when stdout is a terminal and NO_COLOR is unset
+    #[arg(long, value_enum, value_name = "WHEN", default_value = "auto")]
+    color: style::ColorChoice,
+
+    /// Identity thresholds of the green and yellow colors, HIGH,LOW (below LOW is red)
+    #[arg(long, value_name = "HIGH,LOW", value_delimiter = ',', num_args = 2, default_values_t = [0.995, 0.95])]
+    color_thresholds: Vec<f64>,
+
+    /// Print --select-alignments in the order listed, repeats included
+    #[arg(long, requires = "select_alignments", conflicts_with = "section_by")]
+    preserve_order: bool,
     
     /// Print only file metadata (sequences and trace spacing)
     #[arg(short, long)]
@@ -22,11 +107,577 @@ struct Args {
     /// Emit alignments in PAF format
     #[arg(long)]
     paf: bool,
+
+    /// Emit PAF in the layout of FastGA's ALNtoPAF without CIGAR: its 12 columns plus dv:F and df:i, ignoring
+    /// --tags and --mapq-mode
+    #[arg(long)]
+    compat_alntopaf: bool,
+
+    /// Start PAF output with a '#' comment line naming the columns and describing the enabled tags
+    #[arg(long)]
+    paf_header: bool,
+
+    /// Emit alignments as MAF blocks (sequence text is placeholders unless --fasta is given)
+    #[arg(long)]
+    maf: bool,
+
+    /// Emit alignments as BEDPE: query interval, target interval, index, score (identity x 1000), +, strand
+    #[arg(long)]
+    bedpe: bool,
+
+    /// Write the selected alignments to a new .1aln file instead of printing them
+    #[arg(long, value_name = "OUT")]
+    write_1aln: Option<String>,
+
+    /// Write the aligned query and target bases of the selected alignments to PREFIX.query.fa and
+    /// PREFIX.target.fa instead of printing them (needs --fasta for both sides; '-' targets reverse-complemented)
+    #[arg(long, value_name = "PREFIX")]
+    extract_fasta: Option<String>,
+
+    /// Read the --fasta genomes fully into memory up front instead of seeking per alignment
+    #[arg(long)]
+    preload: bool,
+
+    /// Emit every alignment twice, as is and with query and target swapped (PAF or --write-1aln only;
+    /// swapped records carry no tracepoints)
+    #[arg(long)]
+    transpose: bool,
+
+    /// FASTA files with the aligned genomes, as query=PATH and/or target=PATH (indexed with .fai)
+    #[arg(long, value_name = "SIDE=PATH", num_args = 1..=2)]
+    fasta: Vec<String>,
+
+    /// Count exact matches/mismatches/indels by realigning each tracepoint segment (needs --fasta for both genomes)
+    #[arg(long)]
+    exact_identity: bool,
+
+    /// PAF mapping quality: constant:N, unique (overlap-based, buffers all alignments) or length-identity
+    #[arg(long, value_name = "MODE", default_value = "constant:255")]
+    mapq_mode: MapqMode,
+
+    /// Optional PAF tags to write, comma-separated (tp can be large: one pair per trace segment)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "LIST", default_value = "df,tp,nm")]
+    tags: Vec<PafTag>,
+
+    /// Score per matching base for the AS:i tag and score column
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    score_match: i64,
+
+    /// Penalty per mismatching base
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    score_mismatch: i64,
+
+    /// Penalty for opening a gap
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    score_gap_open: i64,
+
+    /// Penalty per gap base
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    score_gap_extend: i64,
+
+    /// Write no optional PAF tags
+    #[arg(long, conflicts_with = "tags")]
+    no_tags: bool,
+
+    /// Report the contig offsets added to each alignment's contig coordinates to place it on the
+    /// scaffold: as qo:i/to:i tags in PAF, on stderr otherwise. Helps tell a mismatched GDB apart
+    #[arg(long, conflicts_with = "compat_alntopaf")]
+    print_offsets: bool,
+
+    /// Emit one line per alignment from a template such as '{qname}\t{qstart}\t{identity:.4}'
+    #[arg(long, value_name = "TEMPLATE")]
+    format: Option<Template>,
+
+    /// Handling of whitespace in sequence names for tabular output: full, first-word, error or replace:STR
+    #[arg(long, value_name = "POLICY", default_value = "first-word")]
+    name_policy: NamePolicy,
+
+    /// Coordinate convention for human-readable and --format output (PAF is always 0-based half-open)
+    #[arg(long, value_enum, value_name = "BASE", default_value = "0")]
+    coord_base: CoordBase,
+
+    /// Where sequence lengths come from: the GDB metadata, or the lengths carried by each alignment record (L line)
+    #[arg(long, value_enum, value_name = "SOURCE", default_value = "metadata")]
+    length_source: LengthSource,
+
+    /// Print summary statistics over all alignments (exact; keeps every query span in memory)
+    #[arg(long)]
+    stats: bool,
+
+    /// Like --stats, but estimate the N50 and L50 in constant memory, within 0.5% for the N50,
+    /// for files whose spans don't fit in memory
+    #[arg(long)]
+    stats_approx: bool,
+
+    /// Write --stats or --stats-approx as a JSON document with per-sequence, per-pair and
+    /// identity-histogram breakdowns; field names are stable and described in the stats module
+    #[arg(long)]
+    json: bool,
+
+    /// Only report how each embedded GDB reference resolves on disk (reads no alignments)
+    #[arg(long)]
+    check_refs: bool,
+
+    /// Extra directory to look for referenced GDBs in, by file name (repeatable)
+    #[arg(long, value_name = "DIR")]
+    gdb_dir: Vec<String>,
+
+    /// Resolve symlinks in the alignment path and in absolute recorded reference paths before
+    /// searching for GDBs, so relative references resolve from the alignment's real directory
+    #[arg(long)]
+    resolve_symlinks: bool,
+
+    /// Read default options from this TOML file instead of ./.oneview.toml or ~/.oneview.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+
+    /// Ignore configuration files, for example to unset a flag one turns on
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    /// Target metadata source when both an external GDB and the embedded skeleton are available
+    #[arg(long, value_enum, value_name = "SOURCE", default_value = "external")]
+    prefer: TargetSource,
+
+    /// Two-column TSV pinning embedded references (path or file name) to local GDB/FASTA paths, tried first
+    #[arg(long, value_name = "FILE")]
+    paths_from: Option<String>,
+
+    /// Query names and lengths from a .fai when no query GDB can be loaded (IDs by line order, see --query-fai-ids)
+    #[arg(long, value_name = "PATH")]
+    query_fai: Option<String>,
+
+    /// Target names and lengths from a .fai when neither a target GDB nor an embedded skeleton is available
+    #[arg(long, value_name = "PATH")]
+    target_fai: Option<String>,
+
+    /// Two-column TSV (name, contig ID) matching --query-fai sequences to IDs instead of line order
+    #[arg(long, value_name = "FILE", requires = "query_fai")]
+    query_fai_ids: Option<String>,
+
+    /// Two-column TSV (name, contig ID) matching --target-fai sequences to IDs instead of line order
+    #[arg(long, value_name = "FILE", requires = "target_fai")]
+    target_fai_ids: Option<String>,
+
+    /// Print per sequence-pair alignment statistics as TSV
+    #[arg(long)]
+    stats_pairs: bool,
+
+    /// Only print the line types declared in the file's schema header (reads no alignments)
+    #[arg(long)]
+    schema: bool,
+
+    /// Only print the oneview-rs and onecode versions, and the file's ONE format version and
+    /// provenance (the programs and commands that wrote it), for bug reports (reads no alignments)
+    #[arg(long, conflicts_with = "schema")]
+    version_info: bool,
+
+    /// Print the number of alignments on each target sequence as TSV, most hit first
+    #[arg(long)]
+    per_target_counts: bool,
+
+    /// Greedily chain collinear alignments per query/target/strand and print each chain's span and score as TSV
+    #[arg(long)]
+    chain_score: bool,
+
+    /// Print one TSV row per target sequence, including those without alignments: alignments,
+    /// merged aligned bp, percent covered, mean identity, distinct query partners and largest alignment
+    #[arg(long)]
+    stats_per_target: bool,
+
+    /// Like --stats-per-target, for query sequences
+    #[arg(long)]
+    stats_per_query: bool,
+
+    /// Print aligned query bp per query/target group (--group-by) in identity bins split at these
+    /// comma-separated thresholds, e.g. 0.95,0.99,0.999, binning each tracepoint segment on its own
+    #[arg(long, value_name = "LIST", value_delimiter = ',', num_args = 1..)]
+    identity_bins: Option<Vec<f64>>,
+
+    /// Write, per query sequence, the merged intervals aligned anywhere on the target as BED to FILE ('-' for stdout)
+    #[arg(long, value_name = "FILE")]
+    query_covered_bed: Option<String>,
+
+    /// Print per query sequence its alignment count, distinct targets hit and aligned bp as TSV,
+    /// most alignments first
+    #[arg(long)]
+    query_summary: bool,
+
+    /// Print forward and reverse alignment counts and aligned bp per query/target group (--group-by)
+    /// and genome-wide, with each pair's dominant orientation
+    #[arg(long)]
+    orientation_stats: bool,
+
+    /// Flag an --orientation-stats pair as mixed when its minority strand holds at least this
+    /// fraction of its aligned bp
+    #[arg(long, value_name = "F", default_value_t = 0.1, requires = "orientation_stats")]
+    mixed_orientation_frac: f64,
+
+    /// Order --orientation-stats pairs by total aligned bp, largest first, instead of by name
+    #[arg(long, requires = "orientation_stats")]
+    sort_by_span: bool,
+
+    /// Only list queries with at least N alignments in --query-summary
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "query_summary")]
+    min_count: usize,
+
+    /// Drop every alignment of query sequences whose merged aligned fraction is below F (reads the file twice)
+    #[arg(long, value_name = "F")]
+    min_covered_frac: Option<f64>,
+
+    /// Keep at most N alignments per query/target sequence pair, the longest first (fewest
+    /// differences breaking ties); reads the file twice and prints survivors in file order
+    #[arg(long, value_name = "N")]
+    max_per_pair: Option<usize>,
+
+    /// Collapse near-duplicate alignments (same sequences and strand, query and target intervals
+    /// each overlapping reciprocally by --collapse-frac, chained) to the one with the most matches;
+    /// PAF records carry the cluster size as nr:i. Reads the file twice
+    #[arg(long)]
+    collapse_redundant: bool,
+
+    /// Reciprocal overlap fraction for --collapse-redundant
+    #[arg(long, value_name = "F", default_value_t = 0.9, requires = "collapse_redundant")]
+    collapse_frac: f64,
+
+    /// Largest query or target gap --chain-score bridges between consecutive alignments
+    #[arg(long, value_name = "BP", default_value_t = 100_000)]
+    chain_max_gap: i64,
+
+    /// Write local identity as bedGraph to FILE ('-' for stdout): per tracepoint segment, or per
+    /// --identity-window bases when --fasta is given for both sides
+    #[arg(long, value_name = "FILE")]
+    identity_track: Option<String>,
+
+    /// Write naive SNV and small indel calls against the target as VCF to FILE ('-' for stdout);
+    /// needs --fasta for both sides
+    #[arg(long, value_name = "FILE")]
+    call_vcf: Option<String>,
+
+    /// Write per-base target alignment depth as bedGraph to FILE ('-' for stdout), both strands counted alike
+    #[arg(long, value_name = "FILE")]
+    target_depth_bedgraph: Option<String>,
+
+    /// Print how many bases alignments cover at each depth, per sequence of --space and genome-wide
+    /// (sequence '*'), as TSV: sequence, depth, bases
+    #[arg(long)]
+    depth_hist: bool,
+
+    /// Highest depth --depth-hist tells apart; deeper bases are counted as N+
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "depth_hist")]
+    depth_hist_max: usize,
+
+    /// Report, for every feature of --feature-type in this GFF3 file, how much of it alignments
+    /// cover, their length-weighted identity, and whether one alignment or several cover it
+    #[arg(long, value_name = "GFF3")]
+    gene_coverage: Option<String>,
+
+    /// Write the --gene-coverage report to FILE instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "FILE", requires = "gene_coverage")]
+    output_path: Option<String>,
+
+    /// Window size in bases for base-level identity tracks (needs --fasta for both sides)
+    #[arg(long, value_name = "BP", default_value_t = 100)]
+    identity_window: i64,
+
+    /// Coordinate space of --identity-track intervals and of --depth-hist
+    #[arg(long, value_enum, value_name = "SPACE", default_value = "target")]
+    space: track::Space,
+
+    /// How --identity-track combines overlapping alignments
+    #[arg(long, value_enum, value_name = "HOW", default_value = "max")]
+    combine: track::Combine,
+
+    /// Check every alignment's trace lists and coordinates for consistency; exits non-zero on any violation
+    #[arg(long)]
+    validate: bool,
+
+    /// Realign each alignment from --fasta and compare its edit distance with the recorded D value
+    #[arg(long)]
+    verify_diffs: bool,
+
+    /// Check only about N alignments, evenly spread through the file (--verify-diffs)
+    #[arg(long, value_name = "N", requires = "verify_diffs")]
+    sample: Option<usize>,
+
+    /// Largest difference between realigned and recorded counts still accepted by --verify-diffs
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "verify_diffs")]
+    tolerance: i64,
+
+    /// Compare with a second .1aln by coordinates and report added, removed and changed
+    /// (strand or differences) alignments; record filters apply to both files, while filters built from
+    /// --input's sequence IDs or alignments cannot be combined with it
+    #[arg(long, value_name = "FILE", conflicts_with_all = [
+        "dedup", "min_query_len", "max_query_len", "min_target_len", "max_target_len", "max_per_pair",
+        "min_covered_frac", "collapse_redundant",
+    ])]
+    compare: Option<String>,
+
+    /// Grouping key for --stats-pairs
+    #[arg(long, value_enum, value_name = "KEY", default_value = "sequence")]
+    group_by: GroupBy,
+
+    /// Parse sequence names as PanSN (sample#haplotype#contig); use --pansn=DELIM for another delimiter
+    #[arg(long, value_name = "DELIM", num_args = 0..=1, require_equals = true, default_missing_value = "#")]
+    pansn: Option<char>,
+
+    /// Keep only alignments whose query belongs to this PanSN sample
+    #[arg(long, value_name = "SAMPLE")]
+    query_sample: Option<String>,
+
+    /// Keep only alignments whose target belongs to this PanSN sample
+    #[arg(long, value_name = "SAMPLE")]
+    target_sample: Option<String>,
+
+    /// Rename query sequences using a two-column TSV (old name, new name)
+    #[arg(long, value_name = "FILE")]
+    rename_query: Option<String>,
+
+    /// Rename target sequences using a two-column TSV (old name, new name)
+    #[arg(long, value_name = "FILE")]
+    rename_target: Option<String>,
+
+    /// Rename query and target sequences alike using a two-column TSV (original name, new name);
+    /// a name listed twice is warned about and its last entry used
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["rename_query", "rename_target"])]
+    id_map: Option<String>,
+
+    /// Fail if a sequence has no entry in its rename map
+    #[arg(long)]
+    rename_strict: bool,
+
+    /// Remove this prefix from query names that carry it (after --rename-query)
+    #[arg(long, value_name = "STR")]
+    strip_prefix_query: Option<String>,
+
+    /// Remove this prefix from target names that carry it (after --rename-target)
+    #[arg(long, value_name = "STR")]
+    strip_prefix_target: Option<String>,
+
+    /// Prepend this prefix to every query name (after --strip-prefix-query)
+    #[arg(long, value_name = "STR")]
+    add_prefix_query: Option<String>,
+
+    /// Prepend this prefix to every target name (after --strip-prefix-target)
+    #[arg(long, value_name = "STR")]
+    add_prefix_target: Option<String>,
+
+    /// Keep only alignments on query sequences at least this long (whole-sequence length from the
+    /// metadata, not the alignment span); other records are skipped before their traces are read
+    #[arg(long, visible_alias = "min-query-seqlen", value_name = "BP")]
+    min_query_len: Option<i64>,
+
+    /// Keep only alignments on query sequences at most this long (whole-sequence length from the metadata)
+    #[arg(long, visible_alias = "max-query-seqlen", value_name = "BP")]
+    max_query_len: Option<i64>,
+
+    /// Keep only alignments on target sequences at least this long (whole-sequence length from the metadata)
+    #[arg(long, visible_alias = "min-target-seqlen", value_name = "BP")]
+    min_target_len: Option<i64>,
+
+    /// Keep only alignments on target sequences at most this long (whole-sequence length from the metadata)
+    #[arg(long, visible_alias = "max-target-seqlen", value_name = "BP")]
+    max_target_len: Option<i64>,
+
+    /// Keep only alignments on this relative strand; --transpose keeps the strand, so both records follow it
+    #[arg(long, value_name = "STRAND", value_parser = ["+", "-"])]
+    strand: Option<String>,
+
+    /// Keep only alignments spanning at least this many bases on both the query and the target
+    #[arg(long, value_name = "BP")]
+    min_length: Option<i64>,
+
+    /// Keep only alignments with at least N matching bases: PAF column 10, estimated from the
+    /// differences or exact with --exact-identity
+    #[arg(long, value_name = "N")]
+    min_matches: Option<i64>,
+
+    /// Keep only '-' strand alignments of at least BP (default 1000) on both sequences, for hunting
+    /// inversions; shorthand for --strand - --min-length BP
+    #[arg(long, value_name = "BP", num_args = 0..=1, default_missing_value = "1000", conflicts_with = "strand")]
+    only_inversions: Option<i64>,
+
+    /// Keep only alignments overlapping this target region (NAME or NAME:START-END, in --coord-base convention);
+    /// seeks through the region index when `oneview-rs index` has built one
+    #[arg(long, value_name = "REGION")]
+    target_region: Option<String>,
+
+    /// Keep only alignments of this query sequence; when the file is sorted by query, binary-search
+    /// to them through the binary index instead of scanning
+    #[arg(long, value_name = "NAME", conflicts_with = "seek_target")]
+    seek_query: Option<String>,
+
+    /// Keep only alignments on this target sequence; when the file is sorted by target, binary-search
+    /// to them through the binary index instead of scanning
+    #[arg(long, value_name = "NAME")]
+    seek_target: Option<String>,
+
+    /// Trust that the file is sorted by the --seek-query/--seek-target key instead of sampling it to check;
+    /// with --section-by, that it is sorted by the section key, so sections stream
+    #[arg(long)]
+    assume_sorted: bool,
+
+    /// Group human output into one section per query or target sequence, each with a header and
+    /// subtotals. Buffers every alignment to group them, unless --assume-sorted says the file is
+    /// already sorted by that side, when only one section is held at a time
+    #[arg(long, value_enum, value_name = "SIDE")]
+    section_by: Option<track::Space>,
+
+    /// Drop exact duplicates: alignments with the same sequences, coordinates and strand as an earlier one
+    #[arg(long)]
+    dedup: bool,
+
+    /// Drop alignments whose coordinates fall outside their sequences (default: warn and emit them)
+    #[arg(long)]
+    skip_invalid: bool,
+
+    /// Name sequences query_ID/target_ID when a genome has no metadata at all; lengths are reported as 0 and
+    /// coordinates stay in contig space (reverse-strand targets in the reverse-complement frame)
+    #[arg(long)]
+    allow_anonymous: bool,
+
+    /// Skip alignments whose sequence IDs are missing from the metadata, summarizing them at the end
+    #[arg(long)]
+    skip_missing_metadata: bool,
+
+    /// Trace spacing to use when a file with tracepoints has no 't' line (otherwise that is an error)
+    #[arg(long, value_name = "N")]
+    assume_trace_spacing: Option<i64>,
+
+    /// Exit with status 0 even when the file looks truncated (the partial output is the same either way)
+    #[arg(long)]
+    ignore_truncation: bool,
+
+    /// Abort on the first alignment whose coordinates fall outside its sequences or whose T/X list
+    /// cannot be read
+    #[arg(long)]
+    strict: bool,
+
+    /// List the raw tracepoints and trace diffs in human output; with =N, only the first and last N of each
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "0")]
+    show_trace_values: Option<usize>,
+
+    /// Whether human output prints the trace lines of alignments without traces ("Trace segments: 0",
+    /// "Tracepoints: 0 values"); --emit-empty-traces false leaves them out
+    #[arg(long, value_name = "BOOL", default_value_t = true, action = clap::ArgAction::Set)]
+    emit_empty_traces: bool,
+
+    /// Print at most the first N raw trace values of each list, then '... (M more)'; implies
+    /// --show-trace-values. Unlimited by default; about 20 keeps terminal output readable
+    #[arg(long, value_name = "N")]
+    limit_traces: Option<usize>,
+
+    /// Walk through how the alignment chosen with -a is decoded: stored A line, metadata lookups,
+    /// strand flip and offset arithmetic, and a labeled PAF line
+    #[arg(long)]
+    explain: bool,
+
+    /// List the target features from this GFF3 file that each alignment overlaps, with the fraction
+    /// of each covered (ga:Z: tag in PAF, a Features line in human output)
+    #[arg(long, value_name = "GFF3")]
+    annotate: Option<String>,
+
+    /// GFF3 feature type --annotate and --gene-coverage report
+    #[arg(long, value_name = "TYPE", default_value = "gene")]
+    feature_type: String,
+
+    /// Show the alignment chosen with -a as stacked query/midline/target rows, realigned from the
+    /// tracepoints (needs --fasta for both sides)
+    #[arg(long)]
+    pretty: bool,
+
+    /// Columns per --pretty row
+    #[arg(long, value_name = "N", default_value_t = 60)]
+    pretty_width: usize,
+
+    /// Only show the part of the --pretty alignment within this query interval (in --coord-base convention)
+    #[arg(long, value_name = "START-END")]
+    pretty_region: Option<String>,
+
+    /// Print alignments last to first, seeking backward through the .1idx index when the file has one;
+    /// without it, every selected alignment is held in memory until the end of the file
+    #[arg(long)]
+    reverse_output: bool,
+
+    /// Cut printed alignments where tracepoint segments exceed divergence F (0-1), dropping those
+    /// segments; pieces carry the original alignment index (PAF tag oi:i)
+    #[arg(long, value_name = "F")]
+    split_at_divergence: Option<f64>,
+
+    /// Cut printed alignments at tracepoint segments whose query and target spans differ by more
+    /// than BP, implying a larger gap, dropping those segments (pieces as with --split-at-divergence)
+    #[arg(long, value_name = "BP")]
+    max_gap: Option<i64>,
+
+    /// Shortest run of divergent or gapped segments that --split-at-divergence and --max-gap cut at
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    min_bad_segments: usize,
+
+    /// Merge tracepoint segments to trace spacing N, a multiple of the file's, for every output including
+    /// tp:Z and --write-1aln
+    #[arg(long, value_name = "N")]
+    respace: Option<i64>,
+
+    /// Trim leading and trailing tracepoint segments with divergence above F (0-1) from every alignment
+    #[arg(long, value_name = "F")]
+    trim_ends: Option<f64>,
+
+    /// Also trim end segments with more than N differences (can be used without --trim-ends)
+    #[arg(long, value_name = "N")]
+    trim_max_diffs: Option<i64>,
+
+    /// Clip alignments crossing the --target-region boundary to the region; the query side is cut in
+    /// proportion within the tracepoint segment the boundary falls in
+    #[arg(long, requires = "target_region")]
+    clip: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum PafTag {
+    /// df:i number of differences
+    Df,
+    /// tp:Z trace-diff,tracepoint pairs
+    Tp,
+    /// NM:i edit distance, for records realigned by --exact-identity
+    Nm,
+    /// de:f estimated divergence
+    De,
+    /// id:f estimated identity
+    Id,
+    /// AS:i alignment score (see --score-*)
+    As,
+    /// ib:i net indel balance, query span minus target span
+    Ib,
+    /// cg:Z CIGAR (not supported yet)
+    Cg,
+    /// cs:Z difference string (not supported yet)
+    Cs,
+    /// ch:Z (not supported yet)
+    Ch,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum GroupBy {
+    /// Full sequence names
+    Sequence,
+    /// PanSN sample (requires --pansn)
+    Sample,
 }
 
 #[derive(Debug, Default)]
 struct AlignmentData {
+    /// Position of the alignment in the file (0-based)
+    index: usize,
+    /// Metadata IDs, kept so sequences can be looked up after renaming
+    query_id: i64,
+    target_id: i64,
     query_name: String,
+    /// Scaffold offset of the query contig: query_start - query_offset is the contig coordinate
+    query_offset: i64,
+    /// Scaffold offset of the target contig
+    target_offset: i64,
+    /// Alignments this one represents, itself included (`--collapse-redundant`)
+    cluster_size: Option<usize>,
     query_length: i64,
     query_start: i64,
     query_end: i64,
@@ -35,466 +686,3325 @@ struct AlignmentData {
     target_start: i64,
     target_end: i64,
     strand: char,
+    /// Trace spacing in effect for this record (the last 't' line before it)
+    trace_spacing: i64,
     differences: i64,
     tracepoints: Vec<i64>,
     trace_diffs: Vec<i64>,
+    /// Mapping quality assigned by a whole-file pass (`--mapq-mode unique`)
+    mapq: Option<u8>,
+    /// Exact edit counts from realignment (`--exact-identity`)
+    exact: Option<EditCounts>,
+    /// Where `differences` came from
+    differences_source: DifferencesSource,
+    /// Coordinates are negative, reversed or past the sequence length
+    out_of_bounds: bool,
+    /// Names are placeholders for a genome without metadata (`--allow-anonymous`)
+    anonymous: bool,
+    /// The record as stored, for `--explain`
+    raw: Option<Box<explain::RawRecord>>,
+    /// Position among the pieces of an alignment cut by `--split-at-divergence` or `--max-gap`;
+    /// `index` stays that of the original
+    piece: Option<usize>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum DifferencesSource {
+    /// The record's D line
+    #[default]
+    DLine,
+    /// No D line: the sum of the X trace diffs
+    TraceDiffs,
+    /// Neither D nor X: `differences` is 0 but unknown, not a perfect match
+    Missing,
+}
 
-    if args.metadata && args.paf {
-        return Err("Cannot combine --metadata with --paf output".into());
+impl AlignmentData {
+    fn query_span(&self) -> i64 {
+        (self.query_end - self.query_start).max(0)
     }
 
-    let output_format = if args.paf {
-        OutputFormat::Paf
-    } else {
-        OutputFormat::Human
-    };
-    
-    let (metadata, trace_spacing) = get_file_metadata(&args.input)?;
-    
-    match (args.metadata, args.alignment) {
-        (true, _) => {
-            // Only metadata
-            print_metadata(&metadata, trace_spacing, &args.input)?;
+    fn target_span(&self) -> i64 {
+        (self.target_end - self.target_start).max(0)
+    }
+
+    /// Positive when the query carries net inserted bases relative to the target.
+    fn indel_balance(&self) -> i64 {
+        self.query_span() - self.target_span()
+    }
+
+    /// The same alignment with the query and target roles exchanged.
+    ///
+    /// Both intervals are forward-strand, so swapping them is exact for either
+    /// strand. Tracepoints are spaced along the query and cannot be swapped
+    /// without realignment, so the result has none.
+    fn transposed(&self) -> AlignmentData {
+        AlignmentData {
+            index: self.index,
+            query_id: self.target_id,
+            target_id: self.query_id,
+            query_name: self.target_name.clone(),
+            query_offset: self.target_offset,
+            target_offset: self.query_offset,
+            cluster_size: self.cluster_size,
+            query_length: self.target_length,
+            query_start: self.target_start,
+            query_end: self.target_end,
+            target_name: self.query_name.clone(),
+            target_length: self.query_length,
+            target_start: self.query_start,
+            target_end: self.query_end,
+            strand: self.strand,
+            trace_spacing: self.trace_spacing,
+            differences: self.differences,
+            tracepoints: Vec::new(),
+            trace_diffs: Vec::new(),
+            mapq: self.mapq,
+            exact: self.exact.map(|exact| EditCounts {
+                insertions: exact.deletions,
+                deletions: exact.insertions,
+                ..exact
+            }),
+            differences_source: self.differences_source,
+            out_of_bounds: self.out_of_bounds,
+            anonymous: self.anonymous,
+            piece: self.piece,
+            raw: None,
         }
-        (false, Some(idx)) => {
-            // Only specific alignment
-            read_single_alignment(
-                &args.input,
-                idx,
-                &metadata,
-                trace_spacing,
-                output_format,
-            )?;
+    }
+
+    /// PAF column 11, summed over both sequences as ALNtoPAF does, or the
+    /// number of alignment columns when exact counts are known.
+    fn block_length(&self) -> i64 {
+        match &self.exact {
+            Some(exact) => exact.columns(),
+            None => self.query_span() + self.target_span(),
         }
-        (false, None) => {
-            // Default: metadata + all alignments
-            if output_format == OutputFormat::Human {
-                print_metadata(&metadata, trace_spacing, &args.input)?;
-                writeln!(io::stdout(), "\n=== ALIGNMENTS ===\n")?;
-            }
-            read_all_alignments(
-                &args.input,
-                &metadata,
-                trace_spacing,
-                output_format,
-            )?;
+    }
+
+    /// PAF column 10. Matches ALNtoPAF calculation (when not computing CIGAR).
+    fn matches(&self) -> i64 {
+        match &self.exact {
+            Some(exact) => exact.matches,
+            None => ((self.block_length() - self.differences) / 2).max(0),
         }
     }
-    Ok(())
-}
 
-struct FileMetadata {
-    // Query genome (gdb1 - first reference, or embedded if self-alignment)
-    query_seq_names: HashMap<i64, String>,
-    query_seq_lengths: HashMap<i64, i64>,
-    query_contig_offsets: HashMap<i64, (i64, i64)>,
+    /// Identity: matches per alignment column when exact, otherwise the
+    /// fraction of the block not accounted for by differences.
+    fn identity(&self) -> f64 {
+        let block_length = self.block_length();
+        if block_length == 0 {
+            return 0.0;
+        }
+        match &self.exact {
+            Some(exact) => exact.matches as f64 / block_length as f64,
+            None => (2 * self.matches()) as f64 / block_length as f64,
+        }
+    }
+}
 
-    // Target genome (gdb2 - second reference, or embedded skeleton)
-    target_seq_names: HashMap<i64, String>,
-    target_seq_lengths: HashMap<i64, i64>,
-    target_contig_offsets: HashMap<i64, (i64, i64)>,
+/// The process arguments from the subcommand name on, which stands in for the program name.
+fn subcommand_args() -> Vec<std::ffi::OsString> {
+    std::env::args_os().skip(1).collect()
 }
 
-fn get_file_metadata(path: &str) -> Result<(FileMetadata, i64), Box<dyn std::error::Error>> {
-    let mut file = OneFile::open_read(path, None, None, 1)?;
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Hidden maintainer subcommand, parsed on its own so it stays out of the main help
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        return bench::run(bench::BenchArgs::parse_from(config::args_with_config::<bench::BenchArgs>(subcommand_args())?));
+    }
+    if std::env::args().nth(1).as_deref() == Some("liftover") {
+        return liftover::run(liftover::LiftoverArgs::parse_from(config::args_with_config::<liftover::LiftoverArgs>(
+            subcommand_args(),
+        )?));
+    }
+    if std::env::args().nth(1).as_deref() == Some("index") {
+        return region::run(region::IndexArgs::parse_from(config::args_with_config::<region::IndexArgs>(subcommand_args())?));
+    }
+
+    let mut args = Args::parse_from(config::args_with_config::<Args>(std::env::args_os().collect())?);
+
+    if args.metadata && args.paf {
+        return Err("Cannot combine --metadata with --paf output".into());
+    }
+    if args.format.is_some() && (args.metadata || args.paf) {
+        return Err("Cannot combine --format with --metadata or --paf".into());
+    }
+    if args.maf && (args.metadata || args.paf || args.format.is_some()) {
+        return Err("Cannot combine --maf with --metadata, --paf or --format".into());
+    }
+    if args.bedpe && (args.metadata || args.paf || args.maf || args.format.is_some()) {
+        return Err("Cannot combine --bedpe with --metadata, --paf, --maf or --format".into());
+    }
+    let report_modes = [
+        args.stats,
+        args.stats_approx,
+        args.stats_pairs,
+        args.per_target_counts,
+        args.chain_score,
+        args.stats_per_target,
+        args.stats_per_query,
+        args.query_covered_bed.is_some(),
+        args.query_summary,
+        args.orientation_stats,
+        args.identity_bins.is_some(),
+        args.identity_track.is_some(),
+        args.call_vcf.is_some(),
+        args.target_depth_bedgraph.is_some(),
+        args.depth_hist,
+        args.gene_coverage.is_some(),
+        args.validate,
+        args.verify_diffs,
+        args.compare.is_some(),
+    ];
+    let report_mode_count = report_modes.iter().filter(|&&mode| mode).count();
+    if report_mode_count > 1 {
+        return Err("Only one of --stats, --stats-approx, --stats-pairs, --per-target-counts, --chain-score, --stats-per-target, --stats-per-query, --query-covered-bed, --query-summary, --orientation-stats, --identity-bins, --identity-track, --call-vcf, --target-depth-bedgraph, --depth-hist, --gene-coverage, --validate, --verify-diffs and --compare can be used".into());
+    }
+    let default_format = std::env::var("ONEVIEW_DEFAULT_FORMAT").map(|format| format.trim().to_ascii_lowercase());
+    // JSON exists only for the summary statistics, so json as a default turns on --json there
+    if (args.stats || args.stats_approx) && default_format.as_deref() == Ok("json") {
+        args.json = true;
+    }
+    if args.json && !(args.stats || args.stats_approx) {
+        return Err("--json requires --stats or --stats-approx".into());
+    }
+    if report_mode_count == 1
+        && (args.metadata || args.paf || args.maf || args.bedpe || args.format.is_some() || args.alignment.is_some())
+    {
+        return Err("Report modes cannot be combined with --metadata, --paf, --maf, --bedpe, --format or --alignment".into());
+    }
+    // Precedence: output options on the command line, then ONEVIEW_DEFAULT_FORMAT, then human
+    if report_mode_count == 0
+        && !(args.metadata
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf
+            || args.schema
+            || args.pretty
+            || args.explain
+            || args.extract_fasta.is_some()
+            || args.write_1aln.is_some()
+            || args.interactive
+            || args.section_by.is_some())
+        && let Ok(format) = &default_format
+    {
+        match format.as_str() {
+            "" | "human" | "json" => {}
+            "paf" => args.paf = true,
+            "maf" => args.maf = true,
+            "bedpe" => args.bedpe = true,
+            _ => {
+                return Err(format!(
+                    "Invalid ONEVIEW_DEFAULT_FORMAT '{}': expected human, paf, maf, bedpe, or json (which only applies to --stats and --stats-approx)",
+                    format
+                ).into());
+            }
+        }
+    }
+    if args.chain_max_gap < 0 {
+        return Err("--chain-max-gap must not be negative".into());
+    }
+    if args.tolerance < 0 {
+        return Err("--tolerance must not be negative".into());
+    }
+    if args.sample == Some(0) {
+        return Err("--sample must be at least 1".into());
+    }
+    if args.identity_bins.as_ref().is_some_and(|thresholds| thresholds.iter().any(|threshold| !(0.0..=1.0).contains(threshold))) {
+        return Err("--identity-bins thresholds must be between 0 and 1".into());
+    }
+    if args.color_thresholds.iter().any(|threshold| !(0.0..=1.0).contains(threshold))
+        || args.color_thresholds[0] < args.color_thresholds[1]
+    {
+        return Err("--color-thresholds must be HIGH,LOW between 0 and 1 with HIGH >= LOW".into());
+    }
+    if !(0.0..=0.5).contains(&args.mixed_orientation_frac) {
+        return Err("--mixed-orientation-frac must be between 0 and 0.5".into());
+    }
+    if args.min_covered_frac.is_some_and(|fraction| !(0.0..=1.0).contains(&fraction)) {
+        return Err("--min-covered-frac must be between 0 and 1".into());
+    }
+    if let (Some(min), Some(max)) = (args.min_query_len, args.max_query_len)
+        && min > max
+    {
+        return Err("--min-query-len must not exceed --max-query-len".into());
+    }
+    if let (Some(min), Some(max)) = (args.min_target_len, args.max_target_len)
+        && min > max
+    {
+        return Err("--min-target-len must not exceed --max-target-len".into());
+    }
+    if !(args.collapse_frac > 0.0 && args.collapse_frac <= 1.0) {
+        return Err("--collapse-frac must be greater than 0 and at most 1".into());
+    }
+    if args.max_per_pair == Some(0) {
+        return Err("--max-per-pair must be at least 1".into());
+    }
+    if args.depth_hist_max == 0 {
+        return Err("--depth-hist-max must be at least 1".into());
+    }
+    if args.identity_window <= 0 {
+        return Err("--identity-window must be positive".into());
+    }
+    if args.assume_trace_spacing.is_some_and(|trace_spacing| trace_spacing <= 0) {
+        return Err("--assume-trace-spacing must be positive".into());
+    }
+    if args.split_at_divergence.is_some_and(|divergence| !(0.0..=1.0).contains(&divergence)) {
+        return Err("--split-at-divergence must be between 0 and 1".into());
+    }
+    if args.trim_ends.is_some_and(|divergence| !(0.0..=1.0).contains(&divergence)) {
+        return Err("--trim-ends must be between 0 and 1".into());
+    }
+    if args.trim_max_diffs.is_some_and(|diffs| diffs < 0) {
+        return Err("--trim-max-diffs must not be negative".into());
+    }
+    if args.min_bad_segments == 0 {
+        return Err("--min-bad-segments must be at least 1".into());
+    }
+    if args.skip_invalid && args.strict {
+        return Err("Cannot combine --skip-invalid with --strict".into());
+    }
+    if args.pansn.is_none() {
+        if args.group_by == GroupBy::Sample {
+            return Err("--group-by sample requires --pansn".into());
+        }
+        if args.query_sample.is_some() || args.target_sample.is_some() {
+            return Err("--query-sample/--target-sample require --pansn".into());
+        }
+    }
+
+    if args.check_refs {
+        let resolve_options = resolve_options(&args)?;
+        let result = check_references(&args.input, &resolve_options);
+        if let Some(map) = &resolve_options.path_map {
+            map.report_unused();
+        }
+        return result;
+    }
+    if args.schema {
+        let schema = schema::read_schema(&args.input)?;
+        schema::write_schema(&mut io::stdout().lock(), &schema)?;
+        return Ok(());
+    }
+    if args.version_info {
+        let header = schema::read_header(&args.input)?;
+        schema::write_version_info(&mut io::stdout().lock(), &args.input, &header)?;
+        return Ok(());
+    }
+
+    if args.write_1aln.is_some()
+        && (args.metadata
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf
+            || report_mode_count > 0)
+    {
+        return Err("--write-1aln cannot be combined with other output modes".into());
+    }
+    if args.pretty
+        && (args.alignment.is_none()
+            || args.metadata
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf
+            || args.extract_fasta.is_some()
+            || args.write_1aln.is_some()
+            || report_mode_count > 0)
+    {
+        return Err("--pretty shows a single alignment: it needs -a N and no other output mode".into());
+    }
+    if args.explain
+        && (args.alignment.is_none()
+            || args.pretty
+            || args.metadata
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf)
+    {
+        return Err("--explain describes a single alignment: it needs -a N and no other output mode".into());
+    }
+    if args.annotate.is_some()
+        && (args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf
+            || args.transpose
+            || args.metadata
+            || report_mode_count > 0)
+    {
+        return Err("--annotate applies to human and PAF alignment output, without --transpose".into());
+    }
+    if args.pretty_region.is_some() && !args.pretty {
+        return Err("--pretty-region requires --pretty".into());
+    }
+    if args.pretty_width < 10 {
+        return Err("--pretty-width must be at least 10".into());
+    }
+    if args.section_by.is_some()
+        && (args.alignment.is_some()
+            || args.metadata
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || report_mode_count > 0
+            || args.write_1aln.is_some()
+            || args.extract_fasta.is_some())
+    {
+        return Err("--section-by applies to human output of all alignments".into());
+    }
+    if args.interactive
+        && (report_mode_count > 0
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf
+            || args.write_1aln.is_some()
+            || args.extract_fasta.is_some()
+            || args.section_by.is_some()
+            || args.reverse_output)
+    {
+        return Err("--interactive is a viewer of its own and cannot be combined with output formats or report modes".into());
+    }
+    if args.interactive && !io::stdout().is_terminal() {
+        return Err("--interactive needs a terminal on stdout".into());
+    }
+    if args.select_alignments.is_some()
+        && (report_mode_count > 0 || args.write_1aln.is_some() || args.extract_fasta.is_some() || args.reverse_output)
+    {
+        return Err("--select-alignments cannot be combined with report modes, --write-1aln, --extract-fasta or --reverse-output".into());
+    }
+    if args.reverse_output && (args.alignment.is_some() || args.metadata || report_mode_count > 0 || args.write_1aln.is_some()) {
+        return Err("--reverse-output applies to printing all alignments and cannot be combined with --alignment, --metadata, --write-1aln or report modes".into());
+    }
+    if (args.split_at_divergence.is_some() || args.max_gap.is_some())
+        && (args.metadata || report_mode_count > 0 || args.write_1aln.is_some())
+    {
+        return Err("--split-at-divergence and --max-gap apply to printed alignments and cannot be combined with --metadata, --write-1aln or report modes".into());
+    }
+    if args.max_gap.is_some_and(|max_gap| max_gap < 0) {
+        return Err("--max-gap must not be negative".into());
+    }
+    if args.extract_fasta.is_some()
+        && (args.metadata
+            || args.paf
+            || args.maf
+            || args.bedpe
+            || args.format.is_some()
+            || args.compat_alntopaf
+            || args.write_1aln.is_some()
+            || report_mode_count > 0)
+    {
+        return Err("--extract-fasta cannot be combined with other output modes".into());
+    }
+    if args.transpose && !(args.paf || args.compat_alntopaf || args.write_1aln.is_some()) {
+        return Err("--transpose needs --paf, --compat-alntopaf or --write-1aln".into());
+    }
+    if args.compat_alntopaf && (args.metadata || args.maf || args.bedpe || args.format.is_some()) {
+        return Err("--compat-alntopaf produces PAF and cannot be combined with --metadata, --maf, --bedpe or --format".into());
+    }
+    if args.compat_alntopaf && args.exact_identity {
+        return Err("--compat-alntopaf reproduces ALNtoPAF's estimates and cannot be combined with --exact-identity".into());
+    }
+
+    let tags = if args.no_tags || args.compat_alntopaf { Vec::new() } else { args.tags.clone() };
+    if tags.iter().any(|tag| matches!(tag, PafTag::Cg | PafTag::Cs | PafTag::Ch)) {
+        return Err("The cg, cs and ch tags need base-level alignment reconstruction, which is not supported yet".into());
+    }
+
+    if args.mapq_mode == MapqMode::Unique && args.alignment.is_some() {
+        return Err("--mapq-mode unique compares against all alignments and cannot be used with --alignment".into());
+    }
+
+    let output_format = if args.paf || args.compat_alntopaf {
+        OutputFormat::Paf
+    } else if args.maf {
+        OutputFormat::Maf
+    } else if args.bedpe {
+        OutputFormat::Bedpe
+    } else if args.format.is_some() {
+        OutputFormat::Template
+    } else {
+        OutputFormat::Human
+    };
+    if args.paf_header && (output_format != OutputFormat::Paf || report_mode_count > 0) {
+        return Err("--paf-header needs PAF output (--paf or --compat-alntopaf)".into());
+    }
+    let pansn = args.pansn.map(PanSn::new);
+    let mut output = OutputConfig {
+        format: output_format,
+        pansn,
+        names: NameSanitizer::new(args.name_policy.clone()),
+        coord_base: args.coord_base,
+        template: args.format.clone(),
+        tags,
+        mapq_mode: if args.compat_alntopaf { MapqMode::Constant(255) } else { args.mapq_mode },
+        compat_alntopaf: args.compat_alntopaf,
+        transpose: args.transpose,
+        // --limit-traces alone shows the lists, cut after N values
+        trace_values: args.show_trace_values.or(args.limit_traces.map(|_| 0)),
+        trace_limit: args.limit_traces,
+        print_offsets: args.print_offsets,
+        collapsed: args.collapse_redundant,
+        empty_traces: args.emit_empty_traces,
+        // The viewer lays out plain text itself
+        style: style::Style::new((args.color.enabled() && !args.interactive).then_some(style::Palette {
+            high: args.color_thresholds[0],
+            low: args.color_thresholds[1],
+        })),
+        sections: args.section_by.map(|side| Sections { side, sorted: args.assume_sorted }),
+        pretty: if args.pretty {
+            Some(pretty::PrettyOptions {
+                width: args.pretty_width,
+                region: args.pretty_region.as_deref().map(|region| parse_region(region, args.coord_base)).transpose()?,
+                color: args.color.enabled(),
+                coord_base: args.coord_base,
+            })
+        } else {
+            None
+        },
+        split: (args.split_at_divergence.is_some() || args.max_gap.is_some()).then_some(split::SegmentSplit {
+            max_divergence: args.split_at_divergence,
+            max_gap: args.max_gap,
+            min_bad_segments: args.min_bad_segments,
+        }),
+        annotations: None,
+        sequences: Sequences::default(),
+        scoring: ScoringModel {
+            match_score: args.score_match,
+            mismatch: args.score_mismatch,
+            gap_open: args.score_gap_open,
+            gap_extend: args.score_gap_extend,
+        },
+    };
+    let streaming = is_pipe(&args.input);
+    if streaming && args.alignment.is_some() {
+        return Err("--alignment needs random access and cannot read from a pipe; use a regular file".into());
+    }
+    if streaming && args.interactive {
+        return Err("--interactive needs random access and cannot read from a pipe; use a regular file".into());
+    }
+    if streaming && args.select_alignments.is_some() {
+        return Err("--select-alignments needs random access and cannot read from a pipe; use a regular file".into());
+    }
+    if streaming && args.write_1aln.is_some() {
+        return Err("--write-1aln copies the input header and cannot read from a pipe; use a regular file".into());
+    }
+    let resolve_options = resolve_options(&args)?;
+    let fai_metadata = fai::FaiMetadata {
+        query: args.query_fai.as_deref().map(|path| fai::read_tables(path, args.query_fai_ids.as_deref())).transpose()?,
+        target: args.target_fai.as_deref().map(|path| fai::read_tables(path, args.target_fai_ids.as_deref())).transpose()?,
+    };
+    let (mut metadata, trace_spacing, stream) =
+        get_file_metadata(&args.input, args.assume_trace_spacing, streaming, &resolve_options, fai_metadata)?;
+    if let Some(map) = &resolve_options.path_map {
+        map.report_unused();
+    }
+    if let Some(new_spacing) = args.respace {
+        if trace_spacing == 0 {
+            return Err("--respace needs tracepoints, and this file has none".into());
+        }
+        if new_spacing <= 0 || new_spacing % trace_spacing != 0 {
+            return Err(format!(
+                "--respace {} is not a positive multiple of the file's trace spacing {}; segments can only be merged, not split",
+                new_spacing, trace_spacing
+            ).into());
+        }
+    }
+
+    let excluded = ExcludedSequences {
+        query: excluded_ids(&metadata.query_seq_lengths, args.min_query_len, args.max_query_len),
+        target: excluded_ids(&metadata.target_seq_lengths, args.min_target_len, args.max_target_len),
+    };
+    if args.min_query_len.or(args.max_query_len).or(args.min_target_len).or(args.max_target_len).is_some() {
+        let total = |ids: &HashSet<i64>, lengths: &HashMap<i64, i64>| -> i64 { ids.iter().filter_map(|id| lengths.get(id)).sum() };
+        eprintln!(
+            "Excluding alignments on {} query sequences ({} bp) and {} target sequences ({} bp) by sequence length",
+            excluded.query.len(),
+            total(&excluded.query, &metadata.query_seq_lengths),
+            excluded.target.len(),
+            total(&excluded.target, &metadata.target_seq_lengths)
+        );
+    }
+    let mut strand = args.strand.as_deref().and_then(|strand| strand.chars().next());
+    let mut min_length = args.min_length;
+    if let Some(threshold) = args.only_inversions {
+        // An explicit --min-length can only tighten the threshold
+        let threshold = min_length.map_or(threshold, |min_length| min_length.max(threshold));
+        strand = Some('-');
+        min_length = Some(threshold);
+        eprintln!(
+            "--only-inversions: keeping '-' strand alignments of at least {} bp on both query and target",
+            threshold
+        );
+    }
+    let mut filter = AlignmentFilter {
+        pansn,
+        query_sample: args.query_sample.clone(),
+        target_sample: args.target_sample.clone(),
+        skip_invalid: args.skip_invalid,
+        strand,
+        min_length,
+        min_matches: args.min_matches,
+        uncovered_query_ids: HashSet::new(),
+        pair_survivors: None,
+        target_region: args.target_region.as_deref().map(|region| parse_target_region(region, args.coord_base)).transpose()?,
+        seek: match (&args.seek_query, &args.seek_target) {
+            (Some(name), _) => Some(SeekKey { side: track::Space::Query, name: name.clone(), assume_sorted: args.assume_sorted }),
+            (None, Some(name)) => Some(SeekKey { side: track::Space::Target, name: name.clone(), assume_sorted: args.assume_sorted }),
+            (None, None) => None,
+        },
+        seen: args.dedup.then(|| RefCell::new(HashSet::new())),
+        duplicates: Cell::new(0),
+    };
+
+    // Capture FASTA lookups before renaming changes the names
+    output.sequences = open_fasta(&args.fasta, &metadata)?;
+    if output.pretty.is_some() && (output.sequences.query.is_none() || output.sequences.target.is_none()) {
+        return Err("--pretty needs --fasta query=PATH target=PATH".into());
+    }
+    if args.extract_fasta.is_some() && (output.sequences.query.is_none() || output.sequences.target.is_none()) {
+        return Err("--extract-fasta needs --fasta query=PATH target=PATH".into());
+    }
+    if args.call_vcf.is_some() && (output.sequences.query.is_none() || output.sequences.target.is_none()) {
+        return Err("--call-vcf needs --fasta query=PATH target=PATH".into());
+    }
+    if args.verify_diffs && (output.sequences.query.is_none() || output.sequences.target.is_none()) {
+        return Err("--verify-diffs needs --fasta query=PATH target=PATH".into());
+    }
+    if args.preload {
+        for genome in [&output.sequences.query, &output.sequences.target].into_iter().flatten() {
+            genome.preload()?;
+        }
+    }
+
+    let exact_identity = args.exact_identity
+        && if output.sequences.query.is_some() && output.sequences.target.is_some() {
+            true
+        } else {
+            eprintln!("Warning: --exact-identity needs --fasta query=PATH target=PATH; using tracepoint estimates");
+            false
+        };
+
+    // Rename before anything looks at names, so every output and filter sees the new ones
+    rename_metadata(&args, &mut metadata)?;
+
+    if let Some(pansn) = &pansn {
+        report_unmatched_pansn_names(pansn, &metadata);
+    }
+    if let Some(gff_path) = &args.annotate {
+        let annotations = gff::FeatureIndex::read(gff_path, &args.feature_type)?;
+        eprintln!("Loaded {} {} features from {}", annotations.feature_count(), args.feature_type, gff_path);
+        annotations.report_unknown_sequences(metadata.target_seq_names.values());
+        output.annotations = Some(annotations);
+    }
+
+    let trim_ends = (args.trim_ends.is_some() || args.trim_max_diffs.is_some()).then_some(split::TrimEnds {
+        max_divergence: args.trim_ends,
+        max_diffs: args.trim_max_diffs,
+    });
+    let clip = filter.target_region.as_ref().filter(|_| args.clip).map(|region| split::Clip {
+        target_name: region.name.clone(),
+        start: region.start,
+        end: region.end,
+    });
+    let mut ctx = ParseContext {
+        metadata: &metadata,
+        length_source: args.length_source,
+        // Trace lists can be huge; don't materialize them when nothing prints them
+        read_traces: match output.format {
+            OutputFormat::Paf => output.tags.contains(&PafTag::Tp),
+            OutputFormat::Bedpe => false,
+            // Depth and coverage only need coordinates
+            _ => args.target_depth_bedgraph.is_none() && args.gene_coverage.is_none() && args.query_covered_bed.is_none()
+                && !args.depth_hist
+                && !args.query_summary
+                && !args.orientation_stats,
+        } || output.split.is_some()
+            || trim_ends.is_some()
+            || clip.is_some()
+            || args.respace.is_some()
+            || exact_identity,
+        trace_spacing,
+        sequences: &output.sequences,
+        exact_identity,
+        strict: args.strict,
+        skip_missing_metadata: args.skip_missing_metadata,
+        allow_anonymous: args.allow_anonymous,
+        trim_ends,
+        clip,
+        respace: args.respace,
+        explain: args.explain,
+        stream,
+        excluded,
+        representatives: None,
+        warnings: ParseWarnings::default(),
+    };
+    // What the output reports and writes; records are still read at the file's own spacing
+    let output_trace_spacing = args.respace.unwrap_or(trace_spacing);
+    if let Some(min_fraction) = args.min_covered_frac {
+        filter.uncovered_query_ids = uncovered_query_ids(&args.input, &mut ctx, &filter, min_fraction)?;
+    }
+    if args.collapse_redundant {
+        ctx.representatives = Some(redundancy_representatives(&args.input, &mut ctx, &filter, args.collapse_frac)?);
+    }
+    if let Some(max) = args.max_per_pair {
+        filter.pair_survivors = Some(pair_survivors(&args.input, &mut ctx, &filter, max)?);
+    }
+    // Only human alignment and metadata output is paged; tables and record formats never are
+    let page = !args.no_pager
+        && output.format == OutputFormat::Human
+        && report_mode_count == 0
+        && !args.interactive
+        && args.write_1aln.is_none()
+        && args.extract_fasta.is_none()
+        && io::stdout().is_terminal();
+    let pager = if page { pager::Pager::start()? } else { None };
+    let mut result = run(&args, &mut ctx, output_trace_spacing, &filter, &output);
+    if let Some(pager) = pager {
+        // Quitting the pager early is a normal way to stop reading
+        if result.as_ref().is_err_and(|e| pager::is_broken_pipe(e.as_ref())) {
+            result = Ok(());
+        }
+        pager.finish();
+    }
+    ctx.warnings.report();
+    if args.dedup {
+        eprintln!("Removed {} duplicate alignments", filter.duplicates.get());
+    }
+    result?;
+    if ctx.warnings.truncated.is_some() && !args.ignore_truncation {
+        // Everything before the truncation was emitted; the status tells pipelines it is partial
+        std::process::exit(EXIT_TRUNCATED);
+    }
+    Ok(())
+}
+
+/// FASTA lookups for the `--fasta` arguments, by the metadata's current names.
+fn open_fasta(specs: &[String], metadata: &FileMetadata) -> Result<Sequences, Box<dyn std::error::Error>> {
+    let mut sequences = Sequences::default();
+    for spec in specs {
+        match spec.split_once('=') {
+            Some(("query", path)) if sequences.query.is_none() => {
+                sequences.query = Some(GenomeFasta::new(path, metadata.query_seq_names.clone()));
+            }
+            Some(("target", path)) if sequences.target.is_none() => {
+                sequences.target = Some(GenomeFasta::new(path, metadata.target_seq_names.clone()));
+            }
+            _ => {
+                return Err(format!(
+                    "Invalid --fasta argument '{}': expected query=PATH or target=PATH, each at most once",
+                    spec
+                ).into());
+            }
+        }
+    }
+    Ok(sequences)
+}
+
+/// Apply the rename maps and prefix options to the metadata's names.
+fn rename_metadata(args: &Args, metadata: &mut FileMetadata) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(map_path) = &args.id_map {
+        let map = rename::read_name_map(map_path, true)?;
+        rename::rename_sequences(&mut metadata.query_seq_names, &map, args.rename_strict, "Query")?;
+        rename::rename_sequences(&mut metadata.target_seq_names, &map, args.rename_strict, "Target")?;
+    }
+    if let Some(map_path) = &args.rename_query {
+        let map = rename::read_name_map(map_path, false)?;
+        rename::rename_sequences(&mut metadata.query_seq_names, &map, args.rename_strict, "Query")?;
+    }
+    if let Some(map_path) = &args.rename_target {
+        let map = rename::read_name_map(map_path, false)?;
+        rename::rename_sequences(&mut metadata.target_seq_names, &map, args.rename_strict, "Target")?;
+    }
+    // Order: rename map, then strip prefix, then add prefix
+    rename::apply_prefixes(
+        &mut metadata.query_seq_names,
+        args.strip_prefix_query.as_deref(),
+        args.add_prefix_query.as_deref(),
+    );
+    rename::apply_prefixes(
+        &mut metadata.target_seq_names,
+        args.strip_prefix_target.as_deref(),
+        args.add_prefix_target.as_deref(),
+    );
+    Ok(())
+}
+
+fn resolve_options(args: &Args) -> Result<ResolveOptions, Box<dyn std::error::Error>> {
+    Ok(ResolveOptions {
+        gdb_dirs: args.gdb_dir.clone(),
+        path_map: args.paths_from.as_deref().map(resolve::PathMap::read).transpose()?,
+        prefer: args.prefer,
+        resolve_symlinks: args.resolve_symlinks,
+    })
+}
+
+/// Dispatch to the selected mode once metadata is loaded.
+fn run(
+    args: &Args,
+    ctx: &mut ParseContext,
+    trace_spacing: i64,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.stats || args.stats_approx {
+        let summary = if args.stats_approx { SummaryStats::approximate() } else { SummaryStats::default() };
+        return print_summary_stats(&args.input, ctx, filter, summary, args.json);
+    }
+    if args.stats_pairs {
+        return print_pair_stats(&args.input, ctx, filter, args.group_by, output.pansn, &output.names);
+    }
+    if args.per_target_counts {
+        return print_per_target_counts(&args.input, ctx, filter, &output.names);
+    }
+    if args.chain_score {
+        return print_chain_scores(&args.input, ctx, filter, &output.names, args.chain_max_gap);
+    }
+    if let Some(thresholds) = &args.identity_bins {
+        let bins = IdentityBins::new(thresholds.clone());
+        return print_identity_bins(&args.input, ctx, filter, bins, args.group_by, output);
+    }
+    if args.orientation_stats {
+        let stats = OrientationStats::new(args.mixed_orientation_frac, args.sort_by_span);
+        return print_orientation_stats(&args.input, ctx, filter, stats, args.group_by, output);
+    }
+    if args.query_summary {
+        return print_query_summary(&args.input, ctx, filter, &output.names, args.min_count);
+    }
+    if let Some(bed_path) = &args.query_covered_bed {
+        return write_query_covered_bed(&args.input, bed_path, ctx, filter, output);
+    }
+    if args.stats_per_target || args.stats_per_query {
+        return print_sequence_stats(&args.input, ctx, filter, &output.names, args.stats_per_target);
+    }
+    if let Some(track_path) = &args.identity_track {
+        let track = track::IdentityTrack::new(args.space, args.combine);
+        return write_identity_track(&args.input, track_path, track, args.identity_window, ctx, filter, output);
+    }
+    if let Some(gff_path) = &args.gene_coverage {
+        let features = gff::FeatureIndex::read(gff_path, &args.feature_type)?;
+        eprintln!("Loaded {} {} features from {}", features.feature_count(), args.feature_type, gff_path);
+        features.report_unknown_sequences(ctx.metadata.target_seq_names.values());
+        let coverage = coverage::GeneCoverage::new(features);
+        return write_gene_coverage(&args.input, args.output_path.as_deref(), coverage, ctx, filter, output);
+    }
+    if args.depth_hist {
+        return print_depth_histogram(&args.input, ctx, filter, args.space, args.depth_hist_max, output);
+    }
+    if let Some(depth_path) = &args.target_depth_bedgraph {
+        return write_target_depth(&args.input, depth_path, ctx, filter, output);
+    }
+    if let Some(vcf_path) = &args.call_vcf {
+        return call_variants(&args.input, vcf_path, ctx, filter, output);
+    }
+    if args.validate {
+        return validate_alignments(&args.input, ctx, filter);
+    }
+    if args.verify_diffs {
+        return verify_differences(&args.input, ctx, filter, args.sample, args.tolerance, args.strict);
+    }
+    if let Some(other) = &args.compare {
+        return compare_files(args, other, ctx, filter, output);
+    }
+    
+    if let Some(prefix) = &args.extract_fasta {
+        return extract_fasta(&args.input, prefix, ctx, filter, output);
+    }
+    if let Some(out_path) = &args.write_1aln {
+        return write_alignments(&args.input, out_path, ctx, trace_spacing, filter, args.transpose);
+    }
+
+    if output.format == OutputFormat::Maf {
+        print_maf_header(&mut io::stdout().lock(), &output.sequences)?;
+    }
+    if args.paf_header {
+        print_paf_header(&mut io::stdout().lock(), output, ctx.exact_identity)?;
+    }
+
+    if args.interactive {
+        return interactive::run(&args.input, ctx, filter, output);
+    }
+    if let Some(list) = &args.select_alignments {
+        return read_selected_alignments(&args.input, list, ctx, filter, output, args.preserve_order);
+    }
+
+    match (args.metadata, args.alignment) {
+        (true, _) => {
+            // Only metadata
+            print_metadata(ctx.metadata, trace_spacing, &args.input)?;
+        }
+        (false, Some(idx)) => {
+            // Only specific alignment
+            read_single_alignment(&args.input, idx, ctx, filter, output)?;
+        }
+        (false, None) => {
+            // Default: metadata + all alignments
+            if output.format == OutputFormat::Human {
+                print_metadata(ctx.metadata, trace_spacing, &args.input)?;
+                writeln!(io::stdout(), "\n=== ALIGNMENTS ===\n")?;
+            }
+            read_all_alignments(&args.input, ctx, filter, output, args.reverse_output)?;
+        }
+    }
+    Ok(())
+}
+
+struct FileMetadata {
+    // Query genome (gdb1 - first reference, or embedded if self-alignment)
+    query_seq_names: HashMap<i64, String>,
+    query_seq_lengths: HashMap<i64, i64>,
+    query_contig_offsets: HashMap<i64, (i64, i64)>,
+
+    // Target genome (gdb2 - second reference, or embedded skeleton)
+    target_seq_names: HashMap<i64, String>,
+    target_seq_lengths: HashMap<i64, i64>,
+    target_contig_offsets: HashMap<i64, (i64, i64)>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum LengthSource {
+    /// Sequence lengths from the GDB metadata
+    Metadata,
+    /// Lengths stored with each alignment record, falling back to metadata when absent
+    Aline,
+}
+
+/// Recoverable oddities seen while parsing, reported once at the end of the run.
+#[derive(Debug, Default)]
+struct ParseWarnings {
+    /// Records whose own lengths disagree with the metadata
+    length_mismatches: usize,
+    /// Records without lengths under `--length-source aline`
+    missing_record_lengths: usize,
+    /// Records `--exact-identity` could not realign (no usable trace)
+    exact_unavailable: usize,
+    /// Records without a D line, by how the difference count was recovered
+    differences_from_trace: usize,
+    differences_missing: usize,
+    /// Records with coordinates outside their sequences, and the first one described
+    out_of_bounds: usize,
+    first_out_of_bounds: Option<String>,
+    /// Records whose scaffold interval leaves the contig it was recorded on,
+    /// and the first one described
+    contig_overruns: usize,
+    first_contig_overrun: Option<String>,
+    /// T or X lines whose list could not be read, and the first one described
+    unreadable_traces: usize,
+    first_unreadable_trace: Option<String>,
+    /// Records skipped under `--skip-missing-metadata`, and how many hit each (side, ID)
+    skipped_missing_metadata: usize,
+    missing_metadata: BTreeMap<(&'static str, i64), usize>,
+    /// Alignments promised by the header and found, when fewer were found
+    truncated: Option<(i64, usize)>,
+    /// Distinct values of the 't' lines read during the pass
+    trace_spacings: BTreeSet<i64>,
+    /// Records shortened by `--trim-ends`, the bases removed from each side,
+    /// and records with nothing left, which are dropped
+    trimmed: usize,
+    trimmed_query_bases: i64,
+    trimmed_target_bases: i64,
+    trimmed_away: usize,
+    /// Records cut to the region by `--clip`, and records crossing its
+    /// boundary that had no usable trace to cut and were kept whole
+    clipped: usize,
+    unclipped: usize,
+}
+
+impl ParseWarnings {
+    fn report(&self) {
+        if self.length_mismatches > 0 {
+            eprintln!(
+                "Warning: {} alignments carry sequence lengths that differ from the GDB metadata (see --length-source)",
+                self.length_mismatches
+            );
+        }
+        if self.missing_record_lengths > 0 {
+            eprintln!(
+                "Warning: {} alignments have no length line; used metadata lengths instead",
+                self.missing_record_lengths
+            );
+        }
+        if self.differences_from_trace > 0 {
+            eprintln!(
+                "Warning: {} alignments have no D line; differences were summed from their X trace diffs",
+                self.differences_from_trace
+            );
+        }
+        if self.differences_missing > 0 {
+            eprintln!(
+                "Warning: {} alignments have neither D nor X lines; their difference counts are unknown (df:i:-1)",
+                self.differences_missing
+            );
+        }
+        if self.out_of_bounds > 0 {
+            eprintln!(
+                "Warning: {} alignments had coordinates exceeding sequence bounds; first: {}",
+                self.out_of_bounds,
+                self.first_out_of_bounds.as_deref().unwrap_or("")
+            );
+        }
+        if self.contig_overruns > 0 {
+            eprintln!(
+                "Warning: {} alignments cross a contig boundary in scaffold coordinates, which points at corrupt \
+                 contig offsets in the metadata; first: {}",
+                self.contig_overruns,
+                self.first_contig_overrun.as_deref().unwrap_or("")
+            );
+        }
+        if self.unreadable_traces > 0 {
+            eprintln!(
+                "Warning: {} T/X lines could not be read and were left empty, so trace-based output for \
+                 those alignments is incomplete; first: {}",
+                self.unreadable_traces,
+                self.first_unreadable_trace.as_deref().unwrap_or("")
+            );
+        }
+        if self.skipped_missing_metadata > 0 {
+            eprintln!(
+                "Warning: skipped {} alignments whose sequence IDs are missing from the metadata:",
+                self.skipped_missing_metadata
+            );
+            eprintln!("  side\tid\talignments");
+            for ((side, id), count) in &self.missing_metadata {
+                eprintln!("  {}\t{}\t{}", side, id, count);
+            }
+        }
+        if self.trace_spacings.len() > 1 {
+            let values: Vec<String> = self.trace_spacings.iter().map(|value| value.to_string()).collect();
+            eprintln!(
+                "Warning: the file has several trace spacings ({}); each alignment uses the last one before it",
+                values.join(", ")
+            );
+        }
+        if let Some((expected, found)) = self.truncated {
+            eprintln!(
+                "Warning: file appears truncated: header promises {} alignments, found {}",
+                expected, found
+            );
+        }
+        if self.exact_unavailable > 0 {
+            eprintln!(
+                "Warning: {} alignments could not be realigned and use tracepoint estimates",
+                self.exact_unavailable
+            );
+        }
+        if self.trimmed > 0 || self.trimmed_away > 0 {
+            eprintln!(
+                "Trimmed {} query and {} target bases from {} alignments; dropped {} alignments divergent throughout",
+                self.trimmed_query_bases, self.trimmed_target_bases, self.trimmed, self.trimmed_away
+            );
+        }
+        if self.clipped > 0 {
+            eprintln!("Clipped {} alignments to the target region", self.clipped);
+        }
+        if self.unclipped > 0 {
+            eprintln!(
+                "Warning: {} alignments crossing the target region boundary have no usable trace and were not clipped",
+                self.unclipped
+            );
+        }
+    }
+}
+
+/// A non-seekable input (FIFO or pipe), left open by the metadata pass so
+/// the alignments are read in the same single pass.
+struct StreamedInput {
+    file: OneFile,
+    /// The line the metadata pass stopped at
+    current_line: char,
+}
+
+/// FIFOs and character devices can only be read once, front to back.
+fn is_pipe(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        std::fs::metadata(path)
+            .map(|metadata| metadata.file_type().is_fifo() || metadata.file_type().is_char_device())
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Everything `parse_alignment` needs besides the file, shared across a run.
+struct ParseContext<'a> {
+    metadata: &'a FileMetadata,
+    length_source: LengthSource,
+    /// Whether to copy the T/X lists into each `AlignmentData`
+    read_traces: bool,
+    /// The current trace spacing, updated by 't' lines as the file is read
+    trace_spacing: i64,
+    sequences: &'a Sequences,
+    /// Realign each record against `sequences` to get exact edit counts
+    exact_identity: bool,
+    /// Fail on records with out-of-bounds coordinates or unreadable trace lists instead of counting them
+    strict: bool,
+    /// Skip records whose sequence IDs are missing from the metadata instead of failing
+    skip_missing_metadata: bool,
+    /// Synthesize placeholders for a side whose metadata is entirely missing
+    allow_anonymous: bool,
+    /// Drop divergent end segments from every record (`--trim-ends`)
+    trim_ends: Option<split::TrimEnds>,
+    /// Cut every record to the `--target-region` (`--clip`)
+    clip: Option<split::Clip>,
+    /// Coarser trace spacing to merge every record's segments to (`--respace`)
+    respace: Option<i64>,
+    /// Keep each record's stored form (`--explain`)
+    explain: bool,
+    /// The open input when it is a pipe, consumed by the first alignment pass
+    stream: Option<StreamedInput>,
+    /// Sequences whose records are skipped unparsed
+    excluded: ExcludedSequences,
+    /// `--collapse-redundant` cluster representatives with their cluster sizes, by index;
+    /// the other records are skipped unparsed
+    representatives: Option<HashMap<usize, usize>>,
+    warnings: ParseWarnings,
+}
+
+/// Sequences outside the `--min-query-len`/`--max-query-len` and target
+/// length bounds, by metadata ID. Their records are skipped as soon as the
+/// A line is read, before any trace line.
+#[derive(Default)]
+struct ExcludedSequences {
+    query: HashSet<i64>,
+    target: HashSet<i64>,
+}
+
+/// IDs of the sequences whose whole length is below `min` or above `max`.
+fn excluded_ids(lengths: &HashMap<i64, i64>, min: Option<i64>, max: Option<i64>) -> HashSet<i64> {
+    if min.is_none() && max.is_none() {
+        return HashSet::new();
+    }
+    lengths
+        .iter()
+        .filter(|&(_, &length)| min.is_some_and(|min| length < min) || max.is_some_and(|max| length > max))
+        .map(|(&id, _)| id)
+        .collect()
+}
+
+impl ParseContext<'_> {
+    /// Track a 't' line, remembering every distinct value seen.
+    fn set_trace_spacing(&mut self, trace_spacing: i64) {
+        self.trace_spacing = trace_spacing;
+        self.warnings.trace_spacings.insert(trace_spacing);
+    }
+}
+
+/// Whether the file has T lines: from the header counts when available,
+/// otherwise by reading the first alignment (`line_type` is the line just
+/// read). A stream can't be rewound after peeking, so it is assumed to have them.
+fn has_tracepoints(file: &mut OneFile, mut line_type: char, streaming: bool) -> bool {
+    if let Ok((count, _, _)) = file.stats('T') {
+        return count > 0;
+    }
+    if streaming {
+        return true;
+    }
+    if line_type != 'A' {
+        return false;
+    }
+    loop {
+        line_type = file.read_line();
+        match line_type {
+            'T' => return true,
+            'A' | '\0' => return false,
+            _ => {}
+        }
+    }
+}
+
+/// Load sequence metadata and the trace spacing (0 when the file has no
+/// tracepoints, so none applies).
+fn get_file_metadata(
+    path: &str,
+    assume_trace_spacing: Option<i64>,
+    streaming: bool,
+    resolve_options: &ResolveOptions,
+    fai_metadata: fai::FaiMetadata,
+) -> Result<(FileMetadata, i64, Option<StreamedInput>), Box<dyn std::error::Error>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+
+    // Check if there are reference paths to external GDB files
+    let references = file.get_references();
+
+    // Initialize empty metadata structures
+    let mut query_seq_names = HashMap::new();
+    let mut query_seq_lengths = HashMap::new();
+    let mut query_contig_offsets = HashMap::new();
+    let mut target_seq_names = HashMap::new();
+    let mut target_seq_lengths = HashMap::new();
+    let mut target_contig_offsets = HashMap::new();
+
+    // The embedded GDB skeleton (if present) is the target genome (gdb2)
+    let embedded_names = file.get_all_sequence_names();
+    let embedded_lengths = file.get_all_sequence_lengths();
+    let embedded_offsets = file.get_all_contig_offsets();
+
+    // Process references:
+    // - First reference (count=1) is the QUERY genome (gdb1/A-read)
+    // - Second reference (count=2) is the TARGET genome (gdb2/B-read)
+    // - If there's an embedded skeleton, it's for the TARGET (gdb2/B-read)
+
+    let mut has_external_query = false;
+    let mut external_target = None;
+
+    for (ref_idx, (ref_path, ref_count)) in references.iter().enumerate() {
+        if ref_path.is_empty() {
+            continue;
+        }
+
+        // Skip if this is not a genome reference (count > 2 might be other metadata)
+        if *ref_count > 2 {
+            continue;
+        }
+
+        let is_query = *ref_count == 1;    // First reference is query (A-read)
+        let is_target = *ref_count == 2;   // Second reference is target (B-read)
+
+        eprintln!("Processing reference {}: {} (count: {}, type: {})",
+            ref_idx + 1, ref_path, ref_count,
+            if is_query { "query" } else if is_target { "target" } else { "unknown" });
+
+        let resolution = resolve::resolve_gdb_path(ref_path, path, resolve_options);
+        let gdb_path = if let Some((_, found_path)) = resolution.found {
+            found_path
+        } else {
+            eprintln!("Warning: Could not find GDB file for reference: {}", ref_path);
+            eprintln!("Tried:");
+            for attempt in &resolution.tried {
+                eprintln!("  - {}", attempt);
+            }
+            eprintln!("Contig-to-scaffold mappings for this reference will not be available");
+            continue;
+        };
+
+        // Try to load the GDB metadata
+        if let Ok((ref_names, ref_lengths, ref_offsets)) = OneFile::read_gdb_metadata(&gdb_path) {
+            if is_query {
+                query_seq_names = ref_names;
+                query_seq_lengths = ref_lengths;
+                query_contig_offsets = ref_offsets;
+                has_external_query = true;
+                eprintln!("Loaded query genome metadata from: {} ({} sequences)", gdb_path, query_seq_names.len());
+            } else if is_target {
+                eprintln!("Loaded target genome metadata from: {} ({} sequences)", gdb_path, ref_names.len());
+                external_target = Some((ref_names, ref_lengths, ref_offsets));
+            }
+        } else {
+            eprintln!("Warning: Failed to load GDB metadata from: {}", gdb_path);
+        }
+    }
+
+    // A .fai stands in only for a genome nothing else describes
+    if let Some((names, lengths, offsets)) = fai_metadata.query {
+        if has_external_query {
+            eprintln!("Warning: query metadata loaded from its GDB; ignoring --query-fai");
+        } else {
+            eprintln!("Using --query-fai for query genome ({} sequences, one contig each)", names.len());
+            (query_seq_names, query_seq_lengths, query_contig_offsets) = (names, lengths, offsets);
+            has_external_query = true;
+        }
+    }
+
+    // The target can be described both by its GDB and by the embedded skeleton; --prefer picks one
+    let embedded_target =
+        (!embedded_names.is_empty()).then_some((embedded_names, embedded_lengths, embedded_offsets));
+    let target_source = match (external_target, embedded_target) {
+        (Some(external), Some(embedded)) => {
+            eprintln!("Target genome has both an external GDB and an embedded skeleton (choose with --prefer)");
+            match resolve_options.prefer {
+                TargetSource::External => Some((external, "external GDB")),
+                TargetSource::Embedded => Some((embedded, "embedded skeleton")),
+            }
+        }
+        (Some(external), None) => Some((external, "external GDB")),
+        (None, Some(embedded)) => Some((embedded, "embedded skeleton")),
+        (None, None) => None,
+    };
+    if let Some(((names, lengths, offsets), source)) = target_source {
+        eprintln!("Using {} for target genome ({} sequences)", source, names.len());
+        (target_seq_names, target_seq_lengths, target_contig_offsets) = (names, lengths, offsets);
+    }
+
+    if let Some((names, lengths, offsets)) = fai_metadata.target {
+        if !target_seq_names.is_empty() {
+            eprintln!("Warning: target metadata loaded from its GDB or skeleton; ignoring --target-fai");
+        } else {
+            eprintln!("Using --target-fai for target genome ({} sequences, one contig each)", names.len());
+            (target_seq_names, target_seq_lengths, target_contig_offsets) = (names, lengths, offsets);
+        }
+    }
+
+    // If this is a self-alignment (no external query), use target for query too
+    if !has_external_query && !target_seq_names.is_empty() {
+        query_seq_names = target_seq_names.clone();
+        query_seq_lengths = target_seq_lengths.clone();
+        query_contig_offsets = target_contig_offsets.clone();
+        eprintln!("Self-alignment detected: using target genome for query");
+    }
+
+    if query_seq_names.is_empty() && target_seq_names.is_empty() {
+        eprintln!("Warning: No sequence metadata found in file or external references (see --allow-anonymous)");
+    }
+
+    let metadata = FileMetadata {
+        query_seq_names,
+        query_seq_lengths,
+        query_contig_offsets,
+        target_seq_names,
+        target_seq_lengths,
+        target_contig_offsets,
+    };
+
+    // Trace spacing, from the first 't' line before any alignment
+    let mut found_spacing = None;
+    let mut line_type = file.read_line();
+    loop {
+        match line_type {
+            't' => {
+                found_spacing = Some(file.int(0));
+                break;
+            }
+            'A' | '\0' => break,
+            _ => line_type = file.read_line(),
+        }
+    }
+
+    let trace_spacing = match (found_spacing, assume_trace_spacing) {
+        (Some(trace_spacing), _) => trace_spacing,
+        // Without tracepoints the spacing is never used, so don't claim one
+        _ if !has_tracepoints(&mut file, line_type, streaming) => 0,
+        (None, Some(assumed)) => {
+            eprintln!("Warning: no trace spacing ('t') line in the file; assuming {}", assumed);
+            assumed
+        }
+        (None, None) => {
+            return Err("The file has tracepoints but no trace spacing ('t') line, so segment boundaries \
+                        are unknown; pass --assume-trace-spacing N if you know the value"
+                .into());
+        }
+    };
+
+    // A pipe can't be reopened, so the alignment pass continues from here
+    let stream = streaming.then_some(StreamedInput {
+        file,
+        current_line: line_type,
+    });
+    Ok((metadata, trace_spacing, stream))
+}
+
+/// `--check-refs`: run the GDB resolution for every reference and report the outcome.
+fn check_references(path: &str, resolve_options: &ResolveOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let file = OneFile::open_read(path, None, None, 1)?;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+
+    writeln!(handle, "=== REFERENCES ===\n")?;
+    writeln!(handle, "File: {}", path)?;
+
+    let mut failures = 0;
+    for (ref_idx, (ref_path, ref_count)) in file.get_references().iter().enumerate() {
+        let role = match ref_count {
+            1 => "query",
+            2 => "target",
+            _ => "not a genome reference",
+        };
+        writeln!(handle, "\nReference {}: {} (count: {}, {})", ref_idx + 1, ref_path, ref_count, role)?;
+        if ref_path.is_empty() || *ref_count > 2 {
+            writeln!(handle, "  Skipped")?;
+            continue;
+        }
+
+        let resolution = resolve::resolve_gdb_path(ref_path, path, resolve_options);
+        match &resolution.found {
+            Some((strategy, gdb_path)) => {
+                writeln!(
+                    handle,
+                    "  Resolved by strategy {} ({}): {}",
+                    strategy,
+                    resolve::strategy_description(*strategy),
+                    gdb_path
+                )?;
+                if let Some(canonical) = resolution.tried.last().and_then(|attempt| attempt.canonical.as_ref())
+                    && canonical.as_path() != std::path::Path::new(gdb_path)
+                {
+                    writeln!(handle, "  Canonical path: {}", canonical.display())?;
+                }
+                for attempt in &resolution.tried[..resolution.tried.len() - 1] {
+                    if attempt.canonical.is_some() {
+                        writeln!(handle, "  Skipped [{}] {}", attempt.strategy, attempt)?;
+                    }
+                }
+                match OneFile::read_gdb_metadata(gdb_path) {
+                    Ok((names, _, _)) => writeln!(handle, "  Sequences: {}", names.len())?,
+                    Err(e) => {
+                        failures += 1;
+                        writeln!(handle, "  FAILED to load GDB metadata: {}", e)?;
+                    }
+                }
+            }
+            None => {
+                failures += 1;
+                writeln!(handle, "  FAILED: no usable candidate. Tried:")?;
+                for attempt in &resolution.tried {
+                    writeln!(handle, "    [{}] {}", attempt.strategy, attempt)?;
+                }
+            }
+        }
+    }
+
+    let embedded = file.get_all_sequence_names();
+    if embedded.is_empty() {
+        writeln!(handle, "\nEmbedded skeleton: none")?;
+    } else {
+        writeln!(handle, "\nEmbedded skeleton: {} sequences", embedded.len())?;
+    }
+
+    if failures > 0 {
+        return Err(format!("{} reference(s) could not be resolved", failures).into());
+    }
+    Ok(())
+}
+
+fn print_metadata(
+    metadata: &FileMetadata,
+    trace_spacing: i64,
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+
+    writeln!(handle, "=== METADATA ===\n")?;
+    writeln!(handle, "File: {}", path)?;
+    if trace_spacing > 0 {
+        writeln!(handle, "Trace spacing: {}", trace_spacing)?;
+    }
+    writeln!(handle, "Query sequences: {}", metadata.query_seq_names.len())?;
+    writeln!(handle, "Target sequences: {}", metadata.target_seq_names.len())?;
+
+    // Sort query sequences by ID for consistent output
+    if !metadata.query_seq_names.is_empty() {
+        let mut query_sequences: Vec<_> = metadata.query_seq_names.iter().collect();
+        query_sequences.sort_by_key(|&(&id, _)| id);
+
+        writeln!(handle, "\nQuery Sequences:")?;
+        for &(&id, ref name) in query_sequences.iter().take(10) {
+            let length = metadata.query_seq_lengths.get(&id).copied().unwrap_or(0);
+            writeln!(handle, "  {}) {} (length: {})", id, name, length)?;
+        }
+        if query_sequences.len() > 10 {
+            writeln!(handle, "  ... and {} more", query_sequences.len() - 10)?;
+        }
+    }
+
+    // Sort target sequences by ID for consistent output
+    if !metadata.target_seq_names.is_empty() {
+        let mut target_sequences: Vec<_> = metadata.target_seq_names.iter().collect();
+        target_sequences.sort_by_key(|&(&id, _)| id);
+
+        writeln!(handle, "\nTarget Sequences:")?;
+        for &(&id, ref name) in target_sequences.iter().take(10) {
+            let length = metadata.target_seq_lengths.get(&id).copied().unwrap_or(0);
+            writeln!(handle, "  {}) {} (length: {})", id, name, length)?;
+        }
+        if target_sequences.len() > 10 {
+            writeln!(handle, "  ... and {} more", target_sequences.len() - 10)?;
+        }
+    }
+    
+    // Count alignments
+    write!(handle, "\nCounting alignments...")?;
+    handle.flush()?;
+    
+    let counts = count_body_lines(path)?;
+    writeln!(handle, "\rTotal alignments: {}    ", counts.alignments)?;
+    if counts.groups > 0 {
+        writeln!(handle, "Alignment groups: {}", counts.groups)?;
+    }
+    if counts.scaffolds > 0 {
+        writeln!(handle, "Embedded skeleton scaffolds: {}", counts.scaffolds)?;
+    }
+    
+    Ok(())
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum OutputFormat {
+    Human,
+    Paf,
+    Maf,
+    Bedpe,
+    Template,
+}
+
+/// Everything the printers need besides the alignment itself.
+struct OutputConfig {
+    format: OutputFormat,
+    pansn: Option<PanSn>,
+    /// Applied to names in tabular formats
+    names: NameSanitizer,
+    /// Ignored by formats whose spec fixes the convention (PAF)
+    coord_base: CoordBase,
+    /// Set when `format` is `Template`
+    template: Option<Template>,
+    /// Optional PAF tags, written in a fixed order regardless of this order
+    tags: Vec<PafTag>,
+    mapq_mode: MapqMode,
+    /// PAF as written by ALNtoPAF, replacing every optional tag
+    compat_alntopaf: bool,
+    /// Follow every PAF record with its query/target swap
+    transpose: bool,
+    /// List raw trace values in human output, eliding all but the first and last N when N > 0
+    trace_values: Option<usize>,
+    /// `--limit-traces`: most trace values printed per list
+    trace_limit: Option<usize>,
+    /// `--section-by`: group human output by query or target sequence
+    sections: Option<Sections>,
+    /// `--print-offsets`: report the contig offsets added to each record's coordinates
+    print_offsets: bool,
+    /// `--collapse-redundant`: records carry their cluster size
+    collapsed: bool,
+    /// Print trace lines in human output for records without traces
+    empty_traces: bool,
+    /// `--color`: escapes for human output only
+    style: style::Style,
+    /// Show the single alignment base by base instead of in `format`
+    pretty: Option<pretty::PrettyOptions>,
+    /// Print the pieces of alignments cut at divergent or gapped segments instead of whole alignments
+    split: Option<split::SegmentSplit>,
+    /// Target features to list per alignment (`--annotate`)
+    annotations: Option<gff::FeatureIndex>,
+    /// Fetched lazily, only by formats that show bases
+    sequences: Sequences,
+    scoring: ScoringModel,
+}
+
+/// Per-alignment selection criteria, applied before any output or statistics.
+struct AlignmentFilter {
+    pansn: Option<PanSn>,
+    query_sample: Option<String>,
+    target_sample: Option<String>,
+    skip_invalid: bool,
+    strand: Option<char>,
+    min_length: Option<i64>,
+    min_matches: Option<i64>,
+    /// Query sequences below `--min-covered-frac`, by metadata ID, filled by a first pass
+    uncovered_query_ids: HashSet<i64>,
+    /// Alignments within `--max-per-pair` of their sequence pair, by index, filled by a first pass
+    pair_survivors: Option<HashSet<usize>>,
+    target_region: Option<TargetRegion>,
+    seek: Option<SeekKey>,
+    /// Alignments accepted so far, when dropping exact duplicates (`--dedup`)
+    seen: Option<RefCell<HashSet<AlignmentKey>>>,
+    duplicates: Cell<usize>,
+}
+
+/// A `--target-region`: a target sequence name and a 0-based half-open interval on it.
+struct TargetRegion {
+    name: String,
+    start: i64,
+    end: i64,
+}
+
+/// A `--seek-query`/`--seek-target` sequence.
+struct SeekKey {
+    side: track::Space,
+    name: String,
+    assume_sorted: bool,
+}
+
+/// Sequences, coordinates and strand: what makes two records the same alignment.
+type AlignmentKey = (i64, i64, i64, i64, i64, i64, char);
+
+impl AlignmentFilter {
+    fn accepts(&self, aln: &AlignmentData) -> bool {
+        if self.skip_invalid && aln.out_of_bounds {
+            return false;
+        }
+        if self.strand.is_some_and(|strand| strand != aln.strand) {
+            return false;
+        }
+        if self.min_matches.is_some_and(|min_matches| aln.matches() < min_matches) {
+            return false;
+        }
+        if self.min_length.is_some_and(|min_length| {
+            aln.query_end - aln.query_start < min_length || aln.target_end - aln.target_start < min_length
+        }) {
+            return false;
+        }
+        if self.uncovered_query_ids.contains(&aln.query_id) {
+            return false;
+        }
+        if self.pair_survivors.as_ref().is_some_and(|survivors| !survivors.contains(&aln.index)) {
+            return false;
+        }
+        if let Some(seek) = &self.seek {
+            let name = match seek.side {
+                track::Space::Query => &aln.query_name,
+                track::Space::Target => &aln.target_name,
+            };
+            if *name != seek.name {
+                return false;
+            }
+        }
+        if let Some(region) = &self.target_region
+            && (aln.target_name != region.name || aln.target_start >= region.end || aln.target_end <= region.start)
+        {
+            return false;
+        }
+        if let Some(pansn) = &self.pansn {
+            if let Some(sample) = &self.query_sample
+                && pansn.sample(&aln.query_name) != sample
+            {
+                return false;
+            }
+            if let Some(sample) = &self.target_sample
+                && pansn.sample(&aln.target_name) != sample
+            {
+                return false;
+            }
+        }
+        // Last, so only records that pass every other criterion are remembered
+        if let Some(seen) = &self.seen {
+            let key = (
+                aln.query_id,
+                aln.query_start,
+                aln.query_end,
+                aln.target_id,
+                aln.target_start,
+                aln.target_end,
+                aln.strand,
+            );
+            if !seen.borrow_mut().insert(key) {
+                self.duplicates.set(self.duplicates.get() + 1);
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn report_unmatched_pansn_names(pansn: &PanSn, metadata: &FileMetadata) {
+    let names: HashSet<&String> = metadata
+        .query_seq_names
+        .values()
+        .chain(metadata.target_seq_names.values())
+        .collect();
+    let unmatched = pansn.count_unmatched(names);
+    if unmatched > 0 {
+        eprintln!(
+            "Warning: {} sequence names do not follow the PanSN pattern and are treated as their own sample",
+            unmatched
+        );
+    }
+}
+
+fn read_single_alignment(
+    path: &str,
+    idx: usize,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+
+    // Require O(1) access via binary index
+    if file.goto('A', (idx + 1) as i64).is_err() {
+        // Tell an out-of-range index apart from a missing index
+        let count = count_alignments(path)?;
+        if count == 0 {
+            return Err(format!("Cannot read alignment {}: file contains no alignments", idx).into());
+        }
+        if idx >= count {
+            return Err(format!(
+                "Alignment {} is out of range: file contains {} alignments (0-indexed)",
+                idx, count
+            ).into());
+        }
+        return Err(format!(
+            "Cannot access alignment {} directly. Binary index not available for this file.\n\
+             Please ensure the file has an associated .1idx index file.",
+            idx
+        ).into());
+    }
+
+    eprintln!("Using O(1) binary index to jump to alignment {}", idx);
+    file.read_line(); // Read the 'A' line we jumped to
+    let (Some(aln), _) = parse_alignment(&mut file, ctx, idx)? else {
+        return Ok(());
+    };
+
+    if !filter.accepts(&aln) {
+        return Ok(());
+    }
+    if ctx.explain {
+        explain::write(&mut io::stdout().lock(), &aln, output)?;
+        return Ok(());
+    }
+    if let Some(options) = &output.pretty {
+        let Some((query, target, ops)) = align::reconstruct_with_bases(&aln, &output.sequences)? else {
+            return Err(format!("Cannot show alignment {} base by base: it has no usable trace", idx).into());
+        };
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        print_alignment_human(&mut handle, &aln, output)?;
+        pretty::render(&mut handle, &aln, query.as_bytes(), target.as_bytes(), &ops, options)?;
+        return Ok(());
+    }
+    print_alignment(&mut io::stdout().lock(), &aln, output)?;
+    Ok(())
+}
+
+/// Alignment numbers listed one per line in `path`; blank lines and `#`
+/// comments are skipped.
+fn read_alignment_list(path: &str) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot read alignment list {}: {}", path, e))?;
+    let mut indices = Vec::new();
+    for (line_idx, line) in io::BufRead::lines(io::BufReader::new(file)).enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let idx = line
+            .parse::<usize>()
+            .map_err(|_| format!("{}:{}: expected an alignment number, got '{}'", path, line_idx + 1, line))?;
+        indices.push(idx);
+    }
+    Ok(indices)
+}
+
+/// `--select-alignments`: seek to each listed alignment in file order, so
+/// the reads stay sequential, and print them in file order or as listed.
+fn read_selected_alignments(
+    path: &str,
+    list: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+    preserve_order: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let requested = read_alignment_list(list)?;
+    let Some((mut file, count)) = open_seekable(path, ctx)? else {
+        return Err(format!(
+            "Cannot select alignments from {}: it needs a binary index (.1idx) and a single trace spacing",
+            path
+        )
+        .into());
+    };
+    let mut objects: Vec<usize> = requested.clone();
+    objects.sort_unstable();
+    objects.dedup();
+    if let Some(&last) = objects.last()
+        && last as i64 >= count
+    {
+        let out_of_range = objects.iter().filter(|&&idx| idx as i64 >= count).count();
+        return Err(format!(
+            "Alignment {} is out of range: file contains {} alignments (0-indexed); {} listed number(s) in {} are past the end",
+            last, count, out_of_range, list
+        )
+        .into());
+    }
+
+    let mut alignments = Vec::new();
+    visit_objects(&mut file, ctx, objects.iter().map(|&idx| idx as i64), |aln| {
+        if filter.accepts(&aln) {
+            alignments.push(aln);
+        }
+        Ok(())
+    })?;
+    let found = alignments.len();
+    let unique_mapq = output.format == OutputFormat::Paf && output.mapq_mode == MapqMode::Unique;
+
+    let mut handle = io::BufWriter::new(io::stdout().lock());
+    let result = if preserve_order {
+        if unique_mapq {
+            let mapqs = mapq::assign_unique(&alignments);
+            for (aln, mapq) in alignments.iter_mut().zip(mapqs) {
+                aln.mapq = Some(mapq);
+            }
+        }
+        // Filtered-out alignments are simply missing from the lookup
+        let by_index: HashMap<usize, &AlignmentData> = alignments.iter().map(|aln| (aln.index, aln)).collect();
+        requested
+            .iter()
+            .filter_map(|idx| by_index.get(idx))
+            .try_for_each(|aln| print_alignment(&mut handle, aln, output))
+            .map_err(Into::into)
+    } else {
+        write_buffered(&mut handle, alignments, output, unique_mapq, false)
+    };
+    let result = result.and_then(|()| {
+        if found == 0 && output.format == OutputFormat::Human {
+            writeln!(handle, "No alignments found")?;
+        }
+        Ok(())
+    });
+    flush_after(&mut handle, result)
+}
+
+/// Parse `START-END` in `coord_base` convention into a 0-based half-open interval.
+fn parse_region(region: &str, coord_base: CoordBase) -> Result<(i64, i64), Box<dyn std::error::Error>> {
+    let parsed = region
+        .split_once('-')
+        .and_then(|(start, end)| Some((start.trim().parse::<i64>().ok()?, end.trim().parse::<i64>().ok()?)));
+    let Some((start, end)) = parsed else {
+        return Err(format!("Invalid region '{}': expected START-END", region).into());
+    };
+    let (start, end) = coord_base.to_internal(start, end);
+    if start < 0 || start >= end {
+        return Err(format!("Invalid region '{}': empty or negative", region).into());
+    }
+    Ok((start, end))
+}
+
+/// Parse `NAME` or `NAME:START-END`; names may contain ':' themselves, so
+/// only a suffix that parses as an interval is taken as one.
+fn parse_target_region(region: &str, coord_base: CoordBase) -> Result<TargetRegion, Box<dyn std::error::Error>> {
+    if let Some((name, interval)) = region.rsplit_once(':')
+        && interval.contains('-')
+        && !name.is_empty()
+    {
+        let (start, end) = parse_region(interval, coord_base)?;
+        return Ok(TargetRegion { name: name.to_string(), start, end });
+    }
+    if region.is_empty() {
+        return Err("Invalid --target-region: empty name".into());
+    }
+    Ok(TargetRegion { name: region.to_string(), start: 0, end: i64::MAX })
+}
+
+/// Flush `out` whether or not `result` is an error, so records written
+/// before a failure still reach the output. The original error wins over a
+/// flush error.
+fn flush_after<W: Write + ?Sized>(
+    out: &mut W,
+    result: Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let flushed = out.flush();
+    result?;
+    Ok(flushed?)
+}
+
+fn read_all_alignments(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+    reverse: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Stdout is line-buffered on its own, which costs a write per record
+    let mut handle = io::BufWriter::new(io::stdout().lock());
+    let result = write_all_alignments(&mut handle, path, ctx, filter, output, reverse);
+    flush_after(&mut handle, result)
+}
+
+fn write_all_alignments(
+    handle: &mut dyn Write,
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+    reverse: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let unique_mapq = output.format == OutputFormat::Paf && output.mapq_mode == MapqMode::Unique;
+
+    // Region and sorted-key queries seek to their candidates and write them like the buffered path below
+    let mut candidates = None;
+    if let Some(region) = &filter.target_region {
+        candidates = indexed_region_objects(path, ctx, region)?;
+    }
+    if candidates.is_none()
+        && let Some(seek) = &filter.seek
+    {
+        candidates = sorted_key_objects(path, ctx, seek)?;
+    }
+    if let Some((mut file, objects)) = candidates {
+        let mut alignments = Vec::new();
+        visit_objects(&mut file, ctx, objects, |aln| {
+            if filter.accepts(&aln) {
+                alignments.push(aln);
+            }
+            Ok(())
+        })?;
+        let found = alignments.len();
+        write_buffered(handle, alignments, output, unique_mapq, reverse)?;
+        if found == 0 && output.format == OutputFormat::Human {
+            writeln!(handle, "No alignments found")?;
+        }
+        return Ok(());
+    }
+
+    // Counted after filtering, like the seeking path above, so both report an empty result alike
+    let mut accepted = 0;
+    // Dedup keeps the first copy in file order, so walking backward would keep the wrong one
+    let seeked = reverse
+        && !unique_mapq
+        && filter.seen.is_none()
+        && output.sections.is_none()
+        && for_each_alignment_backward(path, ctx, |aln| {
+            if filter.accepts(&aln) {
+                accepted += 1;
+                print_alignment(handle, &aln, output)?;
+            }
+            Ok(())
+        })?
+        .is_some();
+
+    if seeked {
+        // Written while walking the index backward
+    } else if let Some(sections) = output.sections.filter(|sections| sections.sorted && !reverse) {
+        // Sorted by the section key: each section is complete when the key changes
+        let mut section = SectionWriter::new(sections.side);
+        for_each_alignment(path, ctx, |aln| {
+            if filter.accepts(&aln) {
+                accepted += 1;
+                section.push(handle, aln, output)?;
+            }
+            Ok(())
+        })?;
+        section.finish(handle, output)?;
+    } else if unique_mapq || reverse || output.sections.is_some() {
+        // Overlap-based MAPQ, reversing without the index, and grouping unsorted sections need every
+        // alignment before the first is written
+        let mut alignments = Vec::new();
+        for_each_alignment(path, ctx, |aln| {
+            if filter.accepts(&aln) {
+                alignments.push(aln);
+            }
+            Ok(())
+        })?;
+        accepted = alignments.len();
+        write_buffered(handle, alignments, output, unique_mapq, reverse)?;
+    } else {
+        for_each_alignment(path, ctx, |aln| {
+            if filter.accepts(&aln) {
+                accepted += 1;
+                print_alignment(handle, &aln, output)?;
+            }
+            Ok(())
+        })?;
+    }
+
+    if accepted == 0 && output.format == OutputFormat::Human {
+        writeln!(handle, "No alignments found")?;
+    }
+    Ok(())
+}
+
+/// `--section-by` settings.
+#[derive(Copy, Clone, Debug)]
+struct Sections {
+    side: track::Space,
+    /// `--assume-sorted`: stream sections instead of buffering the file
+    sorted: bool,
+}
+
+/// `n` with commas between groups of three digits.
+fn thousands(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if n < 0 {
+        grouped.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Human output in `--section-by` sections. Alignments arrive grouped by
+/// the section's sequence; each run of them is held until the next sequence
+/// starts, since the header gives its count.
+struct SectionWriter {
+    side: track::Space,
+    alignments: Vec<AlignmentData>,
+}
+
+impl SectionWriter {
+    fn new(side: track::Space) -> Self {
+        SectionWriter {
+            side,
+            alignments: Vec::new(),
+        }
+    }
+
+    fn name(&self, aln: &AlignmentData) -> String {
+        match self.side {
+            track::Space::Query => aln.query_name.clone(),
+            track::Space::Target => aln.target_name.clone(),
+        }
+    }
+
+    fn push(&mut self, handle: &mut dyn Write, aln: AlignmentData, output: &OutputConfig) -> io::Result<()> {
+        if self.alignments.first().is_some_and(|first| self.name(first) != self.name(&aln)) {
+            self.finish(handle, output)?;
+        }
+        self.alignments.push(aln);
+        Ok(())
+    }
+
+    /// Write the held section, if any.
+    fn finish(&mut self, handle: &mut dyn Write, output: &OutputConfig) -> io::Result<()> {
+        let Some(first) = self.alignments.first() else {
+            return Ok(());
+        };
+        let (side, name, length) = match self.side {
+            track::Space::Query => ("query", &first.query_name, first.query_length),
+            track::Space::Target => ("target", &first.target_name, first.target_length),
+        };
+        writeln!(
+            handle,
+            "== {} {} (len {}; {} alignments) ==\n",
+            side,
+            name,
+            thousands(length),
+            thousands(self.alignments.len() as i64)
+        )?;
+        let name = name.clone();
+        let mut aligned_bp = 0;
+        let mut identity_sum = 0.0;
+        for aln in &self.alignments {
+            print_alignment(handle, aln, output)?;
+            aligned_bp += match self.side {
+                track::Space::Query => aln.query_span(),
+                track::Space::Target => aln.target_span(),
+            };
+            identity_sum += aln.identity();
+        }
+        writeln!(
+            handle,
+            "-- {} {}: {} aligned bp, mean identity {:.6} --\n",
+            side,
+            name,
+            thousands(aligned_bp),
+            identity_sum / self.alignments.len() as f64
+        )?;
+        self.alignments.clear();
+        Ok(())
+    }
+}
+
+/// Write alignments collected in file order, after assigning overlap-based
+/// MAPQ or reversing them when asked, in sections when asked.
+fn write_buffered(
+    handle: &mut dyn Write,
+    mut alignments: Vec<AlignmentData>,
+    output: &OutputConfig,
+    unique_mapq: bool,
+    reverse: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if unique_mapq {
+        let mapqs = mapq::assign_unique(&alignments);
+        for (aln, mapq) in alignments.iter_mut().zip(mapqs) {
+            aln.mapq = Some(mapq);
+        }
+    }
+    if reverse {
+        alignments.reverse();
+    }
+    if let Some(sections) = output.sections {
+        // Sections in order of first appearance, alignments in order within them
+        let mut section = SectionWriter::new(sections.side);
+        let mut first_seen: HashMap<String, usize> = HashMap::new();
+        for aln in &alignments {
+            let next = first_seen.len();
+            first_seen.entry(section.name(aln)).or_insert(next);
+        }
+        alignments.sort_by_cached_key(|aln| first_seen[&section.name(aln)]);
+        for aln in alignments {
+            section.push(handle, aln, output)?;
+        }
+        section.finish(handle, output)?;
+        return Ok(());
+    }
+    for aln in &alignments {
+        print_alignment(handle, aln, output)?;
+    }
+    Ok(())
+}
+
+fn write_alignments(
+    path: &str,
+    out_path: &str,
+    ctx: &mut ParseContext,
+    trace_spacing: i64,
+    filter: &AlignmentFilter,
+    transpose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = ctx.metadata;
+    // Swapped records are written with the same IDs, so both sides must share one GDB
+    if transpose
+        && (metadata.query_contig_offsets != metadata.target_contig_offsets
+            || metadata.query_seq_names.is_empty())
+    {
+        return Err("--transpose with --write-1aln needs a self-alignment (one GDB for both sides)".into());
+    }
+
+    let source = OneFile::open_read(path, None, None, 1)?;
+    let mut writer = writer::AlnWriter::create(out_path, &source, metadata, trace_spacing)?;
+    let mut written = 0;
+    let count = for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            writer.write(&aln)?;
+            written += 1;
+            if transpose {
+                writer.write(&aln.transposed())?;
+                written += 1;
+            }
+        }
+        Ok(())
+    })?;
+    if transpose {
+        eprintln!("Note: swapped records were written without T/X lines");
+    }
+    eprintln!("Wrote {} records from {} alignments to {}", written, count, out_path);
+    Ok(())
+}
+
+fn extract_fasta(
+    path: &str,
+    prefix: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(query_fasta), Some(target_fasta)) = (&output.sequences.query, &output.sequences.target) else {
+        return Err("--extract-fasta needs --fasta query=PATH target=PATH".into());
+    };
+    let create = |suffix: &str| -> Result<io::BufWriter<std::fs::File>, Box<dyn std::error::Error>> {
+        let out_path = format!("{}.{}.fa", prefix, suffix);
+        let file = std::fs::File::create(&out_path).map_err(|e| format!("Cannot create {}: {}", out_path, e))?;
+        Ok(io::BufWriter::new(file))
+    };
+    let mut query_out = create("query")?;
+    let mut target_out = create("target")?;
+
+    let write_record = |out: &mut dyn Write, name: &str, start: i64, end: i64, strand: char, index: usize, bases: &str| {
+        let (start, end) = output.coord_base.interval(start, end);
+        writeln!(out, ">{}:{}-{}({}) aln={}", name, start, end, strand, index)?;
+        for line in bases.as_bytes().chunks(60) {
+            out.write_all(line)?;
+            writeln!(out)?;
+        }
+        Ok::<(), io::Error>(())
+    };
+
+    let mut extracted = 0;
+    let mut out_of_bounds = 0;
+    let result = for_each_alignment(path, ctx, |aln| {
+        if !filter.accepts(&aln) {
+            return Ok(());
+        }
+        if aln.out_of_bounds {
+            out_of_bounds += 1;
+            return Ok(());
+        }
+        let query = query_fasta.fetch(aln.query_id, aln.query_start, aln.query_end, '+')?;
+        let target = target_fasta.fetch(aln.target_id, aln.target_start, aln.target_end, aln.strand)?;
+        write_record(
+            &mut query_out,
+            &output.names.apply(&aln.query_name)?,
+            aln.query_start,
+            aln.query_end,
+            '+',
+            aln.index,
+            &query,
+        )?;
+        write_record(
+            &mut target_out,
+            &output.names.apply(&aln.target_name)?,
+            aln.target_start,
+            aln.target_end,
+            aln.strand,
+            aln.index,
+            &target,
+        )?;
+        extracted += 1;
+        Ok(())
+    })
+    .map(|_| ());
+    // Whatever was extracted before a failure is still written out
+    let result = flush_after(&mut query_out, result);
+    flush_after(&mut target_out, result)?;
+    if out_of_bounds > 0 {
+        eprintln!("Warning: {} alignments with out-of-bounds coordinates were not extracted", out_of_bounds);
+    }
+    eprintln!("Extracted {} alignments to {}.query.fa and {}.target.fa", extracted, prefix, prefix);
+    Ok(())
+}
+
+/// Line types that end the run of associated lines following an `A` record.
+///
+/// * `A`: the next alignment.
+/// * `g`: a group object. FastGA can declare `A` records as members of
+///   groups; the line opens the next group and the alignments after it are
+///   read as usual. Only the number of groups is reported (metadata view).
+/// * `S`: a scaffold of a GDB skeleton embedded in the file. Its name is
+///   already in the target metadata, read through the skeleton tables.
+/// * `a`, `^`: other object types; nothing is read from them.
+/// * `\0`: end of file.
+///
+/// None of these belong to the alignment before them. Callers hand the
+/// boundary line back to their reading loop, which skips anything that is
+/// not `A` or `t`, so an alignment after any of them is never lost.
+fn is_record_boundary(line_type: char) -> bool {
+    matches!(line_type, 'A' | 'a' | 'g' | 'S' | '^' | '\0')
+}
+
+/// Object lines in the body of a file, found by reading it through.
+#[derive(Debug, Default)]
+struct BodyCounts {
+    alignments: usize,
+    /// `g` lines
+    groups: usize,
+    /// `S` lines of an embedded skeleton
+    scaffolds: usize,
+}
+
+fn count_body_lines(path: &str) -> Result<BodyCounts, Box<dyn std::error::Error>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let mut counts = BodyCounts::default();
+    loop {
+        match file.read_line() {
+            '\0' => break,
+            'A' => counts.alignments += 1,
+            'g' => counts.groups += 1,
+            'S' => counts.scaffolds += 1,
+            _ => {}
+        }
+    }
+    Ok(counts)
+}
+
+fn count_alignments(path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    Ok(count_body_lines(path)?.alignments)
+}
+
+fn print_summary_stats(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    mut summary: SummaryStats,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            summary.add(&aln);
+        }
+        Ok(())
+    })?;
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    if json {
+        summary.write_json(&mut handle, path)?;
+    } else {
+        summary.write(&mut handle)?;
+    }
+    Ok(())
+}
+
+/// The `--group-by` key of a sequence name, sanitized for tabular output.
+fn group_key(name: &str, group_by: GroupBy, pansn: Option<PanSn>, names: &NameSanitizer) -> io::Result<String> {
+    let key = match (group_by, pansn) {
+        (GroupBy::Sample, Some(pansn)) => pansn.sample(name),
+        _ => name,
+    };
+    Ok(names.apply(key)?.into_owned())
+}
+
+fn print_identity_bins(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    mut bins: IdentityBins,
+    group_by: GroupBy,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut whole = 0;
+    for_each_alignment(path, ctx, |aln| {
+        if !filter.accepts(&aln) {
+            return Ok(());
+        }
+        let query = group_key(&aln.query_name, group_by, output.pansn, &output.names)?;
+        let target = group_key(&aln.target_name, group_by, output.pansn, &output.names)?;
+        match trace::segments(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        ) {
+            Some(segments) => {
+                for segment in segments {
+                    bins.add(&query, &target, 1.0 - segment.divergence(), segment.query_end - segment.query_start);
+                }
+            }
+            None => {
+                whole += 1;
+                bins.add(&query, &target, aln.identity(), aln.query_span());
+            }
+        }
+        Ok(())
+    })?;
+
+    if whole > 0 {
+        eprintln!("Note: {} alignment(s) without a usable trace were binned whole by their overall identity", whole);
+    }
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    bins.write(&mut handle)?;
+    Ok(())
+}
+
+fn print_pair_stats(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    group_by: GroupBy,
+    pansn: Option<PanSn>,
+    names: &NameSanitizer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let key = |name: &str| group_key(name, group_by, pansn, names);
+
+    let mut pair_stats = PairStats::default();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            pair_stats.add(&key(&aln.query_name)?, &key(&aln.target_name)?, &aln);
+        }
+        Ok(())
+    })?;
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    pair_stats.write(&mut handle)?;
+    Ok(())
+}
+
+fn print_per_target_counts(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    names: &NameSanitizer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            *counts.entry(aln.target_name).or_insert(0) += 1;
+        }
+        Ok(())
+    })?;
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(name_a, count_a), (name_b, count_b)| {
+        count_b.cmp(count_a).then_with(|| name_a.cmp(name_b))
+    });
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    for (name, count) in counts {
+        writeln!(handle, "{}\t{}", names.apply(&name)?, count)?;
+    }
+    Ok(())
+}
+
+/// `--stats-per-target` (`target` set) or `--stats-per-query`.
+fn print_sequence_stats(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    names: &NameSanitizer,
+    target: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = ctx.metadata;
+    let (seq_names, seq_lengths) = if target {
+        (&metadata.target_seq_names, &metadata.target_seq_lengths)
+    } else {
+        (&metadata.query_seq_names, &metadata.query_seq_lengths)
+    };
+    let mut sequences = Vec::with_capacity(seq_names.len());
+    for (id, name) in seq_names {
+        sequences.push((names.apply(name)?.into_owned(), seq_lengths.get(id).copied().unwrap_or(0)));
+    }
+    let mut stats = SequenceStats::new(sequences.into_iter());
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            let query = names.apply(&aln.query_name)?;
+            let target_name = names.apply(&aln.target_name)?;
+            let identity = aln.identity();
+            if target {
+                stats.add(&target_name, &query, aln.target_start, aln.target_end, identity);
+            } else {
+                stats.add(&query, &target_name, aln.query_start, aln.query_end, identity);
+            }
+        }
+        Ok(())
+    })?;
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    stats.write(&mut handle)?;
+    Ok(())
+}
+
+/// The `--collapse-redundant` clusters of the alignments `filter` accepts:
+/// a first pass collects their intervals, then each cluster's representative
+/// is kept with its size.
+fn redundancy_representatives(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    fraction: f64,
+) -> Result<HashMap<usize, usize>, Box<dyn std::error::Error>> {
+    if ctx.stream.is_some() {
+        return Err("--collapse-redundant reads the file twice and cannot read from a pipe; use a regular file".into());
+    }
+    let mut clusters = redundancy::Clusters::new(fraction);
+    let mut accepted = 0;
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            clusters.add(&aln);
+            accepted += 1;
+        }
+        Ok(())
+    })?;
+    // The real pass starts over: duplicates and warnings must not carry over
+    if let Some(seen) = &filter.seen {
+        seen.borrow_mut().clear();
+        filter.duplicates.set(0);
+    }
+    ctx.warnings = ParseWarnings::default();
+
+    let representatives = clusters.representatives();
+    eprintln!(
+        "--collapse-redundant: {} alignments collapsed into {} clusters; dropped {}",
+        accepted,
+        representatives.len(),
+        accepted - representatives.len()
+    );
+    Ok(representatives)
+}
 
-    // Check if there are reference paths to external GDB files
-    let references = file.get_references();
+/// How `--max-per-pair` ranks the alignments of a pair: longest block,
+/// then fewest differences, then earliest in the file.
+type PairRank = (i64, Reverse<i64>, Reverse<usize>);
 
-    // Initialize empty metadata structures
-    let mut query_seq_names = HashMap::new();
-    let mut query_seq_lengths = HashMap::new();
-    let mut query_contig_offsets = HashMap::new();
-    let mut target_seq_names = HashMap::new();
-    let mut target_seq_lengths = HashMap::new();
-    let mut target_contig_offsets = HashMap::new();
+/// The alignments `--max-per-pair` keeps, by index: a first pass holds the
+/// `max` best of each sequence pair in a bounded min-heap.
+fn pair_survivors(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    max: usize,
+) -> Result<HashSet<usize>, Box<dyn std::error::Error>> {
+    if ctx.stream.is_some() {
+        return Err("--max-per-pair reads the file twice and cannot read from a pipe; use a regular file".into());
+    }
+    let mut heaps: HashMap<(i64, i64), BinaryHeap<Reverse<PairRank>>> = HashMap::new();
+    let mut capped: HashSet<(i64, i64)> = HashSet::new();
+    let mut dropped = 0;
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            let pair = (aln.query_id, aln.target_id);
+            let heap = heaps.entry(pair).or_default();
+            heap.push(Reverse((aln.block_length(), Reverse(aln.differences), Reverse(aln.index))));
+            if heap.len() > max {
+                heap.pop();
+                capped.insert(pair);
+                dropped += 1;
+            }
+        }
+        Ok(())
+    })?;
+    // The real pass starts over: duplicates and warnings must not carry over
+    if let Some(seen) = &filter.seen {
+        seen.borrow_mut().clear();
+        filter.duplicates.set(0);
+    }
+    ctx.warnings = ParseWarnings::default();
 
-    // The embedded GDB skeleton (if present) is the target genome (gdb2)
-    let embedded_names = file.get_all_sequence_names();
-    let embedded_lengths = file.get_all_sequence_lengths();
-    let embedded_offsets = file.get_all_contig_offsets();
+    let survivors: HashSet<usize> = heaps
+        .values()
+        .flat_map(|heap| heap.iter().map(|Reverse((_, _, Reverse(index)))| *index))
+        .collect();
+    eprintln!(
+        "--max-per-pair: {} of {} sequence pairs went over the cap of {}; dropped {} alignments",
+        capped.len(),
+        heaps.len(),
+        max,
+        dropped
+    );
+    Ok(survivors)
+}
 
-    // Process references:
-    // - First reference (count=1) is the QUERY genome (gdb1/A-read)
-    // - Second reference (count=2) is the TARGET genome (gdb2/B-read)
-    // - If there's an embedded skeleton, it's for the TARGET (gdb2/B-read)
+/// Query sequence IDs for `--min-covered-frac`: a first pass merges the
+/// query intervals of the alignments `filter` accepts, and sequences whose
+/// covered fraction stays below `min_fraction` lose all their alignments in
+/// the second.
+fn uncovered_query_ids(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    min_fraction: f64,
+) -> Result<HashSet<i64>, Box<dyn std::error::Error>> {
+    if ctx.stream.is_some() {
+        return Err("--min-covered-frac reads the file twice and cannot read from a pipe; use a regular file".into());
+    }
+    let metadata = ctx.metadata;
+    if metadata.query_seq_lengths.is_empty() {
+        return Err("--min-covered-frac needs query sequence lengths from the metadata".into());
+    }
+    let mut coverage = SequenceStats::new(
+        metadata
+            .query_seq_names
+            .iter()
+            .map(|(id, name)| (name.clone(), metadata.query_seq_lengths.get(id).copied().unwrap_or(0))),
+    );
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            coverage.add(&aln.query_name, &aln.target_name, aln.query_start, aln.query_end, 0.0);
+        }
+        Ok(())
+    })?;
+    // The real pass starts over: duplicates and warnings must not carry over
+    if let Some(seen) = &filter.seen {
+        seen.borrow_mut().clear();
+        filter.duplicates.set(0);
+    }
+    ctx.warnings = ParseWarnings::default();
 
-    let mut has_external_query = false;
-    let mut has_external_target = false;
+    let uncovered: HashSet<&str> = coverage
+        .covered_fractions()
+        .filter(|&(_, fraction)| fraction < min_fraction)
+        .map(|(name, _)| name)
+        .collect();
+    eprintln!(
+        "--min-covered-frac: dropping the alignments of {} query sequences covered below {}",
+        uncovered.len(),
+        min_fraction
+    );
+    Ok(metadata
+        .query_seq_names
+        .iter()
+        .filter(|(_, name)| uncovered.contains(name.as_str()))
+        .map(|(&id, _)| id)
+        .collect())
+}
 
-    for (ref_idx, (ref_path, ref_count)) in references.iter().enumerate() {
-        if ref_path.is_empty() {
-            continue;
+fn write_query_covered_bed(
+    path: &str,
+    bed_path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut coverage = SequenceStats::default();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            let query = output.names.apply(&aln.query_name)?;
+            coverage.add(&query, &aln.target_name, aln.query_start, aln.query_end, 0.0);
         }
+        Ok(())
+    })?;
 
-        // Skip if this is not a genome reference (count > 2 might be other metadata)
-        if *ref_count > 2 {
-            continue;
+    if bed_path == "-" {
+        coverage.write_bed(&mut io::stdout().lock())?;
+    } else {
+        let file = std::fs::File::create(bed_path)
+            .map_err(|e| format!("Cannot create query coverage BED {}: {}", bed_path, e))?;
+        let mut writer = io::BufWriter::new(file);
+        let result = coverage.write_bed(&mut writer).map_err(Into::into);
+        flush_after(&mut writer, result)?;
+    }
+    Ok(())
+}
+
+fn print_orientation_stats(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    mut stats: OrientationStats,
+    group_by: GroupBy,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            let query = group_key(&aln.query_name, group_by, output.pansn, &output.names)?;
+            let target = group_key(&aln.target_name, group_by, output.pansn, &output.names)?;
+            stats.add(&query, &target, &aln);
         }
+        Ok(())
+    })?;
 
-        let is_query = *ref_count == 1;    // First reference is query (A-read)
-        let is_target = *ref_count == 2;   // Second reference is target (B-read)
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    stats.write(&mut handle)?;
+    Ok(())
+}
 
-        eprintln!("Processing reference {}: {} (count: {}, type: {})",
-            ref_idx + 1, ref_path, ref_count,
-            if is_query { "query" } else if is_target { "target" } else { "unknown" });
+fn print_query_summary(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    names: &NameSanitizer,
+    min_count: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut summary = QuerySummary::default();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            summary.add(&names.apply(&aln.query_name)?, &names.apply(&aln.target_name)?, &aln);
+        }
+        Ok(())
+    })?;
 
-        // Try to load genome metadata
-        let query_path = ref_path;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    summary.write(&mut handle, min_count)?;
+    Ok(())
+}
 
-        // Helper function to strip fasta extensions
-        let strip_fasta_ext = |p: &str| -> String {
-            let path_str = p.to_string();
-            // Try to remove common fasta extensions
-            for ext in &[".fasta.gz", ".fa.gz", ".fna.gz", ".fasta", ".fa", ".fna"] {
-                if path_str.ends_with(ext) {
-                    return path_str[..path_str.len() - ext.len()].to_string();
-                }
-            }
-            path_str
-        };
+fn print_chain_scores(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    names: &NameSanitizer,
+    max_gap: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut chainer = chain::Chainer::default();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            chainer.add(&names.apply(&aln.query_name)?, &names.apply(&aln.target_name)?, &aln);
+        }
+        Ok(())
+    })?;
 
-        // Get alignment file directory for relative path resolution
-        let aln_dir = std::path::Path::new(path)
-            .parent()
-            .unwrap_or_else(|| std::path::Path::new("."));
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    chainer.write(&mut handle, max_gap)?;
+    Ok(())
+}
 
-        // Try multiple strategies to find the GDB file
-        let mut gdb_path: Option<String> = None;
+fn write_identity_track(
+    path: &str,
+    track_path: &str,
+    mut track: track::IdentityTrack,
+    window: i64,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base_level = output.sequences.query.is_some() && output.sequences.target.is_some();
+    if !base_level {
+        eprintln!("No --fasta for both sides; identity track uses tracepoint segments as windows");
+    }
 
-        // Strategy 1: Try as absolute path (as-is)
-        if std::path::Path::new(query_path).exists() {
-            gdb_path = Some(query_path.clone());
+    let mut skipped = 0;
+    for_each_alignment(path, ctx, |aln| {
+        if !filter.accepts(&aln) {
+            return Ok(());
         }
-
-        // Strategy 2: Try adding .1gdb or .gdb extension to the original path
-        if gdb_path.is_none() {
-            for ext in &[".1gdb", ".gdb"] {
-                let with_ext = format!("{}{}", query_path, ext);
-                if std::path::Path::new(&with_ext).exists() {
-                    gdb_path = Some(with_ext);
-                    break;
-                }
+        let name = output.names.apply(track.sequence_name(&aln))?.into_owned();
+        if base_level {
+            if let Some(ops) = align::reconstruct(&aln, &output.sequences)? {
+                track.add_windows(&name, &aln, &ops, window);
+                return Ok(());
             }
+        } else if let Some(segments) = trace::segments(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        ) {
+            track.add_segments(&name, &aln, &segments);
+            return Ok(());
         }
+        skipped += 1;
+        Ok(())
+    })?;
 
-        // Strategy 3: Strip fasta extension and try with GDB extensions (absolute path)
-        if gdb_path.is_none() {
-            let base_path = strip_fasta_ext(query_path);
-            if base_path != *query_path {
-                for ext in &[".1gdb", ".gdb"] {
-                    let with_ext = format!("{}{}", base_path, ext);
-                    if std::path::Path::new(&with_ext).exists() {
-                        gdb_path = Some(with_ext);
-                        break;
-                    }
-                }
-            }
-        }
+    if skipped > 0 {
+        eprintln!("Warning: {} alignment(s) without a usable trace left out of the identity track", skipped);
+    }
+    if track_path == "-" {
+        track.write(&mut io::stdout().lock())?;
+    } else {
+        let file = std::fs::File::create(track_path)
+            .map_err(|e| format!("Cannot create identity track {}: {}", track_path, e))?;
+        let mut writer = io::BufWriter::new(file);
+        let result = track.write(&mut writer).map_err(Into::into);
+        flush_after(&mut writer, result)?;
+    }
+    Ok(())
+}
 
-        // Strategy 4: Try relative path with fasta extension stripped and GDB extension added
-        // (DO THIS BEFORE trying as-is, to avoid finding the fasta file itself)
-        if gdb_path.is_none() {
-            let base_path = strip_fasta_ext(query_path);
-            if base_path != *query_path {
-                for ext in &[".1gdb", ".gdb"] {
-                    let relative_with_ext = aln_dir.join(format!("{}{}", base_path, ext));
-                    if relative_with_ext.exists() {
-                        gdb_path = Some(relative_with_ext.to_string_lossy().to_string());
-                        break;
-                    }
-                }
-            }
+fn write_gene_coverage(
+    path: &str,
+    report_path: Option<&str>,
+    mut coverage: coverage::GeneCoverage,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            coverage.add(&aln, &output.names.apply(&aln.query_name)?, &output.names.apply(&aln.target_name)?);
         }
+        Ok(())
+    })?;
 
-        // Strategy 5: Try relative path with .1gdb or .gdb extension
-        if gdb_path.is_none() {
-            for ext in &[".1gdb", ".gdb"] {
-                let relative_with_ext = aln_dir.join(format!("{}{}", query_path, ext));
-                if relative_with_ext.exists() {
-                    gdb_path = Some(relative_with_ext.to_string_lossy().to_string());
-                    break;
-                }
-            }
+    match report_path {
+        None => coverage.write(&mut io::stdout().lock())?,
+        Some(report_path) => {
+            let file = std::fs::File::create(report_path)
+                .map_err(|e| format!("Cannot create coverage report {}: {}", report_path, e))?;
+            let mut writer = io::BufWriter::new(file);
+            let result = coverage.write(&mut writer).map_err(Into::into);
+            flush_after(&mut writer, result)?;
         }
+    }
+    Ok(())
+}
 
-        // Strategy 6: Try relative to alignment file directory (as-is)
-        // (Only as last resort, to avoid finding non-GDB files)
-        if gdb_path.is_none() {
-            let relative_path = aln_dir.join(query_path);
-            if relative_path.exists() {
-                gdb_path = Some(relative_path.to_string_lossy().to_string());
-            }
+fn write_target_depth(
+    path: &str,
+    depth_path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut depth = depth::DepthTrack::default();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            depth.add(&output.names.apply(&aln.target_name)?, aln.target_start, aln.target_end);
         }
+        Ok(())
+    })?;
 
-        let gdb_path = if let Some(found_path) = gdb_path {
-            found_path
-        } else {
-            eprintln!("Warning: Could not find query GDB file for reference: {}", query_path);
-            eprintln!("Tried:");
-            eprintln!("  - {}", query_path);
-            eprintln!("  - {}.1gdb / {}.gdb", query_path, query_path);
-            let base = strip_fasta_ext(query_path);
-            if base != *query_path {
-                eprintln!("  - {}.1gdb / {}.gdb", base, base);
-            }
-            eprintln!("  - Relative paths in {}", aln_dir.display());
-            eprintln!("Query contig-to-scaffold mappings will not be available");
-            query_path.clone()
-        };
+    if depth_path == "-" {
+        depth.write(&mut io::stdout().lock())?;
+    } else {
+        let file = std::fs::File::create(depth_path)
+            .map_err(|e| format!("Cannot create depth bedGraph {}: {}", depth_path, e))?;
+        let mut writer = io::BufWriter::new(file);
+        let result = depth.write(&mut writer).map_err(Into::into);
+        flush_after(&mut writer, result)?;
+    }
+    Ok(())
+}
 
-        // Try to load the GDB metadata
-        if let Ok((ref_names, ref_lengths, ref_offsets)) = OneFile::read_gdb_metadata(&gdb_path) {
-            if is_query {
-                query_seq_names = ref_names;
-                query_seq_lengths = ref_lengths;
-                query_contig_offsets = ref_offsets;
-                has_external_query = true;
-                eprintln!("Loaded query genome metadata from: {} ({} sequences)", gdb_path, query_seq_names.len());
-            } else if is_target {
-                target_seq_names = ref_names;
-                target_seq_lengths = ref_lengths;
-                target_contig_offsets = ref_offsets;
-                has_external_target = true;
-                eprintln!("Loaded target genome metadata from: {} ({} sequences)", gdb_path, target_seq_names.len());
+fn print_depth_histogram(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    space: track::Space,
+    max_depth: usize,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = ctx.metadata;
+    let (seq_names, seq_lengths) = match space {
+        track::Space::Target => (&metadata.target_seq_names, &metadata.target_seq_lengths),
+        track::Space::Query => (&metadata.query_seq_names, &metadata.query_seq_lengths),
+    };
+    let mut lengths = HashMap::new();
+    for (id, name) in seq_names {
+        lengths.insert(output.names.apply(name)?.into_owned(), seq_lengths.get(id).copied().unwrap_or(0));
+    }
+    if lengths.is_empty() {
+        eprintln!("Warning: no sequence lengths in the metadata; depth 0 cannot be counted");
+    }
+
+    let mut depth = depth::DepthTrack::default();
+    for_each_alignment(path, ctx, |aln| {
+        if filter.accepts(&aln) {
+            match space {
+                track::Space::Target => {
+                    depth.add(&output.names.apply(&aln.target_name)?, aln.target_start, aln.target_end);
+                }
+                track::Space::Query => {
+                    depth.add(&output.names.apply(&aln.query_name)?, aln.query_start, aln.query_end);
+                }
             }
-        } else {
-            eprintln!("Warning: Failed to load GDB metadata from: {}", gdb_path);
         }
-    }
+        Ok(())
+    })?;
 
-    // If we didn't load external target, use embedded skeleton
-    if !has_external_target && !embedded_names.is_empty() {
-        target_seq_names = embedded_names;
-        target_seq_lengths = embedded_lengths;
-        target_contig_offsets = embedded_offsets;
-        eprintln!("Using embedded skeleton for target genome ({} sequences)", target_seq_names.len());
-    }
+    let histogram = depth.histogram(&lengths, max_depth)?;
+    let mut sequences: Vec<(&String, &Vec<i64>)> = histogram.iter().collect();
+    sequences.sort_by(|(a, _), (b, _)| stats::natural_cmp(a, b).then_with(|| a.cmp(b)));
+    let mut totals = vec![0; max_depth + 1];
+    let label = |depth: usize| if depth == max_depth { format!("{}+", depth) } else { depth.to_string() };
 
-    // If this is a self-alignment (no external query), use target for query too
-    if !has_external_query && !target_seq_names.is_empty() {
-        query_seq_names = target_seq_names.clone();
-        query_seq_lengths = target_seq_lengths.clone();
-        query_contig_offsets = target_contig_offsets.clone();
-        eprintln!("Self-alignment detected: using target genome for query");
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "#sequence\tdepth\tbases")?;
+    for (name, bins) in sequences {
+        for (depth, &bases) in bins.iter().enumerate() {
+            totals[depth] += bases;
+            writeln!(handle, "{}\t{}\t{}", name, label(depth), bases)?;
+        }
     }
-
-    if query_seq_names.is_empty() && target_seq_names.is_empty() {
-        eprintln!("Warning: No sequence metadata found in file or external references");
+    for (depth, &bases) in totals.iter().enumerate() {
+        writeln!(handle, "*\t{}\t{}", label(depth), bases)?;
     }
 
-    let metadata = FileMetadata {
-        query_seq_names,
-        query_seq_lengths,
-        query_contig_offsets,
-        target_seq_names,
-        target_seq_lengths,
-        target_contig_offsets,
-    };
+    // One-line digest of the genome-wide rows
+    let total: i64 = totals.iter().sum();
+    let shares: Vec<String> = totals
+        .iter()
+        .enumerate()
+        .map(|(depth, &bases)| {
+            let percent = if total > 0 { 100.0 * bases as f64 / total as f64 } else { 0.0 };
+            format!("depth {}: {:.2}%", label(depth), percent)
+        })
+        .collect();
+    eprintln!("Depth over {} bp: {}", total, shares.join(", "));
+    Ok(())
+}
 
-    // Get trace spacing
-    let mut trace_spacing = 100; // default
-    loop {
-        match file.read_line() {
-            't' => { trace_spacing = file.int(0); break; }
-            'A' | '\0' => break,
-            _ => {}
+fn call_variants(
+    path: &str,
+    vcf_path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Header contigs in target ID order; several contigs can share a scaffold name
+    let mut targets: Vec<(&i64, &String)> = ctx.metadata.target_seq_names.iter().collect();
+    targets.sort_by_key(|&(&id, _)| id);
+    let mut contigs: Vec<(String, i64)> = Vec::new();
+    let mut seen = HashSet::new();
+    for (id, name) in targets {
+        let name = output.names.apply(name)?.into_owned();
+        if seen.insert(name.clone()) {
+            contigs.push((name, ctx.metadata.target_seq_lengths.get(id).copied().unwrap_or(0)));
         }
     }
+    let mut caller = vcf::VariantCaller::new(contigs);
+
+    let mut skipped = 0;
+    for_each_alignment(path, ctx, |aln| {
+        if !filter.accepts(&aln) {
+            return Ok(());
+        }
+        if !aln.out_of_bounds
+            && let Some((query, target, ops)) = align::reconstruct_with_bases(&aln, &output.sequences)?
+        {
+            let name = output.names.apply(&aln.target_name)?.into_owned();
+            caller.add(&name, &aln, &query, &target, &ops);
+        } else {
+            skipped += 1;
+        }
+        Ok(())
+    })?;
 
-    Ok((metadata, trace_spacing))
+    if skipped > 0 {
+        eprintln!("Warning: {} alignment(s) could not be realigned and made no calls", skipped);
+    }
+    if vcf_path == "-" {
+        caller.write(&mut io::stdout().lock())?;
+    } else {
+        let file = std::fs::File::create(vcf_path).map_err(|e| format!("Cannot create VCF {}: {}", vcf_path, e))?;
+        let mut writer = io::BufWriter::new(file);
+        let result = caller.write(&mut writer).map_err(Into::into);
+        flush_after(&mut writer, result)?;
+    }
+    Ok(())
 }
 
-fn print_metadata(
-    metadata: &FileMetadata,
-    trace_spacing: i64,
+fn validate_alignments(
     path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
+    let mut summary = validate::ValidationSummary::default();
+    for_each_alignment(path, ctx, |aln| {
+        if !filter.accepts(&aln) {
+            return Ok(());
+        }
+        let violations = validate::check(&aln);
+        for (_, description) in &violations {
+            writeln!(handle, "alignment {}: {}", aln.index, description)?;
+        }
+        summary.add(&violations);
+        Ok(())
+    })?;
 
-    writeln!(handle, "=== METADATA ===\n")?;
-    writeln!(handle, "File: {}", path)?;
-    writeln!(handle, "Trace spacing: {}", trace_spacing)?;
-    writeln!(handle, "Query sequences: {}", metadata.query_seq_names.len())?;
-    writeln!(handle, "Target sequences: {}", metadata.target_seq_names.len())?;
+    summary.write(&mut handle)?;
+    if !summary.is_valid() {
+        return Err("Validation failed".into());
+    }
+    Ok(())
+}
 
-    // Sort query sequences by ID for consistent output
-    if !metadata.query_seq_names.is_empty() {
-        let mut query_sequences: Vec<_> = metadata.query_seq_names.iter().collect();
-        query_sequences.sort_by_key(|&(&id, _)| id);
+fn verify_differences(
+    path: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    sample: Option<usize>,
+    tolerance: i64,
+    strict: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Spread the sample over the header's alignment count; without one, take the first N
+    let stride = match sample {
+        Some(sample) => {
+            let total = match &ctx.stream {
+                Some(stream) => stream.file.stats('A').ok(),
+                None => OneFile::open_read(path, None, None, 1)?.stats('A').ok(),
+            };
+            total.map_or(1, |(count, _, _)| (count.max(0) as usize).div_ceil(sample).max(1))
+        }
+        None => 1,
+    };
+    let sequences = ctx.sequences;
 
-        writeln!(handle, "\nQuery Sequences:")?;
-        for &(&id, ref name) in query_sequences.iter().take(10) {
-            let length = metadata.query_seq_lengths.get(&id).copied().unwrap_or(0);
-            writeln!(handle, "  {}) {} (length: {})", id, name, length)?;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let mut check = verify::DiffCheck::new(tolerance);
+    let mut selected = 0;
+    for_each_alignment(path, ctx, |aln| {
+        if aln.index % stride != 0 || sample.is_some_and(|sample| selected >= sample) || !filter.accepts(&aln) {
+            return Ok(());
         }
-        if query_sequences.len() > 10 {
-            writeln!(handle, "  ... and {} more", query_sequences.len() - 10)?;
+        selected += 1;
+        // Trimmed or split records carry recomputed counts, not the stored D
+        if aln.differences_source != DifferencesSource::DLine || aln.out_of_bounds {
+            check.add_unverifiable();
+            return Ok(());
+        }
+        let realigned = match aln.exact {
+            Some(exact) => Some(exact),
+            None => align::reconstruct(&aln, sequences)?.map(|ops| EditCounts::from_ops(&ops)),
+        };
+        match realigned {
+            Some(counts) => check.add(&mut handle, aln.index, aln.differences, counts.edit_distance())?,
+            None => check.add_unverifiable(),
         }
+        Ok(())
+    })?;
+
+    check.write(&mut handle)?;
+    if strict && check.beyond_tolerance() > 0 {
+        return Err(format!(
+            "{} alignments have recorded differences beyond the tolerance of {}",
+            check.beyond_tolerance(),
+            tolerance
+        ).into());
     }
+    Ok(())
+}
 
-    // Sort target sequences by ID for consistent output
-    if !metadata.target_seq_names.is_empty() {
-        let mut target_sequences: Vec<_> = metadata.target_seq_names.iter().collect();
-        target_sequences.sort_by_key(|&(&id, _)| id);
+/// Load `other`, with the input's rename options and metadata settings,
+/// then stream the input against it and print the `--compare` report.
+fn compare_files(
+    args: &Args,
+    other: &str,
+    ctx: &mut ParseContext,
+    filter: &AlignmentFilter,
+    output: &OutputConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut other_metadata, other_spacing, _) =
+        get_file_metadata(other, None, false, &resolve_options(args)?, fai::FaiMetadata::default())?;
+    rename_metadata(args, &mut other_metadata)?;
+    let sequences = Sequences::default();
+    let mut other_ctx = ParseContext {
+        metadata: &other_metadata,
+        length_source: ctx.length_source,
+        read_traces: false,
+        trace_spacing: other_spacing,
+        sequences: &sequences,
+        exact_identity: false,
+        strict: ctx.strict,
+        skip_missing_metadata: ctx.skip_missing_metadata,
+        allow_anonymous: ctx.allow_anonymous,
+        trim_ends: None,
+        clip: None,
+        respace: None,
+        explain: false,
+        stream: None,
+        excluded: ExcludedSequences::default(),
+        representatives: None,
+        warnings: ParseWarnings::default(),
+    };
+    let mut comparison = compare::Comparison::new(output.coord_base);
+    for_each_alignment(other, &mut other_ctx, |aln| {
+        if filter.accepts(&aln) {
+            comparison.add_other(&aln);
+        }
+        Ok(())
+    })?;
+    other_ctx.warnings.report();
 
-        writeln!(handle, "\nTarget Sequences:")?;
-        for &(&id, ref name) in target_sequences.iter().take(10) {
-            let length = metadata.target_seq_lengths.get(&id).copied().unwrap_or(0);
-            writeln!(handle, "  {}) {} (length: {})", id, name, length)?;
+    for_each_alignment(&args.input, ctx, |aln| {
+        if filter.accepts(&aln) {
+            comparison.add(&aln);
         }
-        if target_sequences.len() > 10 {
-            writeln!(handle, "  ... and {} more", target_sequences.len() - 10)?;
+        Ok(())
+    })?;
+    comparison.write(&mut io::stdout().lock(), &args.input, other)?;
+    Ok(())
+}
+
+/// Parse every alignment in file order and hand it to `visit`.
+/// Returns the number of alignments read.
+fn for_each_alignment<F>(
+    path: &str,
+    ctx: &mut ParseContext,
+    mut visit: F,
+) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: FnMut(AlignmentData) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let (mut file, mut current_line) = match ctx.stream.take() {
+        Some(stream) => (stream.file, stream.current_line),
+        None if is_pipe(path) => {
+            return Err(format!("{} is a pipe and has already been read; this mode needs a regular file", path).into());
         }
-    }
-    
-    // Count alignments
-    write!(handle, "\nCounting alignments...")?;
-    handle.flush()?;
-    
-    let mut file = OneFile::open_read(path, None, None, 1)?;
+        None => {
+            let mut file = OneFile::open_read(path, None, None, 1)?;
+            let current_line = file.read_line();
+            (file, current_line)
+        }
+    };
+    // The header's object count, when the file has one, tells a clean end from a truncated file
+    let expected = file.stats('A').ok().map(|(count, _, _)| count);
+
     let mut count = 0;
     loop {
-        match file.read_line() {
+        match current_line {
             '\0' => break,
-            'A' => count += 1,
-            _ => {}
+            't' => {
+                ctx.set_trace_spacing(file.int(0));
+                current_line = file.read_line();
+            }
+            'A' => {
+                let (aln, next_line) = parse_alignment(&mut file, ctx, count)?;
+                if let Some(aln) = aln {
+                    visit(aln)?;
+                }
+                count += 1;
+                current_line = next_line;
+            }
+            _ => {
+                current_line = file.read_line();
+            }
         }
     }
-    writeln!(handle, "\rTotal alignments: {}    ", count)?;
-    
-    Ok(())
+    if let Some(expected) = expected
+        && (count as i64) < expected
+    {
+        ctx.warnings.truncated = Some((expected, count));
+    }
+    Ok(count)
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-enum OutputFormat {
-    Human,
-    Paf,
+/// Open `path` for visiting records by object number, with its alignment
+/// count. Returns `None` when the file can't be read that way: a pipe, no
+/// index, or several trace spacings (which only a forward pass can
+/// attribute to their records).
+fn open_seekable(path: &str, ctx: &ParseContext) -> Result<Option<(OneFile, i64)>, Box<dyn std::error::Error>> {
+    if ctx.stream.is_some() {
+        return Ok(None);
+    }
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let Ok((count, _, _)) = file.stats('A') else {
+        return Ok(None);
+    };
+    if file.stats('t').is_ok_and(|(spacings, _, _)| spacings > 1) {
+        return Ok(None);
+    }
+    if count > 0 && file.goto('A', count).is_err() {
+        return Ok(None);
+    }
+    Ok(Some((file, count)))
+}
+
+/// Seek to each of the 0-based `objects` in turn and visit its alignment.
+fn visit_objects<F>(
+    file: &mut OneFile,
+    ctx: &mut ParseContext,
+    objects: impl IntoIterator<Item = i64>,
+    mut visit: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(AlignmentData) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let trace_spacing = ctx.trace_spacing;
+    for idx in objects {
+        file.goto('A', idx + 1)?;
+        file.read_line();
+        // A 't' line inside the previous record must not leak into this one
+        ctx.trace_spacing = trace_spacing;
+        let (aln, _) = parse_alignment(file, ctx, idx as usize)?;
+        if let Some(aln) = aln {
+            visit(aln)?;
+        }
+    }
+    Ok(())
 }
 
-fn read_single_alignment(
+/// Visit every alignment from last to first by seeking through the binary
+/// index. Returns `None`, having visited nothing, when the file can't be
+/// walked that way (see [`open_seekable`]).
+fn for_each_alignment_backward<F>(
     path: &str,
-    idx: usize,
-    metadata: &FileMetadata,
-    trace_spacing: i64,
-    format: OutputFormat,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = OneFile::open_read(path, None, None, 1)?;
+    ctx: &mut ParseContext,
+    visit: F,
+) -> Result<Option<usize>, Box<dyn std::error::Error>>
+where
+    F: FnMut(AlignmentData) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let Some((mut file, count)) = open_seekable(path, ctx)? else {
+        return Ok(None);
+    };
+    visit_objects(&mut file, ctx, (0..count).rev(), visit)?;
+    Ok(Some(count as usize))
+}
 
-    // Require O(1) access via binary index
-    if file.goto('A', (idx + 1) as i64).is_err() {
-        return Err(format!(
-            "Cannot access alignment {} directly. Binary index not available for this file.\n\
-             Please ensure the file has an associated .1idx index file.",
-            idx
-        ).into());
-    }
+/// A file opened for seeking and the objects to visit in it.
+type SeekCandidates = (OneFile, Vec<i64>);
 
-    eprintln!("Using O(1) binary index to jump to alignment {}", idx);
-    file.read_line(); // Read the 'A' line we jumped to
-    let (aln, _) = parse_alignment(&mut file, metadata)?;
+/// The file opened for seeking, with the object numbers of the alignments
+/// the region index lists as overlapping `region`, in file order. Returns
+/// `None` when there is no usable index or the file can't be read by
+/// seeking, so the caller scans; either way the path taken goes to stderr.
+fn indexed_region_objects(
+    path: &str,
+    ctx: &ParseContext,
+    region: &TargetRegion,
+) -> Result<Option<SeekCandidates>, Box<dyn std::error::Error>> {
+    let index = if ctx.stream.is_some() { None } else { region::RegionIndex::open(path, &ctx.metadata.target_contig_offsets)? };
+    let Some(mut index) = index else {
+        eprintln!("No region index for {}; scanning every alignment for --target-region (`oneview-rs index` builds one)", path);
+        return Ok(None);
+    };
+    let Some((file, _)) = open_seekable(path, ctx)? else {
+        eprintln!("{} has no binary index to seek with; scanning every alignment for --target-region", path);
+        return Ok(None);
+    };
+    let target_ids = sequence_ids(&ctx.metadata.target_seq_names, "target_", &region.name);
+    let mut objects = Vec::new();
+    for id in target_ids {
+        objects.extend(index.overlapping(id, region.start, region.end)?);
+    }
+    objects.sort_unstable();
+    eprintln!("Using region index {}: {} candidate alignments", region::sidecar_path(path), objects.len());
+    Ok(Some((file, objects)))
+}
 
-    print_alignment(&aln, trace_spacing, format)?;
-    Ok(())
+/// Contig IDs making up the sequence `name` on one side; without metadata
+/// for that side, names are the `query_<ID>`/`target_<ID>` placeholders.
+fn sequence_ids(names: &HashMap<i64, String>, placeholder: &str, name: &str) -> Vec<i64> {
+    if names.is_empty() {
+        return name.strip_prefix(placeholder).and_then(|id| id.parse().ok()).into_iter().collect();
+    }
+    names.iter().filter(|(_, candidate)| **candidate == name).map(|(&id, _)| id).collect()
 }
 
-fn read_all_alignments(
+/// The file opened for seeking, with the objects holding `seek`'s sequence,
+/// found by binary search on the A-line IDs of a file sorted by that side.
+/// Returns `None`, warning on stderr, when the file can't be sought or
+/// doesn't look sorted: unless `--assume-sorted`, evenly spaced records
+/// are sampled and must come in ID order.
+fn sorted_key_objects(
     path: &str,
-    metadata: &FileMetadata,
-    trace_spacing: i64,
-    format: OutputFormat,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = OneFile::open_read(path, None, None, 1)?;
+    ctx: &ParseContext,
+    seek: &SeekKey,
+) -> Result<Option<SeekCandidates>, Box<dyn std::error::Error>> {
+    let (side, field, ids) = match seek.side {
+        track::Space::Query => ("query", 0, sequence_ids(&ctx.metadata.query_seq_names, "query_", &seek.name)),
+        track::Space::Target => ("target", 3, sequence_ids(&ctx.metadata.target_seq_names, "target_", &seek.name)),
+    };
+    let option = format!("--seek-{}", side);
+    let Some((mut file, count)) = open_seekable(path, ctx)? else {
+        eprintln!("{} has no binary index to seek with; scanning every alignment for {}", path, option);
+        return Ok(None);
+    };
+    let mut peek = |idx: i64| -> Result<i64, Box<dyn std::error::Error>> {
+        file.goto('A', idx + 1)?;
+        file.read_line();
+        Ok(file.int(field))
+    };
 
-    let mut current_line = file.read_line();
-    loop {
-        match current_line {
-            '\0' => break,
-            'A' => {
-                let (aln, next_line) = parse_alignment(&mut file, metadata)?;
-                print_alignment(&aln, trace_spacing, format)?;
-                current_line = next_line;
+    const SAMPLES: i64 = 64;
+    if !seek.assume_sorted && count > 1 {
+        let mut previous = i64::MIN;
+        for sample in 0..=SAMPLES.min(count - 1) {
+            let id = peek(sample * (count - 1) / SAMPLES.min(count - 1))?;
+            if id < previous {
+                eprintln!(
+                    "Warning: {} is not sorted by {} ID; scanning every alignment for {} (--assume-sorted skips this check)",
+                    path, side, option
+                );
+                return Ok(None);
             }
-            _ => {
-                current_line = file.read_line();
+            previous = id;
+        }
+    }
+
+    // The contigs of a scaffold are numbered consecutively
+    let (Some(&first_id), Some(&last_id)) = (ids.iter().min(), ids.iter().max()) else {
+        eprintln!("Warning: no {} sequence is named {}", side, seek.name);
+        return Ok(Some((file, Vec::new())));
+    };
+    let mut lower_bound = |key: i64| -> Result<i64, Box<dyn std::error::Error>> {
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if peek(middle)? < key {
+                low = middle + 1;
+            } else {
+                high = middle;
             }
         }
+        Ok(low)
+    };
+    let start = lower_bound(first_id)?;
+    let end = lower_bound(last_id + 1)?;
+    eprintln!("Seeking through the binary index for {} {}: {} alignments", side, seek.name, end - start);
+    Ok(Some((file, (start..end).collect())))
+}
+
+/// Name, length and (offset, contig length) of one metadata sequence ID.
+fn sequence_metadata(
+    names: &HashMap<i64, String>,
+    lengths: &HashMap<i64, i64>,
+    offsets: &HashMap<i64, (i64, i64)>,
+    id: i64,
+) -> Option<(String, i64, (i64, i64))> {
+    Some((names.get(&id)?.clone(), *lengths.get(&id)?, *offsets.get(&id)?))
+}
+
+/// Count a T or X line of alignment `index` whose list could not be read,
+/// or fail under `--strict`.
+fn unreadable_trace(ctx: &mut ParseContext, index: usize, line_type: char) -> Result<(), Box<dyn std::error::Error>> {
+    if ctx.strict {
+        return Err(format!("Alignment {}: cannot read the list of its {} line", index, line_type).into());
     }
+    ctx.warnings.unreadable_traces += 1;
+    ctx.warnings.first_unreadable_trace.get_or_insert_with(|| format!("alignment {} ({} line)", index, line_type));
     Ok(())
 }
 
+/// Parse the alignment whose 'A' line was just read, up to the next record;
+/// `index` is its 0-based position in the file.
+/// Returns `None` for records skipped under `--skip-missing-metadata` and
+/// records `--trim-ends` leaves nothing of.
 fn parse_alignment(
     file: &mut OneFile,
-    metadata: &FileMetadata,
-) -> Result<(AlignmentData, char), Box<dyn std::error::Error>> {
+    ctx: &mut ParseContext,
+    index: usize,
+) -> Result<(Option<AlignmentData>, char), Box<dyn std::error::Error>> {
+    let metadata = ctx.metadata;
+
     // Read alignment coordinates from current 'A' line
     let query_id = file.int(0);
     let target_id = file.int(3);
+    if ctx.excluded.query.contains(&query_id) || ctx.excluded.target.contains(&target_id) {
+        return Ok((None, skip_record(file, ctx)));
+    }
+    let cluster_size = match &ctx.representatives {
+        Some(representatives) => match representatives.get(&index) {
+            Some(&size) => Some(size),
+            None => return Ok((None, skip_record(file, ctx))),
+        },
+        None => None,
+    };
 
-    let query_name = metadata
-        .query_seq_names
-        .get(&query_id)
-        .cloned()
-        .ok_or_else(|| format!("Query sequence with ID {} not found in metadata", query_id))?;
-    let target_name = metadata
-        .target_seq_names
-        .get(&target_id)
-        .cloned()
-        .ok_or_else(|| format!("Target sequence with ID {} not found in metadata", target_id))?;
-    let query_length = metadata
-        .query_seq_lengths
-        .get(&query_id)
-        .copied()
-        .ok_or_else(|| {
-            format!(
-                "Query sequence length for ID {} not found in metadata",
-                query_id
-            )
-        })?;
-    let target_length = metadata
-        .target_seq_lengths
-        .get(&target_id)
-        .copied()
-        .ok_or_else(|| {
-            format!(
-                "Target sequence length for ID {} not found in metadata",
-                target_id
-            )
-        })?;
-
-    let (query_offset, _) = metadata
-        .query_contig_offsets
-        .get(&query_id)
-        .copied()
-        .ok_or_else(|| {
-            format!(
-                "Contig offset for query sequence ID {} not found in metadata",
-                query_id
-            )
-        })?;
-
-    let (target_offset, target_contig_len) = metadata
-        .target_contig_offsets
-        .get(&target_id)
-        .copied()
-        .ok_or_else(|| {
-            format!(
-                "Contig offset for target sequence ID {} not found in metadata",
-                target_id
-            )
-        })?;
+    // Without any metadata for a side, placeholders keep the coordinates usable in contig space
+    let query_anonymous = ctx.allow_anonymous && metadata.query_seq_names.is_empty();
+    let target_anonymous = ctx.allow_anonymous && metadata.target_seq_names.is_empty();
+    let query = if query_anonymous {
+        Some((format!("query_{}", query_id), 0, (0, 0)))
+    } else {
+        sequence_metadata(
+            &metadata.query_seq_names,
+            &metadata.query_seq_lengths,
+            &metadata.query_contig_offsets,
+            query_id,
+        )
+    };
+    let target = if target_anonymous {
+        Some((format!("target_{}", target_id), 0, (0, 0)))
+    } else {
+        sequence_metadata(
+            &metadata.target_seq_names,
+            &metadata.target_seq_lengths,
+            &metadata.target_contig_offsets,
+            target_id,
+        )
+    };
+    let (query, target) = match (query, target) {
+        (Some(query), Some(target)) => (query, target),
+        (query, target) => {
+            let missing: Vec<(&'static str, i64)> = [
+                ("query", query_id, query.is_none()),
+                ("target", target_id, target.is_none()),
+            ]
+            .into_iter()
+            .filter(|&(_, _, missing)| missing)
+            .map(|(side, id, _)| (side, id))
+            .collect();
+            if !ctx.skip_missing_metadata {
+                let (side, id) = missing[0];
+                return Err(format!(
+                    "{} sequence with ID {} not found in metadata. The {} GDB or skeleton the file \
+                     references may not match the one found on disk (see --check-refs); \
+                     use --skip-missing-metadata to skip such alignments",
+                    if side == "query" { "Query" } else { "Target" },
+                    id,
+                    side
+                ).into());
+            }
+            for key in missing {
+                *ctx.warnings.missing_metadata.entry(key).or_insert(0) += 1;
+            }
+            ctx.warnings.skipped_missing_metadata += 1;
+            return Ok((None, skip_record(file, ctx)));
+        }
+    };
+    let (query_name, query_length, (query_offset, query_contig_len)) = query;
+    let (target_name, target_length, (target_offset, target_contig_len)) = target;
 
     let query_contig_start = file.int(1);
     let query_contig_end = file.int(2);
     let mut target_contig_start = file.int(4);
     let mut target_contig_end = file.int(5);
+    // As stored, before the strand flip, for --explain
+    let stored_target = (target_contig_start, target_contig_end);
 
+    let mut record_lengths = None;
+    let mut saw_differences = false;
+    let mut trace_diff_sum = None;
     let mut aln = AlignmentData {
+        index,
+        query_id,
+        target_id,
+        query_offset,
+        target_offset,
+        cluster_size,
         query_name,
         query_length,
         query_start: 0,
@@ -504,6 +4014,8 @@ fn parse_alignment(
         target_start: 0,
         target_end: 0,
         strand: '+',
+        trace_spacing: ctx.trace_spacing,
+        anonymous: query_anonymous || target_anonymous,
         ..Default::default()
     };
 
@@ -512,15 +4024,66 @@ fn parse_alignment(
         let line_type = file.read_line();
         match line_type {
             'R' => aln.strand = '-',
-            'D' => aln.differences = file.int(0),
-            'T' => aln.tracepoints = file.int_list().map(|v| v.to_vec()).unwrap_or_default(),
-            'X' => aln.trace_diffs = file.int_list().map(|v| v.to_vec()).unwrap_or_default(),
-            'A' | 'a' | 'g' | 'S' | '^' | '\0' => break line_type,
+            'L' => record_lengths = Some((file.int(0), file.int(1))),
+            'D' => {
+                aln.differences = file.int(0);
+                saw_differences = true;
+            }
+            'T' if ctx.read_traces => match file.int_list() {
+                Some(tracepoints) => aln.tracepoints = tracepoints.to_vec(),
+                None => unreadable_trace(ctx, index, 'T')?,
+            },
+            // Applies to the records that follow, not this one
+            't' => ctx.set_trace_spacing(file.int(0)),
+            'X' => match file.int_list() {
+                Some(diffs) => {
+                    trace_diff_sum = Some(diffs.iter().sum::<i64>());
+                    if ctx.read_traces {
+                        aln.trace_diffs = diffs.to_vec();
+                    }
+                }
+                // Without the diffs, a missing D line leaves the difference count unknown
+                None => unreadable_trace(ctx, index, 'X')?,
+            },
+            line_type if is_record_boundary(line_type) => break line_type,
             _ => {}
         }
     };
 
-    if matches!(aln.strand, '-' | '\'') {
+    if !saw_differences {
+        match trace_diff_sum {
+            Some(sum) => {
+                aln.differences = sum;
+                aln.differences_source = DifferencesSource::TraceDiffs;
+                ctx.warnings.differences_from_trace += 1;
+            }
+            None => {
+                aln.differences_source = DifferencesSource::Missing;
+                ctx.warnings.differences_missing += 1;
+            }
+        }
+    }
+
+    match record_lengths {
+        Some(_) if aln.anonymous => {}
+        Some((query_len, target_len)) => {
+            if query_len != aln.query_length || target_len != aln.target_length {
+                ctx.warnings.length_mismatches += 1;
+            }
+            if ctx.length_source == LengthSource::Aline {
+                aln.query_length = query_len;
+                aln.target_length = target_len;
+            }
+        }
+        None if ctx.length_source == LengthSource::Aline => {
+            ctx.warnings.missing_record_lengths += 1;
+        }
+        None => {}
+    }
+
+    // An anonymous target has no known length to flip against
+    let target_flipped = matches!(aln.strand, '-' | '\'') && !target_anonymous;
+    if target_flipped {
         let orig_start = target_contig_start;
         let orig_end = target_contig_end;
         // Reverse-complement target coordinates so start/end reflect forward strand
@@ -533,7 +4096,136 @@ fn parse_alignment(
     aln.target_start = add_offset(target_offset, target_contig_start)?;
     aln.target_end = add_offset(target_offset, target_contig_end)?;
 
-    Ok((aln, next_line))
+    // Anchor each side in its sequence; a length of 0 is unknown (anonymous), so only the start/end order can be checked
+    let violation = [
+        ("query", &aln.query_name, aln.query_start, aln.query_end, aln.query_length),
+        ("target", &aln.target_name, aln.target_start, aln.target_end, aln.target_length),
+    ]
+    .into_iter()
+    .find_map(|(side, name, start, end, length)| {
+        if start < 0 {
+            Some(format!("{} {} start {} is negative", side, name, start))
+        } else if start > end {
+            Some(format!("{} {} start {} is past its end {}", side, name, start, end))
+        } else if length > 0 && end > length {
+            Some(format!("{} {} end {} exceeds its length {}", side, name, end, length))
+        } else {
+            None
+        }
+    });
+    aln.out_of_bounds = violation.is_some();
+    if let Some(violation) = violation {
+        if ctx.strict {
+            return Err(format!("Alignment {} exceeds its sequence bounds: {}", index, violation).into());
+        }
+        ctx.warnings.out_of_bounds += 1;
+        ctx.warnings.first_out_of_bounds.get_or_insert_with(|| format!("alignment {}: {}", index, violation));
+    }
+
+    // A contig occupies [offset, offset + length) of its scaffold; records are relative to one contig
+    let contigs = [
+        ("query", &aln.query_name, query_offset, query_contig_len, aln.query_start, aln.query_end),
+        ("target", &aln.target_name, target_offset, target_contig_len, aln.target_start, aln.target_end),
+    ];
+    for (side, name, offset, contig_len, start, end) in contigs {
+        if contig_len <= 0 || (start >= offset && end <= offset + contig_len) {
+            continue;
+        }
+        let overshoot = if start < offset { start } else { end };
+        let message = format!(
+            "alignment {}: {} {}:{}-{} leaves its contig at {}-{} (overshooting coordinate {})",
+            index, side, name, start, end, offset, offset + contig_len, overshoot
+        );
+        if ctx.strict {
+            return Err(format!("Alignment crosses a contig boundary: {}", message).into());
+        }
+        ctx.warnings.contig_overruns += 1;
+        ctx.warnings.first_contig_overrun.get_or_insert(message);
+    }
+
+    if ctx.explain {
+        aln.raw = Some(Box::new(explain::RawRecord {
+            a_line: [query_id, query_contig_start, query_contig_end, target_id, stored_target.0, stored_target.1],
+            query_contig: (query_offset, query_contig_len),
+            target_contig: (target_offset, target_contig_len),
+            record_lengths,
+            target_flipped,
+        }));
+    }
+
+    if let Some(trim_ends) = &ctx.trim_ends {
+        match trim_ends.apply(&aln) {
+            split::Trim::Unchanged => {}
+            split::Trim::Shortened(trimmed) => {
+                ctx.warnings.trimmed += 1;
+                ctx.warnings.trimmed_query_bases += aln.query_span() - trimmed.query_span();
+                ctx.warnings.trimmed_target_bases += aln.target_span() - trimmed.target_span();
+                aln = *trimmed;
+            }
+            split::Trim::Empty => {
+                ctx.warnings.trimmed_away += 1;
+                return Ok((None, next_line));
+            }
+        }
+    }
+
+    if let Some(clip) = &ctx.clip {
+        match clip.apply(&aln) {
+            split::Clipping::Unchanged => {}
+            split::Clipping::Clipped(clipped) => {
+                ctx.warnings.clipped += 1;
+                aln = *clipped;
+            }
+            split::Clipping::Untraced => ctx.warnings.unclipped += 1,
+        }
+    }
+
+    if let Some(new_spacing) = ctx.respace
+        && aln.trace_spacing != new_spacing
+    {
+        // A later 't' line can change the spacing, so every record is checked again
+        if aln.tracepoints.is_empty() {
+            // Nothing to merge; only keeps a written file from switching spacings for it
+            aln.trace_spacing = new_spacing;
+        } else if aln.trace_spacing <= 0 || new_spacing % aln.trace_spacing != 0 {
+            return Err(format!(
+                "--respace {} is not a multiple of the trace spacing {} set by a later 't' line",
+                new_spacing, aln.trace_spacing
+            ).into());
+        } else if let Some((tracepoints, trace_diffs)) = trace::respace(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            new_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        ) {
+            aln.tracepoints = tracepoints;
+            aln.trace_diffs = trace_diffs;
+            aln.trace_spacing = new_spacing;
+        }
+    }
+
+    if ctx.exact_identity && !aln.out_of_bounds {
+        match align::reconstruct(&aln, ctx.sequences)? {
+            Some(ops) => aln.exact = Some(EditCounts::from_ops(&ops)),
+            None => ctx.warnings.exact_unavailable += 1,
+        }
+    }
+
+    Ok((Some(aln), next_line))
+}
+
+/// Read past the lines of the current record, still following its 't'
+/// lines; returns the first line after it.
+fn skip_record(file: &mut OneFile, ctx: &mut ParseContext) -> char {
+    loop {
+        match file.read_line() {
+            't' => ctx.set_trace_spacing(file.int(0)),
+            line_type if is_record_boundary(line_type) => return line_type,
+            _ => {}
+        }
+    }
 }
 
 fn add_offset(offset: i64, position: i64) -> Result<i64, Box<dyn std::error::Error>> {
@@ -542,53 +4234,117 @@ fn add_offset(offset: i64, position: i64) -> Result<i64, Box<dyn std::error::Err
         .ok_or_else(|| "Coordinate overflow when applying contig offset".into())
 }
 
-fn print_alignment(aln: &AlignmentData, trace_spacing: i64, format: OutputFormat) -> io::Result<()> {
-    match format {
-        OutputFormat::Human => print_alignment_human(aln, trace_spacing),
-        OutputFormat::Paf => print_alignment_paf(aln),
+fn print_alignment(out: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    // PAF carries them as tags instead
+    if output.print_offsets && output.format != OutputFormat::Paf {
+        eprintln!(
+            "Alignment {}: query {} contig offset {}, target {} contig offset {}",
+            aln.index, aln.query_name, aln.query_offset, aln.target_name, aln.target_offset
+        );
+    }
+    if let Some(split) = &output.split
+        && let Some(pieces) = split.apply(aln)
+    {
+        for piece in &pieces {
+            print_record(out, piece, output)?;
+        }
+        return Ok(());
     }
+    print_record(out, aln, output)
 }
 
-fn print_alignment_human(aln: &AlignmentData, trace_spacing: i64) -> io::Result<()> {
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-    
-    writeln!(handle, "Query: {}:{}-{}, query total length: {}", 
-        aln.query_name, aln.query_start, aln.query_end, aln.query_length)?;
-    writeln!(handle, "Target: {}:{}-{}, target total length: {}", 
-        aln.target_name, aln.target_start, aln.target_end, aln.target_length)?;
-    writeln!(handle, "Strand: {}", aln.strand)?;
-    writeln!(handle, "Differences: {}", aln.differences)?;
-    writeln!(handle, "Trace spacing: {}", trace_spacing)?;
+fn print_record(out: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    match output.format {
+        OutputFormat::Human => print_alignment_human(out, aln, output),
+        OutputFormat::Paf => {
+            print_alignment_paf(out, aln, output)?;
+            if output.transpose {
+                print_alignment_paf(out, &aln.transposed(), output)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Maf => print_alignment_maf(out, aln, output),
+        OutputFormat::Bedpe => print_alignment_bedpe(out, aln, output),
+        OutputFormat::Template => {
+            let template = output.template.as_ref().expect("template format without a template");
+            template.render(out, aln, output)
+        }
+    }
+}
+
+fn print_alignment_human(handle: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    let (query_start, query_end) = output.coord_base.interval(aln.query_start, aln.query_end);
+    let (target_start, target_end) = output.coord_base.interval(aln.target_start, aln.target_end);
+    let style = &output.style;
+    let identity = aln.identity();
     
-    print_trace_data(&mut handle, "Tracepoints", &aln.tracepoints)?;
-    print_trace_data(&mut handle, "Trace diffs", &aln.trace_diffs)?;
+    writeln!(handle, "{} {}:{}, query total length: {}", 
+        style.identity("Query:", identity), aln.query_name, style.dim(format!("{}-{}", query_start, query_end)), aln.query_length)?;
+    writeln!(handle, "{} {}:{}, target total length: {}", 
+        style.identity("Target:", identity), aln.target_name, style.dim(format!("{}-{}", target_start, target_end)), aln.target_length)?;
+    writeln!(handle, "Strand: {}", style.strand(aln.strand))?;
+    writeln!(handle, "Indel balance: {} (query span - target span)", aln.indel_balance())?;
+    if let Some(size) = aln.cluster_size {
+        writeln!(handle, "Redundant cluster: {} alignments, this one kept", size)?;
+    }
+    if let Some(piece) = aln.piece {
+        writeln!(handle, "Piece: {} of alignment {} (split at divergent or gapped segments)", piece, aln.index)?;
+    }
+    if aln.anonymous {
+        writeln!(handle, "Anonymous: no metadata; names are placeholders, lengths unknown, coordinates in contig space")?;
+    }
+    match aln.differences_source {
+        DifferencesSource::DLine => writeln!(handle, "Differences: {}", aln.differences)?,
+        DifferencesSource::TraceDiffs => {
+            writeln!(handle, "Differences: {} (no D line; summed from trace diffs)", aln.differences)?
+        }
+        DifferencesSource::Missing => writeln!(handle, "Differences: unknown (no D or X line)")?,
+    }
+    if aln.trace_spacing > 0 {
+        writeln!(handle, "Trace spacing: {}", aln.trace_spacing)?;
+    }
+    if let Some(annotations) = &output.annotations {
+        let features = annotations.overlapping(&aln.target_name, aln.target_start, aln.target_end);
+        if features.is_empty() {
+            writeln!(handle, "Features: none")?;
+        } else {
+            let listed: Vec<String> = features
+                .iter()
+                .map(|(label, covered)| format!("{} ({:.1}% covered)", label, 100.0 * covered))
+                .collect();
+            writeln!(handle, "Features: {}", listed.join(", "))?;
+        }
+    }
+    let has_traces = !aln.tracepoints.is_empty() || !aln.trace_diffs.is_empty();
+    if has_traces || output.empty_traces {
+        print_trace_summary(handle, aln, output)?;
+        if let Some(limit) = output.trace_values {
+            print_trace_data(handle, "Tracepoints", &aln.tracepoints, limit, output.trace_limit)?;
+            print_trace_data(handle, "Trace diffs", &aln.trace_diffs, limit, output.trace_limit)?;
+        }
+    }
 
     writeln!(handle)?;
     Ok(())
 }
 
-fn print_alignment_paf(aln: &AlignmentData) -> io::Result<()> {
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-
-    let query_span = (aln.query_end - aln.query_start).max(0);
-    let target_span = (aln.target_end - aln.target_start).max(0);
-
-    // Match ALNtoPAF calculation (when not computing CIGAR):
-    let block_length = query_span + target_span;
-    let matches = ((block_length - aln.differences) / 2).max(0);
-    let mapq = 255;
+fn print_alignment_paf(handle: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    let block_length = aln.block_length();
+    let matches = aln.matches();
+    let mapq = aln
+        .mapq
+        .or_else(|| mapq::record_mapq(output.mapq_mode, aln))
+        .unwrap_or(255);
 
     write!(
         handle,
         "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
-        aln.query_name,
+        output.names.apply(&aln.query_name)?,
         aln.query_length,
         aln.query_start,
         aln.query_end,
         aln.strand,
-        aln.target_name,
+        output.names.apply(&aln.target_name)?,
         aln.target_length,
         aln.target_start,
         aln.target_end,
@@ -597,9 +4353,69 @@ fn print_alignment_paf(aln: &AlignmentData) -> io::Result<()> {
         mapq
     )?;
 
-    write!(handle, "\tdf:i:{}", aln.differences)?;
+    if output.compat_alntopaf {
+        // ALNtoPAF without CIGAR: divergence over the block, then the raw difference count
+        writeln!(handle, "\tdv:F:{:.4}\tdf:i:{}", 1.0 - aln.identity(), aln.differences)?;
+        return Ok(());
+    }
+
+    if output.tags.contains(&PafTag::Df) {
+        if aln.differences_source == DifferencesSource::Missing {
+            write!(handle, "\tdf:i:-1")?;
+        } else {
+            write!(handle, "\tdf:i:{}", aln.differences)?;
+        }
+    }
+    if output.tags.contains(&PafTag::Nm)
+        && let Some(exact) = &aln.exact
+    {
+        write!(handle, "\tNM:i:{}", exact.edit_distance())?;
+    }
+    if output.tags.contains(&PafTag::De) {
+        match &aln.exact {
+            Some(exact) => write!(handle, "\tde:f:{:.4}", exact.gap_compressed_divergence())?,
+            None => write!(handle, "\tde:f:{:.4}", 1.0 - aln.identity())?,
+        }
+    }
+    if output.tags.contains(&PafTag::Id) {
+        write!(handle, "\tid:f:{:.4}", aln.identity())?;
+    }
+    if output.tags.contains(&PafTag::As) {
+        write!(handle, "\tAS:i:{}", output.scoring.score_alignment(aln))?;
+    }
+    if output.tags.contains(&PafTag::Ib) {
+        write!(handle, "\tib:i:{}", aln.indel_balance())?;
+    }
+    if output.print_offsets {
+        write!(handle, "\tqo:i:{}\tto:i:{}", aln.query_offset, aln.target_offset)?;
+    }
+    if let Some(size) = aln.cluster_size {
+        write!(handle, "\tnr:i:{}", size)?;
+    }
+    if aln.piece.is_some() {
+        write!(handle, "\toi:i:{}", aln.index)?;
+    }
+    if let Some(annotations) = &output.annotations {
+        let features = annotations.overlapping(&aln.target_name, aln.target_start, aln.target_end);
+        if !features.is_empty() {
+            let listed: Vec<String> =
+                features.iter().map(|(label, covered)| format!("{}={:.3}", label, covered)).collect();
+            write!(handle, "\tga:Z:{}", listed.join(","))?;
+        }
+    }
+
+    if let Some(pansn) = &output.pansn {
+        let query = pansn.split(&aln.query_name);
+        write!(handle, "\tsn:Z:{}", query.sample)?;
+        if let Some(haplotype) = query.haplotype {
+            write!(handle, "\thp:i:{}", haplotype)?;
+        }
+    }
 
-    let tp_pairs = if !aln.trace_diffs.is_empty() && !aln.tracepoints.is_empty() {
+    let tp_pairs = if output.tags.contains(&PafTag::Tp)
+        && !aln.trace_diffs.is_empty()
+        && !aln.tracepoints.is_empty()
+    {
         let pair_count = aln.trace_diffs.len().min(aln.tracepoints.len());
         aln.trace_diffs
             .iter()
@@ -618,17 +4434,343 @@ fn print_alignment_paf(aln: &AlignmentData) -> io::Result<()> {
     Ok(())
 }
 
-fn print_trace_data(handle: &mut io::StdoutLock, label: &str, data: &[i64]) -> io::Result<()> {
-    if !data.is_empty() {
-        writeln!(handle, "{}: {} values", label, data.len())?;
-        write!(handle, "  ")?;
-        for (i, &val) in data.iter().enumerate() {
-            if i > 0 { write!(handle, " ")?; }
-            write!(handle, "{}", val)?;
-        }
-        writeln!(handle)?;
+fn print_alignment_bedpe(handle: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    // BEDPE intervals are 0-based half-open like BED, whatever --coord-base says
+    let score = (aln.identity() * 1000.0).round() as i64;
+    writeln!(
+        handle,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t+\t{}",
+        output.names.apply(&aln.query_name)?,
+        aln.query_start,
+        aln.query_end,
+        output.names.apply(&aln.target_name)?,
+        aln.target_start,
+        aln.target_end,
+        aln.index,
+        score,
+        aln.strand
+    )
+}
+
+/// The `--paf-header` line: the 12 PAF columns, then each optional tag
+/// [`print_alignment_paf`] may write under these options with its meaning.
+/// Tags that only some records carry are marked as such.
+fn print_paf_header(handle: &mut dyn Write, output: &OutputConfig, exact_identity: bool) -> io::Result<()> {
+    write!(
+        handle,
+        "#query_name\tquery_length\tquery_start\tquery_end\tstrand\ttarget_name\ttarget_length\ttarget_start\ttarget_end\tmatches\tblock_length\tmapq"
+    )?;
+    let mut tags: Vec<&str> = Vec::new();
+    if output.compat_alntopaf {
+        tags.extend(["dv:F divergence over the block", "df:i number of differences"]);
     } else {
-        writeln!(handle, "{}: 0 values", label)?;
+        if output.tags.contains(&PafTag::Df) {
+            tags.push("df:i number of differences (-1 when unknown)");
+        }
+        if exact_identity && output.tags.contains(&PafTag::Nm) {
+            tags.push("NM:i edit distance from the sequences (realigned records only)");
+        }
+        if output.tags.contains(&PafTag::De) {
+            tags.push(if exact_identity {
+                "de:f gap-compressed divergence from the sequences (estimated where not realigned)"
+            } else {
+                "de:f estimated divergence"
+            });
+        }
+        if output.tags.contains(&PafTag::Id) {
+            tags.push("id:f estimated identity");
+        }
+        if output.tags.contains(&PafTag::As) {
+            tags.push("AS:i alignment score");
+        }
+        if output.tags.contains(&PafTag::Ib) {
+            tags.push("ib:i query span minus target span");
+        }
+        if output.print_offsets {
+            tags.extend(["qo:i query contig offset in the scaffold", "to:i target contig offset in the scaffold"]);
+        }
+        if output.collapsed {
+            tags.push("nr:i alignments collapsed into this one, itself included");
+        }
+        if output.split.is_some() {
+            tags.push("oi:i index of the original alignment (split records only)");
+        }
+        if output.annotations.is_some() {
+            tags.push("ga:Z overlapped target features as label=fraction (when any)");
+        }
+        if output.pansn.is_some() {
+            tags.extend(["sn:Z query sample", "hp:i query haplotype (when the name has one)"]);
+        }
+        if output.tags.contains(&PafTag::Tp) {
+            tags.push("tp:Z trace-diff,tracepoint pairs (when the record has a trace)");
+        }
+    }
+    for tag in tags {
+        write!(handle, "\t{}", tag)?;
     }
+    writeln!(handle)
+}
+
+fn print_maf_header(handle: &mut dyn Write, sequences: &Sequences) -> io::Result<()> {
+    writeln!(handle, "##maf version=1")?;
+    if sequences.query.is_none() || sequences.target.is_none() {
+        writeln!(handle, "# oneview-rs: .1aln files carry no bases and no --fasta was given for both genomes,")?;
+        writeln!(handle, "# so sequence text is N placeholders; only coordinates and sizes are meaningful")?;
+    }
+    writeln!(handle, "# oneview-rs: rows are padded with '-' at the end, not gapped base by base")?;
+    writeln!(handle)?;
     Ok(())
 }
+
+/// One MAF block per alignment, target first on the forward strand. For
+/// reverse alignments the query row is on '-', with its start measured on
+/// the reverse-complemented query as MAF requires.
+fn print_alignment_maf(handle: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    let query_span = aln.query_span();
+    let target_span = aln.target_span();
+    let query_start = if aln.strand == '-' {
+        aln.query_length - aln.query_end
+    } else {
+        aln.query_start
+    };
+    let (target_bases, query_bases) = match (&output.sequences.target, &output.sequences.query) {
+        (Some(target), Some(query)) => (
+            target.fetch(aln.target_id, aln.target_start, aln.target_end, '+')?,
+            query.fetch(aln.query_id, aln.query_start, aln.query_end, aln.strand)?,
+        ),
+        _ => ("N".repeat(target_span as usize), "N".repeat(query_span as usize)),
+    };
+    let columns = query_span.max(target_span) as usize;
+    let padded = |mut text: String| {
+        let padding = columns.saturating_sub(text.len());
+        text.push_str(&"-".repeat(padding));
+        text
+    };
+
+    writeln!(handle, "a score={}", aln.matches())?;
+    writeln!(
+        handle,
+        "s {} {} {} + {} {}",
+        output.names.apply(&aln.target_name)?,
+        aln.target_start,
+        target_span,
+        aln.target_length,
+        padded(target_bases)
+    )?;
+    writeln!(
+        handle,
+        "s {} {} {} {} {} {}",
+        output.names.apply(&aln.query_name)?,
+        query_start,
+        query_span,
+        aln.strand,
+        aln.query_length,
+        padded(query_bases)
+    )?;
+    writeln!(handle)?;
+    Ok(())
+}
+
+fn print_trace_summary(handle: &mut dyn Write, aln: &AlignmentData, output: &OutputConfig) -> io::Result<()> {
+    if aln.tracepoints.is_empty() {
+        return writeln!(handle, "Trace segments: 0");
+    }
+    let summary = trace::segments(
+        aln.query_start - aln.query_offset,
+        aln.query_end - aln.query_offset,
+        aln.trace_spacing,
+        &aln.tracepoints,
+        &aln.trace_diffs,
+    )
+    .and_then(|segments| trace::summarize(&segments));
+    let Some(summary) = summary else {
+        return writeln!(
+            handle,
+            "Trace segments: {} values, not consistent with the query span (see --validate)",
+            aln.tracepoints.len()
+        );
+    };
+
+    // Segment coordinates are relative to the alignment start on the query
+    let query_interval = |start: i64, end: i64| output.coord_base.interval(aln.query_start + start, aln.query_start + end);
+    writeln!(handle, "Trace segments: {}", summary.segments)?;
+    writeln!(
+        handle,
+        "Segment divergence: min {:.4}, mean {:.4}, max {:.4}",
+        summary.min_divergence,
+        summary.mean_divergence,
+        summary.worst.divergence()
+    )?;
+    let (worst_start, worst_end) = query_interval(summary.worst.query_start, summary.worst.query_end);
+    writeln!(
+        handle,
+        "Worst segment: query {}-{}, {} diffs, divergence {:.4}",
+        worst_start,
+        worst_end,
+        summary.worst.diffs,
+        summary.worst.divergence()
+    )?;
+    match summary.longest_exact_run {
+        Some((count, start, end)) => {
+            let (run_start, run_end) = query_interval(start, end);
+            writeln!(handle, "Longest exact run: {} segments, query {}-{}", count, run_start, run_end)
+        }
+        None => writeln!(handle, "Longest exact run: none"),
+    }
+}
+
+/// Print a trace list: whole, as its first and last `limit` values when
+/// `limit` is set, or cut after `cap` values when that prints fewer.
+fn print_trace_data(handle: &mut dyn Write, label: &str, data: &[i64], limit: usize, cap: Option<usize>) -> io::Result<()> {
+    writeln!(handle, "{}: {} values", label, data.len())?;
+    if data.is_empty() {
+        return Ok(());
+    }
+    let join = |values: &[i64]| values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ");
+    let shown = if limit > 0 && data.len() > 2 * limit { 2 * limit } else { data.len() };
+    if let Some(cap) = cap
+        && cap < shown
+    {
+        writeln!(handle, "  {} ... ({} more)", join(&data[..cap]), data.len() - cap)
+    } else if limit > 0 && data.len() > 2 * limit {
+        writeln!(
+            handle,
+            "  {} ... ({} values elided) ... {}",
+            join(&data[..limit]),
+            data.len() - 2 * limit,
+            join(&data[data.len() - limit..])
+        )
+    } else {
+        writeln!(handle, "  {}", join(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn empty_metadata() -> FileMetadata {
+        FileMetadata {
+            query_seq_names: HashMap::new(),
+            query_seq_lengths: HashMap::new(),
+            query_contig_offsets: HashMap::new(),
+            target_seq_names: HashMap::new(),
+            target_seq_lengths: HashMap::new(),
+            target_contig_offsets: HashMap::new(),
+        }
+    }
+
+    fn context<'a>(metadata: &'a FileMetadata, sequences: &'a Sequences) -> ParseContext<'a> {
+        ParseContext {
+            metadata,
+            length_source: LengthSource::Metadata,
+            read_traces: true,
+            trace_spacing: 100,
+            sequences,
+            exact_identity: false,
+            strict: false,
+            skip_missing_metadata: false,
+            allow_anonymous: true,
+            trim_ends: None,
+            clip: None,
+            respace: None,
+            explain: false,
+            stream: None,
+            excluded: ExcludedSequences::default(),
+            representatives: None,
+            warnings: ParseWarnings::default(),
+        }
+    }
+
+    /// Keeps what reaches it, and fails every write once `fail` is set.
+    struct Recorder {
+        written: Rc<RefCell<Vec<u8>>>,
+        fail: bool,
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.fail {
+                return Err(io::Error::other("disk full"));
+            }
+            self.written.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn buffered(fail: bool) -> (io::BufWriter<Recorder>, Rc<RefCell<Vec<u8>>>) {
+        let written = Rc::new(RefCell::new(Vec::new()));
+        let recorder = Recorder {
+            written: Rc::clone(&written),
+            fail,
+        };
+        (io::BufWriter::new(recorder), written)
+    }
+
+    #[test]
+    fn records_before_an_error_are_flushed() {
+        let (mut out, written) = buffered(false);
+        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+            writeln!(out, "record 0")?;
+            writeln!(out, "record 1")?;
+            Err("Alignment 2: broken record".into())
+        })();
+        assert!(written.borrow().is_empty(), "still buffered");
+        let error = flush_after(&mut out, result).unwrap_err();
+        assert_eq!(error.to_string(), "Alignment 2: broken record");
+        assert_eq!(written.borrow().as_slice(), b"record 0\nrecord 1\n");
+    }
+
+    #[test]
+    fn the_original_error_wins_over_a_flush_error() {
+        let (mut out, _) = buffered(true);
+        writeln!(out, "record 0").unwrap();
+        let error = flush_after(&mut out, Err("parse failed".into())).unwrap_err();
+        assert_eq!(error.to_string(), "parse failed");
+
+        let (mut out, _) = buffered(true);
+        writeln!(out, "record 0").unwrap();
+        let error = flush_after(&mut out, Ok(())).unwrap_err();
+        assert_eq!(error.to_string(), "disk full");
+    }
+
+    #[test]
+    fn unreadable_trace_is_counted_once_per_line() {
+        let (metadata, sequences) = (empty_metadata(), Sequences::default());
+        let mut ctx = context(&metadata, &sequences);
+        unreadable_trace(&mut ctx, 3, 'T').unwrap();
+        unreadable_trace(&mut ctx, 7, 'X').unwrap();
+        assert_eq!(ctx.warnings.unreadable_traces, 2);
+        assert_eq!(ctx.warnings.first_unreadable_trace.as_deref(), Some("alignment 3 (T line)"));
+    }
+
+    #[test]
+    fn unreadable_trace_fails_under_strict() {
+        let (metadata, sequences) = (empty_metadata(), Sequences::default());
+        let mut ctx = context(&metadata, &sequences);
+        ctx.strict = true;
+        let error = unreadable_trace(&mut ctx, 3, 'T').unwrap_err();
+        assert_eq!(error.to_string(), "Alignment 3: cannot read the list of its T line");
+        assert_eq!(ctx.warnings.unreadable_traces, 0);
+    }
+
+    #[test]
+    fn compare_refuses_filters_tied_to_the_input() {
+        // Their state comes from --input's IDs or alignments and would be misapplied to the other file
+        for flag in [
+            "--dedup",
+            "--min-query-len=1",
+            "--max-per-pair=1",
+            "--min-covered-frac=0.5",
+            "--collapse-redundant",
+        ] {
+            let parsed = Args::try_parse_from(["oneview-rs", "a.1aln", "--compare", "b.1aln", flag]);
+            assert!(parsed.is_err(), "{} was accepted with --compare", flag);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Mapping-quality estimation for PAF output.
+//!
+//! .1aln files carry no mapping quality, so it has to be derived:
+//!
+//! * `constant:N` writes N for every record (255, "unavailable", by default).
+//! * `unique` counts, for each alignment, the other alignments of the same
+//!   query sequence that overlap it by more than half of its query span, and
+//!   assigns `60 / (1 + count)`: 60 for unique placements, 30 for two-fold,
+//!   20 for three-fold and so on. This needs every alignment at once.
+//! * `length-identity` scores each record on its own as
+//!   `60 * clamp(1 - 10 * divergence, 0, 1) * min(1, span / 10 kb)`, so
+//!   anything at 10% divergence or more gets 0 and short alignments are
+//!   scaled down linearly.
+
+use crate::AlignmentData;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub const MAX_MAPQ: u8 = 60;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MapqMode {
+    Constant(u8),
+    Unique,
+    LengthIdentity,
+}
+
+impl FromStr for MapqMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unique" => Ok(MapqMode::Unique),
+            "length-identity" => Ok(MapqMode::LengthIdentity),
+            _ => match s.strip_prefix("constant:") {
+                Some(value) => value
+                    .parse()
+                    .map(MapqMode::Constant)
+                    .map_err(|_| format!("invalid constant MAPQ '{}' (expected 0-255)", value)),
+                None => Err(format!(
+                    "unknown MAPQ mode '{}' (expected constant:N, unique or length-identity)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Per-record MAPQ for the modes that don't need other alignments.
+/// Returns `None` for `unique`, which must go through [`assign_unique`].
+pub fn record_mapq(mode: MapqMode, aln: &AlignmentData) -> Option<u8> {
+    match mode {
+        MapqMode::Constant(value) => Some(value),
+        MapqMode::LengthIdentity => Some(length_identity_mapq(aln)),
+        MapqMode::Unique => None,
+    }
+}
+
+fn length_identity_mapq(aln: &AlignmentData) -> u8 {
+    let divergence = 1.0 - aln.identity();
+    let identity_factor = (1.0 - 10.0 * divergence).clamp(0.0, 1.0);
+    let length_factor = (aln.query_span() as f64 / 10_000.0).min(1.0);
+    (f64::from(MAX_MAPQ) * identity_factor * length_factor).round() as u8
+}
+
+/// Overlap-based MAPQ for every alignment in `alignments`, in the same order.
+pub fn assign_unique(alignments: &[AlignmentData]) -> Vec<u8> {
+    let mut by_query: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, aln) in alignments.iter().enumerate() {
+        by_query.entry(aln.query_name.as_str()).or_default().push(i);
+    }
+
+    let mut overlapping = vec![0u32; alignments.len()];
+    for indices in by_query.values_mut() {
+        indices.sort_by_key(|&i| alignments[i].query_start);
+        for (pos, &i) in indices.iter().enumerate() {
+            let a = &alignments[i];
+            // Sorted by start, so only later records can begin inside `a`
+            for &j in &indices[pos + 1..] {
+                let b = &alignments[j];
+                if b.query_start >= a.query_end {
+                    break;
+                }
+                let overlap = a.query_end.min(b.query_end) - b.query_start;
+                if 2 * overlap > a.query_span() {
+                    overlapping[i] += 1;
+                }
+                if 2 * overlap > b.query_span() {
+                    overlapping[j] += 1;
+                }
+            }
+        }
+    }
+
+    overlapping
+        .into_iter()
+        .map(|count| (u32::from(MAX_MAPQ) / (1 + count)) as u8)
+        .collect()
+}
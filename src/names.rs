@@ -0,0 +1,151 @@
+//! Sequence-name sanitizing for tabular output formats.
+//!
+//! GDBs keep the full FASTA header, so names may contain whitespace that
+//! breaks column-based parsers downstream.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::io;
+use std::str::FromStr;
+
+/// What to do with a name containing whitespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamePolicy {
+    /// Emit the name unchanged
+    Full,
+    /// Truncate at the first whitespace, as most aligners do
+    FirstWord,
+    /// Refuse to emit the record
+    Error,
+    /// Replace every whitespace character with the given string
+    Replace(String),
+}
+
+impl FromStr for NamePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(NamePolicy::Full),
+            "first-word" => Ok(NamePolicy::FirstWord),
+            "error" => Ok(NamePolicy::Error),
+            _ => match s.strip_prefix("replace:") {
+                Some(replacement) if !replacement.chars().any(char::is_whitespace) => {
+                    Ok(NamePolicy::Replace(replacement.to_string()))
+                }
+                Some(_) => Err("replacement must not contain whitespace".to_string()),
+                None => Err(format!(
+                    "unknown name policy '{}' (expected full, first-word, error or replace:STR)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Applies a [`NamePolicy`], warning once the first time a name is altered.
+#[derive(Debug)]
+pub struct NameSanitizer {
+    policy: NamePolicy,
+    warned: Cell<bool>,
+}
+
+impl NameSanitizer {
+    pub fn new(policy: NamePolicy) -> Self {
+        NameSanitizer {
+            policy,
+            warned: Cell::new(false),
+        }
+    }
+
+    pub fn apply<'a>(&self, name: &'a str) -> io::Result<Cow<'a, str>> {
+        if !name.chars().any(char::is_whitespace) {
+            return Ok(Cow::Borrowed(name));
+        }
+
+        let sanitized = match &self.policy {
+            NamePolicy::Full => return Ok(Cow::Borrowed(name)),
+            NamePolicy::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Sequence name {:?} contains whitespace (see --name-policy)", name),
+                ));
+            }
+            NamePolicy::FirstWord => match name.split_whitespace().next() {
+                Some(word) => Cow::Borrowed(word),
+                // An empty name would shift every column after it
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Sequence name {:?} has no first word (see --name-policy)", name),
+                    ));
+                }
+            },
+            NamePolicy::Replace(replacement) => Cow::Owned(
+                name.split(char::is_whitespace)
+                    .collect::<Vec<_>>()
+                    .join(replacement),
+            ),
+        };
+
+        if !self.warned.replace(true) {
+            eprintln!(
+                "Warning: sequence names contain whitespace; {:?} was written as {:?} (see --name-policy)",
+                name, sanitized
+            );
+        }
+        Ok(sanitized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(policy: &str, name: &str) -> io::Result<String> {
+        NameSanitizer::new(policy.parse().unwrap()).apply(name).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn first_word_cuts_at_tabs_and_spaces() {
+        assert_eq!(apply("first-word", "chr1\tassembled").unwrap(), "chr1");
+        assert_eq!(apply("first-word", "chr1 Homo sapiens").unwrap(), "chr1");
+        assert_eq!(apply("first-word", "  chr1").unwrap(), "chr1");
+    }
+
+    #[test]
+    fn first_word_rejects_all_whitespace() {
+        let error = apply("first-word", " \t ").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replace_keeps_every_word() {
+        assert_eq!(apply("replace:_", "chr1\tHomo sapiens").unwrap(), "chr1_Homo_sapiens");
+        assert_eq!(apply("replace:_", " \t ").unwrap(), "___");
+    }
+
+    #[test]
+    fn full_and_error() {
+        assert_eq!(apply("full", "chr1\tx").unwrap(), "chr1\tx");
+        assert!(apply("error", "chr1\tx").is_err());
+        assert_eq!(apply("error", "chr1").unwrap(), "chr1");
+    }
+
+    #[test]
+    fn header_characters_without_whitespace_pass_through() {
+        // Only whitespace breaks the tabular formats; '@' and '>' are ordinary characters there
+        for policy in ["full", "first-word", "error", "replace:_"] {
+            assert_eq!(apply(policy, "@read1").unwrap(), "@read1");
+            assert_eq!(apply(policy, ">chr1").unwrap(), ">chr1");
+        }
+        assert_eq!(apply("first-word", ">chr1 description").unwrap(), ">chr1");
+    }
+
+    #[test]
+    fn parses_policies() {
+        assert!("replace: ".parse::<NamePolicy>().is_err());
+        assert!("truncate".parse::<NamePolicy>().is_err());
+        assert_eq!("replace:%20".parse::<NamePolicy>(), Ok(NamePolicy::Replace("%20".to_string())));
+    }
+}
@@ -0,0 +1,77 @@
+//! Paging human output on a terminal, the way git does.
+//!
+//! The pager is `$PAGER`, else `less -FRX` (`-F` exits at once when the
+//! output fits on one screen, `-R` keeps colors, `-X` leaves the output on
+//! the screen), run through the shell. An empty `PAGER` or `cat` disables
+//! it. Standard output is redirected into the pager's input for the rest of
+//! the run, so every writer follows without being handed a new handle;
+//! standard error still goes to the terminal. When the user quits the
+//! pager early, writes fail with a broken pipe, which the caller treats as
+//! a normal end. Only available on Unix.
+
+use std::io::{self, Write};
+use std::process::Child;
+
+pub struct Pager {
+    child: Child,
+}
+
+/// Whether `error` is a write into a pager that has exited.
+pub fn is_broken_pipe(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+impl Pager {
+    /// Start the pager and point standard output at it, or return `None`
+    /// when paging is disabled.
+    #[cfg(unix)]
+    pub fn start() -> io::Result<Option<Self>> {
+        use std::os::fd::AsRawFd;
+        use std::process::{Command, Stdio};
+        const DEFAULT_PAGER: &str = "less -FRX";
+
+        let command = std::env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+        let command = command.trim();
+        if command.is_empty() || command == "cat" {
+            return Ok(None);
+        }
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command).stdin(Stdio::piped());
+        // A bare `less` from PAGER still gets the defaults, unless LESS says otherwise
+        if std::env::var_os("LESS").is_none() {
+            shell.env("LESS", "FRX");
+        }
+        let mut child = shell
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("Cannot start pager '{}': {}", command, e)))?;
+        let stdin = child.stdin.take().expect("pager stdin is piped");
+        io::stdout().flush()?;
+        // SAFETY: both descriptors are open; dup2 replaces descriptor 1 atomically
+        if unsafe { libc::dup2(stdin.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Descriptor 1 now holds the pipe; the original handle can close
+        drop(stdin);
+        Ok(Some(Pager { child }))
+    }
+
+    #[cfg(not(unix))]
+    pub fn start() -> io::Result<Option<Self>> {
+        Ok(None)
+    }
+
+    /// Close the pager's input and wait for the user to quit it. Standard
+    /// output goes to `/dev/null` afterwards.
+    pub fn finish(mut self) {
+        let _ = io::stdout().flush();
+        #[cfg(unix)]
+        if let Ok(null) = std::fs::OpenOptions::new().write(true).open("/dev/null") {
+            use std::os::fd::AsRawFd;
+            // SAFETY: as in `start`; this closes the last write end of the pipe
+            unsafe {
+                libc::dup2(null.as_raw_fd(), libc::STDOUT_FILENO);
+            }
+        }
+        let _ = self.child.wait();
+    }
+}
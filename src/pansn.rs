@@ -0,0 +1,132 @@
+//! PanSN sequence-name handling (`sample#haplotype#contig`).
+//!
+//! See https://github.com/pangenome/PanSN-spec. Only the first two delimiters
+//! are structural: anything after the haplotype field belongs to the contig
+//! name, so `HG002#1#chr1#alt` is contig `chr1#alt` of haplotype 1.
+
+/// A sequence name split into its PanSN components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanSnName<'a> {
+    pub sample: &'a str,
+    pub haplotype: Option<u32>,
+    pub contig: &'a str,
+}
+
+/// Parse `name` as `sample<delim>haplotype<delim>contig`.
+///
+/// Returns `None` when the name does not follow the pattern: fewer than two
+/// delimiters, an empty sample or contig, or a non-numeric haplotype.
+pub fn parse(name: &str, delim: char) -> Option<PanSnName<'_>> {
+    let mut fields = name.splitn(3, delim);
+    let sample = fields.next()?;
+    let haplotype = fields.next()?;
+    let contig = fields.next()?;
+
+    if sample.is_empty() || contig.is_empty() {
+        return None;
+    }
+    let haplotype = haplotype.parse().ok()?;
+
+    Some(PanSnName {
+        sample,
+        haplotype: Some(haplotype),
+        contig,
+    })
+}
+
+/// PanSN parser that never fails: names that don't match the pattern become
+/// their own sample with no haplotype.
+#[derive(Debug, Clone, Copy)]
+pub struct PanSn {
+    delim: char,
+}
+
+impl PanSn {
+    pub fn new(delim: char) -> Self {
+        PanSn { delim }
+    }
+
+    pub fn split<'a>(&self, name: &'a str) -> PanSnName<'a> {
+        parse(name, self.delim).unwrap_or(PanSnName {
+            sample: name,
+            haplotype: None,
+            contig: name,
+        })
+    }
+
+    pub fn sample<'a>(&self, name: &'a str) -> &'a str {
+        self.split(name).sample
+    }
+
+    /// Number of names that don't follow the PanSN pattern.
+    pub fn count_unmatched<'a, I>(&self, names: I) -> usize
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        names
+            .into_iter()
+            .filter(|name| parse(name, self.delim).is_none())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delimiters_after_the_haplotype_belong_to_the_contig() {
+        assert_eq!(
+            parse("a#1#chr#x", '#'),
+            Some(PanSnName {
+                sample: "a",
+                haplotype: Some(1),
+                contig: "chr#x",
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_names_that_do_not_follow_the_pattern() {
+        // One delimiter: no haplotype field
+        assert_eq!(parse("a#chr", '#'), None);
+        assert_eq!(parse("chr1", '#'), None);
+        // Non-numeric haplotype
+        assert_eq!(parse("a#x#chr", '#'), None);
+    }
+
+    #[test]
+    fn rejects_empty_fields() {
+        assert_eq!(parse("#1#chr", '#'), None);
+        assert_eq!(parse("a##chr", '#'), None);
+        assert_eq!(parse("a#1#", '#'), None);
+    }
+
+    #[test]
+    fn custom_delimiter() {
+        assert_eq!(
+            parse("HG002.2.chr1#alt", '.'),
+            Some(PanSnName {
+                sample: "HG002",
+                haplotype: Some(2),
+                contig: "chr1#alt",
+            })
+        );
+        assert_eq!(parse("HG002#2#chr1", '.'), None);
+    }
+
+    #[test]
+    fn unmatched_names_become_their_own_sample() {
+        let pansn = PanSn::new('#');
+        assert_eq!(
+            pansn.split("a#chr"),
+            PanSnName {
+                sample: "a#chr",
+                haplotype: None,
+                contig: "a#chr",
+            }
+        );
+        let names = ["a#1#chr".to_string(), "a#chr".to_string(), "chr1".to_string()];
+        assert_eq!(pansn.count_unmatched(&names), 2);
+    }
+}
@@ -0,0 +1,169 @@
+//! BLAST-style stacked display of one base-level alignment (`--pretty`).
+//!
+//! Each block shows the query, a midline and the target, wrapped at a fixed
+//! width, with the position of the first and last base of every row. The
+//! target row is in alignment orientation, so on '-' its positions count
+//! down along the forward strand. The midline marks matches with `|` and
+//! mismatches with `*`; gaps are `-` in the row missing the base.
+
+use crate::AlignmentData;
+use crate::align::Op;
+use crate::coords::CoordBase;
+use std::io::{self, Write};
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+pub struct PrettyOptions {
+    pub width: usize,
+    /// Query interval to show, 0-based half-open
+    pub region: Option<(i64, i64)>,
+    pub color: bool,
+    pub coord_base: CoordBase,
+}
+
+/// One alignment column: the bases (`-` for a gap), the op, and the query
+/// and target offsets from the alignment start before it.
+struct Column {
+    query: u8,
+    target: u8,
+    op: Op,
+    query_offset: i64,
+    target_offset: i64,
+}
+
+fn columns(query: &[u8], target: &[u8], ops: &[Op]) -> Vec<Column> {
+    let (mut i, mut j) = (0usize, 0usize);
+    let mut columns = Vec::with_capacity(ops.len());
+    for &op in ops {
+        let (query_base, target_base) = match op {
+            Op::Match | Op::Mismatch => (query[i], target[j]),
+            Op::Insertion => (query[i], b'-'),
+            Op::Deletion => (b'-', target[j]),
+        };
+        columns.push(Column {
+            query: query_base,
+            target: target_base,
+            op,
+            query_offset: i as i64,
+            target_offset: j as i64,
+        });
+        if op != Op::Deletion {
+            i += 1;
+        }
+        if op != Op::Insertion {
+            j += 1;
+        }
+    }
+    columns
+}
+
+impl PrettyOptions {
+    fn position(&self, position: i64) -> i64 {
+        self.coord_base.interval(position, position + 1).0
+    }
+
+    fn write_row(&self, out: &mut dyn Write, bases: impl Iterator<Item = (u8, Op)>) -> io::Result<()> {
+        for (base, op) in bases {
+            let color = match op {
+                _ if !self.color => None,
+                Op::Mismatch => Some(RED),
+                Op::Insertion | Op::Deletion if base == b'-' => Some(YELLOW),
+                _ => None,
+            };
+            match color {
+                Some(color) => write!(out, "{}{}{}", color, base as char, RESET)?,
+                None => write!(out, "{}", base as char)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Render `ops`, the base-level path between `query` (forward) and `target`
+/// (alignment orientation) of `aln`.
+pub fn render(
+    out: &mut dyn Write,
+    aln: &AlignmentData,
+    query: &[u8],
+    target: &[u8],
+    ops: &[Op],
+    options: &PrettyOptions,
+) -> io::Result<()> {
+    let mut columns = columns(query, target, ops);
+    if let Some((start, end)) = options.region {
+        let (start, end) = (start - aln.query_start, end - aln.query_start);
+        columns.retain(|column| column.query_offset >= start && column.query_offset < end);
+    }
+
+    let (mut matches, mut mismatches, mut gaps) = (0, 0, 0);
+    for column in &columns {
+        match column.op {
+            Op::Match => matches += 1,
+            Op::Mismatch => mismatches += 1,
+            Op::Insertion | Op::Deletion => gaps += 1,
+        }
+    }
+    let percent = |count: usize| if columns.is_empty() { 0.0 } else { 100.0 * count as f64 / columns.len() as f64 };
+    writeln!(
+        out,
+        "Columns: {}, matches: {} ({:.1}%), mismatches: {} ({:.1}%), gaps: {} ({:.1}%)",
+        columns.len(),
+        matches,
+        percent(matches),
+        mismatches,
+        percent(mismatches),
+        gaps,
+        percent(gaps)
+    )?;
+    writeln!(out)?;
+
+    // Forward-strand position of the base at `offset` along each sequence
+    let query_position = |offset: i64| aln.query_start + offset;
+    let target_position = |offset: i64| {
+        if aln.strand == '-' {
+            aln.target_end - 1 - offset
+        } else {
+            aln.target_start + offset
+        }
+    };
+    let digits = [aln.query_end, aln.target_end].iter().map(|value| value.to_string().len()).max().unwrap_or(1);
+
+    for row in columns.chunks(options.width.max(1)) {
+        let first = &row[0];
+        let last = &row[row.len() - 1];
+        // A row of gaps only shows where the next base would be
+        let query_last = last.query_offset + i64::from(last.op != Op::Deletion) - 1;
+        let target_last = last.target_offset + i64::from(last.op != Op::Insertion) - 1;
+        let query_span = (
+            options.position(query_position(first.query_offset)),
+            options.position(query_position(query_last.max(first.query_offset))),
+        );
+        let target_span = (
+            options.position(target_position(first.target_offset)),
+            options.position(target_position(target_last.max(first.target_offset))),
+        );
+
+        write!(out, "Query  {:>width$} ", query_span.0, width = digits)?;
+        options.write_row(out, row.iter().map(|column| (column.query, column.op)))?;
+        writeln!(out, " {}", query_span.1)?;
+
+        write!(out, "       {:>width$} ", "", width = digits)?;
+        for column in row {
+            let mark = match column.op {
+                Op::Match => '|',
+                Op::Mismatch => '*',
+                Op::Insertion | Op::Deletion => ' ',
+            };
+            write!(out, "{}", mark)?;
+        }
+        writeln!(out)?;
+
+        write!(out, "Target {:>width$} ", target_span.0, width = digits)?;
+        options.write_row(out, row.iter().map(|column| (column.target, column.op)))?;
+        writeln!(out, " {}", target_span.1)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
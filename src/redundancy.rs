@@ -0,0 +1,109 @@
+//! Collapsing near-duplicate alignments (`--collapse-redundant`).
+//!
+//! Two alignments are redundant when they join the same query and target
+//! sequences on the same strand and their query intervals, and separately
+//! their target intervals, overlap reciprocally by at least a fraction F:
+//! the shared bases make up at least F of each interval. Redundancy is
+//! chained (single linkage), so a cluster can hold alignments that are only
+//! linked through others. Each cluster is represented by its alignment with
+//! the most matches, the earliest in the file on ties.
+
+use crate::AlignmentData;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+struct Interval {
+    index: usize,
+    query_start: i64,
+    query_end: i64,
+    target_start: i64,
+    target_end: i64,
+    matches: i64,
+}
+
+/// Whether `[a_start, a_end)` and `[b_start, b_end)` overlap by at least
+/// `fraction` of each.
+fn reciprocal(a_start: i64, a_end: i64, b_start: i64, b_end: i64, fraction: f64) -> bool {
+    let overlap = a_end.min(b_end) - a_start.max(b_start);
+    let (a_len, b_len) = (a_end - a_start, b_end - b_start);
+    overlap > 0 && overlap as f64 >= fraction * a_len.max(b_len) as f64
+}
+
+/// Whether the query and the target intervals both overlap reciprocally.
+fn redundant(a: &Interval, b: &Interval, fraction: f64) -> bool {
+    reciprocal(a.query_start, a.query_end, b.query_start, b.query_end, fraction)
+        && reciprocal(a.target_start, a.target_end, b.target_start, b.target_end, fraction)
+}
+
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+pub struct Clusters {
+    fraction: f64,
+    /// Alignments by query sequence, target sequence and strand
+    groups: HashMap<(i64, i64, char), Vec<Interval>>,
+}
+
+impl Clusters {
+    pub fn new(fraction: f64) -> Self {
+        Clusters {
+            fraction,
+            groups: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, aln: &AlignmentData) {
+        self.groups.entry((aln.query_id, aln.target_id, aln.strand)).or_default().push(Interval {
+            index: aln.index,
+            query_start: aln.query_start,
+            query_end: aln.query_end,
+            target_start: aln.target_start,
+            target_end: aln.target_end,
+            matches: aln.matches(),
+        });
+    }
+
+    /// The representative of every cluster, by index, with the cluster size.
+    pub fn representatives(self) -> HashMap<usize, usize> {
+        let mut representatives = HashMap::new();
+        let fraction = self.fraction;
+        for (_, mut intervals) in self.groups {
+            intervals.sort_by_key(|interval| (interval.query_start, interval.query_end));
+            let mut parents: Vec<usize> = (0..intervals.len()).collect();
+            // Sweep by query start, comparing each interval with those whose query end is still ahead
+            let mut active: Vec<usize> = Vec::new();
+            for i in 0..intervals.len() {
+                let current = &intervals[i];
+                active.retain(|&j| intervals[j].query_end > current.query_start);
+                for &j in &active {
+                    if redundant(current, &intervals[j], fraction) {
+                        let (root_i, root_j) = (find(&mut parents, i), find(&mut parents, j));
+                        parents[root_i] = root_j;
+                    }
+                }
+                active.push(i);
+            }
+
+            // Root -> (best member, size)
+            let mut clusters: HashMap<usize, (usize, usize)> = HashMap::new();
+            for i in 0..intervals.len() {
+                let root = find(&mut parents, i);
+                let (best, size) = clusters.entry(root).or_insert((i, 0));
+                *size += 1;
+                let (candidate, current) = (&intervals[i], &intervals[*best]);
+                if (candidate.matches, Reverse(candidate.index)) > (current.matches, Reverse(current.index)) {
+                    *best = i;
+                }
+            }
+            for (best, size) in clusters.into_values() {
+                representatives.insert(intervals[best].index, size);
+            }
+        }
+        representatives
+    }
+}
@@ -0,0 +1,275 @@
+//! The region index: a sidecar (`FILE.1aln.ridx`) that finds the alignments
+//! overlapping a target region without reading the whole file.
+//!
+//! The binary index of a `.1aln` file locates records by object number only.
+//! The region index maps coordinates to object numbers: after a one-line
+//! text header, it holds fixed-size records of four little-endian i64s,
+//! `(target ID, target start, target end, object number)`, sorted by target
+//! ID and start, with scaffold coordinates. The header stores:
+//!
+//! * the size and modification time of the `.1aln` it was built from, to
+//!   notice a rebuilt file;
+//! * a digest of the target contig offsets in use, so an index built with
+//!   scaffold coordinates from a GDB is not used for a view that has contig
+//!   coordinates, or the other way round;
+//! * the longest target span. It bounds how far before a region an
+//!   overlapping alignment can start, so a query is a binary search and a
+//!   short scan.
+//!
+//! It is written by the `index` subcommand.
+
+use crate::fai::FaiMetadata;
+use crate::fasta::Sequences;
+use crate::resolve::ResolveOptions;
+use crate::{ExcludedSequences, LengthSource, ParseContext, ParseWarnings, for_each_alignment, get_file_metadata};
+use clap::Parser;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+const MAGIC: &str = "oneview-region-index";
+const VERSION: u32 = 2;
+const RECORD_BYTES: u64 = 32;
+
+#[derive(Parser, Debug)]
+#[command(name = "oneview-rs index")]
+#[command(about = "Build the region index that --target-region uses to seek to overlapping alignments", long_about = None)]
+pub struct IndexArgs {
+    /// Input .1aln file path; the index is written next to it as FILE.ridx
+    #[arg(value_name = "FILE")]
+    input: String,
+
+    /// Extra directory to look for referenced GDBs in, by file name (repeatable)
+    #[arg(long, value_name = "DIR")]
+    gdb_dir: Vec<String>,
+}
+
+pub fn sidecar_path(path: &str) -> String {
+    format!("{}.ridx", path)
+}
+
+type Record = [i64; 4];
+
+fn decode(bytes: &[u8; RECORD_BYTES as usize]) -> Record {
+    let mut record = [0i64; 4];
+    for (i, value) in record.iter_mut().enumerate() {
+        *value = i64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().expect("8-byte field"));
+    }
+    record
+}
+
+/// Size and modification time (nanoseconds since the epoch) of `path`.
+fn fingerprint(path: &str) -> io::Result<(u64, u128)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(std::time::UNIX_EPOCH).map_or(0, |age| age.as_nanos());
+    Ok((metadata.len(), modified))
+}
+
+/// Digest of the non-zero target contig offsets, 0 when there are none and
+/// coordinates are the contigs' own. FNV-1a, so it is the same in every build.
+pub fn offsets_digest(offsets: &HashMap<i64, (i64, i64)>) -> u64 {
+    let mut shifted: Vec<(i64, i64)> =
+        offsets.iter().filter(|(_, (offset, _))| *offset != 0).map(|(&id, &(offset, _))| (id, offset)).collect();
+    if shifted.is_empty() {
+        return 0;
+    }
+    shifted.sort_unstable();
+    shifted
+        .iter()
+        .flat_map(|(id, offset)| id.to_le_bytes().into_iter().chain(offset.to_le_bytes()))
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+pub fn run(args: IndexArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let resolve_options = ResolveOptions {
+        gdb_dirs: args.gdb_dir.clone(),
+        ..ResolveOptions::default()
+    };
+    let (metadata, trace_spacing, _) = get_file_metadata(&args.input, None, false, &resolve_options, FaiMetadata::default())?;
+    let sequences = Sequences::default();
+    let mut ctx = ParseContext {
+        metadata: &metadata,
+        length_source: LengthSource::Metadata,
+        read_traces: false,
+        trace_spacing,
+        sequences: &sequences,
+        exact_identity: false,
+        strict: false,
+        skip_missing_metadata: true,
+        // Views without metadata query by contig IDs and coordinates too
+        allow_anonymous: true,
+        trim_ends: None,
+        clip: None,
+        respace: None,
+        explain: false,
+        stream: None,
+        excluded: ExcludedSequences::default(),
+        representatives: None,
+        warnings: ParseWarnings::default(),
+    };
+    let mut records: Vec<Record> = Vec::new();
+    for_each_alignment(&args.input, &mut ctx, |aln| {
+        records.push([aln.target_id, aln.target_start, aln.target_end, aln.index as i64]);
+        Ok(())
+    })?;
+    ctx.warnings.report();
+    records.sort_unstable();
+
+    let max_span = records.iter().map(|record| record[2] - record[1]).max().unwrap_or(0);
+    let (source_bytes, source_modified) = fingerprint(&args.input)?;
+    let index_path = sidecar_path(&args.input);
+    let file = File::create(&index_path).map_err(|e| format!("Cannot create {}: {}", index_path, e))?;
+    let mut out = BufWriter::new(file);
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        MAGIC,
+        VERSION,
+        source_bytes,
+        source_modified,
+        offsets_digest(&metadata.target_contig_offsets),
+        records.len(),
+        max_span
+    )?;
+    for record in &records {
+        for value in record {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    out.flush()?;
+    eprintln!("Wrote region index {} ({} alignments)", index_path, records.len());
+    Ok(())
+}
+
+/// The source fingerprint, offsets digest, record count and longest span
+/// from a header line of this version.
+fn parse_header(header: &str) -> Option<((u64, u128), u64, u64, i64)> {
+    let fields: Vec<&str> = header.trim_end().split('\t').collect();
+    let [MAGIC, version, source_bytes, source_modified, digest, records, max_span] = fields.as_slice() else {
+        return None;
+    };
+    if version.parse() != Ok(VERSION) {
+        return None;
+    }
+    Some((
+        (source_bytes.parse().ok()?, source_modified.parse().ok()?),
+        digest.parse().ok()?,
+        records.parse().ok()?,
+        max_span.parse().ok()?,
+    ))
+}
+
+pub struct RegionIndex {
+    reader: BufReader<File>,
+    /// Byte offset of the first record
+    data_start: u64,
+    records: u64,
+    max_span: i64,
+}
+
+impl RegionIndex {
+    /// The index next to `path`, or `None` when there is none, it does not
+    /// belong to the file as it is now, or it was built with other target
+    /// contig offsets than `offsets` (the reason goes to stderr).
+    pub fn open(path: &str, offsets: &HashMap<i64, (i64, i64)>) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let index_path = sidecar_path(path);
+        let Ok(file) = File::open(&index_path) else {
+            return Ok(None);
+        };
+        let mut reader = BufReader::new(file);
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let Some((source_fingerprint, digest, records, max_span)) = parse_header(&header) else {
+            eprintln!("Warning: {} is not a region index this version can read; ignoring it", index_path);
+            return Ok(None);
+        };
+        if fingerprint(path)? != source_fingerprint {
+            eprintln!("Warning: {} was built for a different version of {}; ignoring it (rerun `oneview-rs index`)", index_path, path);
+            return Ok(None);
+        }
+        if offsets_digest(offsets) != digest {
+            eprintln!(
+                "Warning: {} was built with other target contig offsets than this view's metadata (scaffold against \
+                 contig coordinates, or another GDB); ignoring it (rerun `oneview-rs index` with the same GDBs)",
+                index_path
+            );
+            return Ok(None);
+        }
+        Ok(Some(RegionIndex {
+            reader,
+            data_start: header.len() as u64,
+            records,
+            max_span,
+        }))
+    }
+
+    fn record(&mut self, i: u64) -> io::Result<Record> {
+        self.reader.seek(SeekFrom::Start(self.data_start + i * RECORD_BYTES))?;
+        let mut bytes = [0u8; RECORD_BYTES as usize];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(decode(&bytes))
+    }
+
+    /// Object numbers of the alignments on target contig `target_id` that
+    /// overlap `[start, end)` in scaffold coordinates.
+    pub fn overlapping(&mut self, target_id: i64, start: i64, end: i64) -> io::Result<Vec<i64>> {
+        // First record that could overlap: none before it starts late enough to reach `start`
+        let first_start = start.saturating_sub(self.max_span);
+        let (mut low, mut high) = (0, self.records);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let record = self.record(middle)?;
+            if (record[0], record[1]) < (target_id, first_start) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        let mut objects = Vec::new();
+        if low < self.records {
+            self.reader.seek(SeekFrom::Start(self.data_start + low * RECORD_BYTES))?;
+        }
+        let mut bytes = [0u8; RECORD_BYTES as usize];
+        for _ in low..self.records {
+            self.reader.read_exact(&mut bytes)?;
+            let [id, record_start, record_end, object] = decode(&bytes);
+            if id != target_id || record_start >= end {
+                break;
+            }
+            if record_end > start {
+                objects.push(object);
+            }
+        }
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_offsets_are_contig_coordinates() {
+        assert_eq!(offsets_digest(&HashMap::new()), 0);
+        assert_eq!(offsets_digest(&HashMap::from([(0, (0, 1000)), (1, (0, 300))])), 0);
+    }
+
+    #[test]
+    fn offsets_digest_tells_layouts_apart() {
+        let scaffold = HashMap::from([(0, (0, 1000)), (1, (1100, 300))]);
+        let other = HashMap::from([(0, (0, 1000)), (1, (1200, 300))]);
+        assert_ne!(offsets_digest(&scaffold), 0);
+        assert_ne!(offsets_digest(&scaffold), offsets_digest(&other));
+        // Contig lengths are not part of the coordinates
+        assert_eq!(offsets_digest(&scaffold), offsets_digest(&HashMap::from([(1, (1100, 999))])));
+    }
+
+    #[test]
+    fn header_fields() {
+        let header = format!("{}\t{}\t120\t1700000000000000000\t42\t2\t198\n", MAGIC, VERSION);
+        assert_eq!(parse_header(&header), Some(((120, 1_700_000_000_000_000_000), 42, 2, 198)));
+        // Version 1 had no modification time or offsets digest
+        assert_eq!(parse_header(&format!("{}\t1\t120\t2\t198\n", MAGIC)), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Sequence renaming from two-column (old name, new name) TSV maps.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/// Read a two-column TSV of `old<TAB>new` names. Blank lines and lines
+/// starting with `#` are ignored.
+///
+/// A name mapped twice to different names is an error, unless
+/// `warn_duplicates` is set: then every repeated name is reported and its
+/// last entry wins.
+pub fn read_name_map(path: &str, warn_duplicates: bool) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read name map {}: {}", path, e))?;
+
+    let mut map = HashMap::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 2 || fields[0].is_empty() || fields[1].is_empty() {
+            return Err(format!(
+                "{}:{}: expected two tab-separated columns (old name, new name)",
+                path,
+                line_idx + 1
+            )
+            .into());
+        }
+        let previous = map.insert(fields[0].to_string(), fields[1].to_string());
+        if warn_duplicates && previous.is_some() {
+            eprintln!(
+                "Warning: {}:{}: {} is listed more than once; using {}",
+                path,
+                line_idx + 1,
+                fields[0],
+                fields[1]
+            );
+            continue;
+        }
+        if let Some(previous) = previous
+            && previous != fields[1]
+        {
+            return Err(format!(
+                "{}:{}: {} is mapped to both {} and {}",
+                path,
+                line_idx + 1,
+                fields[0],
+                previous,
+                fields[1]
+            )
+            .into());
+        }
+    }
+    Ok(map)
+}
+
+/// Rename every entry of a metadata name table through `map`.
+///
+/// Names absent from the map pass through unchanged unless `strict` is set.
+/// Two distinct sequences ending up with the same name is always an error.
+pub fn rename_sequences(
+    names: &mut HashMap<i64, String>,
+    map: &HashMap<String, String>,
+    strict: bool,
+    side: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let original: HashSet<&String> = names.values().collect();
+    let mut renamed_to: HashMap<&str, &str> = HashMap::new();
+    for old in original {
+        let new = match map.get(old) {
+            Some(new) => new.as_str(),
+            None if strict => {
+                return Err(format!("{} sequence {} has no entry in the rename map", side, old).into());
+            }
+            None => old.as_str(),
+        };
+        if let Some(other) = renamed_to.insert(new, old) {
+            return Err(format!(
+                "{} sequences {} and {} would both be renamed to {}",
+                side, other, old, new
+            )
+            .into());
+        }
+    }
+
+    for name in names.values_mut() {
+        if let Some(new) = map.get(name) {
+            *name = new.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Strip then add a name prefix on every entry of a metadata name table.
+///
+/// Names that don't carry `strip` are left alone before `add` is applied.
+pub fn apply_prefixes(names: &mut HashMap<i64, String>, strip: Option<&str>, add: Option<&str>) {
+    for name in names.values_mut() {
+        if let Some(prefix) = strip
+            && let Some(rest) = name.strip_prefix(prefix)
+        {
+            *name = rest.to_string();
+        }
+        if let Some(prefix) = add {
+            name.insert_str(0, prefix);
+        }
+    }
+}
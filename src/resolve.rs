@@ -0,0 +1,523 @@
+//! Locating the GDB files referenced by a .1aln on the local filesystem.
+//!
+//! References are recorded as the paths given to FastGA, usually the FASTA
+//! files, so the GDB has to be found by trying a fixed chain of strategies.
+//! Paths written on another machine (Windows separators, drive letters,
+//! directories that don't exist here) are normalized first, and the bare
+//! file name is tried next to the alignment and in any `--gdb-dir`. A
+//! `--paths-from` map, when given, is consulted before any of this.
+//!
+//! Candidates are built with `Path` operations on the file name, never by
+//! pasting strings onto the whole path, and extensions match in any case,
+//! so the same chain works with Windows paths on Windows.
+
+use clap::ValueEnum;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const GDB_EXTENSIONS: [&str; 2] = [".1gdb", ".gdb"];
+const FASTA_EXTENSIONS: [&str; 6] = [".fasta.gz", ".fa.gz", ".fna.gz", ".fasta", ".fa", ".fna"];
+
+/// Result of running the strategy chain for one reference.
+pub struct Resolution {
+    /// Strategy number (0-8) and path of the first usable candidate
+    pub found: Option<(usize, String)>,
+    /// Every candidate considered, in order
+    pub tried: Vec<Attempt>,
+}
+
+/// One candidate path and what became of it.
+pub struct Attempt {
+    pub strategy: usize,
+    pub path: String,
+    /// Where the path leads after following symlinks, when it exists
+    pub canonical: Option<PathBuf>,
+    /// Why the candidate was not used
+    pub rejected: Option<&'static str>,
+}
+
+impl fmt::Display for Attempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(canonical) = &self.canonical
+            && canonical.as_path() != Path::new(&self.path)
+        {
+            write!(f, " -> {}", canonical.display())?;
+        }
+        if let Some(reason) = self.rejected {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn strategy_description(strategy: usize) -> &'static str {
+    match strategy {
+        0 => "pinned by --paths-from",
+        1 => "path as recorded",
+        2 => "recorded path with GDB extension",
+        3 => "FASTA extension replaced by GDB extension",
+        4 => "FASTA extension replaced by GDB extension, relative to the alignment file",
+        5 => "recorded path with GDB extension, relative to the alignment file",
+        6 => "recorded path relative to the alignment file",
+        7 => "file name only, next to the alignment file",
+        8 => "file name only, in a --gdb-dir",
+        _ => "unknown strategy",
+    }
+}
+
+/// The file name of `path` without its FASTA extension, matched in any
+/// case, or `None` when it has none. Names that are not UTF-8 keep their
+/// bytes.
+fn fasta_stem(path: &Path) -> Option<OsString> {
+    let name = path.file_name()?;
+    let lower = name.as_encoded_bytes().to_ascii_lowercase();
+    let ext = FASTA_EXTENSIONS
+        .iter()
+        .find(|ext| lower.ends_with(ext.as_bytes()) && lower.len() > ext.len())?;
+    // One file_stem per dot of the extension: genome.fa.gz -> genome.fa -> genome
+    let mut stem = Path::new(name);
+    for _ in 0..ext.matches('.').count() {
+        stem = Path::new(stem.file_stem()?);
+    }
+    Some(stem.as_os_str().to_os_string())
+}
+
+fn has_gdb_ext(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|name| GDB_EXTENSIONS.iter().any(|ext| name.ends_with(ext)))
+}
+
+/// `path` with `suffix` added to its file name: `genome.fa` becomes `genome.fa.1gdb`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// User-supplied reference locations from `--paths-from`: a two-column TSV
+/// of embedded reference (full path or file name) and local GDB or FASTA.
+pub struct PathMap {
+    path: String,
+    entries: HashMap<String, String>,
+    used: RefCell<HashSet<String>>,
+}
+
+impl PathMap {
+    /// Read the map; every local path must exist. Blank lines and lines
+    /// starting with `#` are ignored.
+    pub fn read(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read path map {}: {}", path, e))?;
+        let mut entries = HashMap::new();
+        for (line_idx, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 2 || fields[0].is_empty() || fields[1].is_empty() {
+                return Err(format!(
+                    "{}:{}: expected two tab-separated columns (embedded reference, local path)",
+                    path,
+                    line_idx + 1
+                )
+                .into());
+            }
+            if !Path::new(fields[1]).exists() {
+                return Err(format!("{}:{}: {} does not exist", path, line_idx + 1, fields[1]).into());
+            }
+            entries.insert(fields[0].to_string(), fields[1].to_string());
+        }
+        Ok(PathMap {
+            path: path.to_string(),
+            entries,
+            used: RefCell::new(HashSet::new()),
+        })
+    }
+
+    /// The local path for `reference`: an exact entry first, then one for its file name.
+    fn lookup(&self, reference: &str) -> Option<&str> {
+        let file_name = Path::new(&normalize_reference(reference))
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        let (key, local) = self
+            .entries
+            .get_key_value(reference)
+            .or_else(|| file_name.and_then(|name| self.entries.get_key_value(&name)))?;
+        self.used.borrow_mut().insert(key.clone());
+        Some(local)
+    }
+
+    /// Warn about entries that no reference matched.
+    pub fn report_unused(&self) {
+        let used = self.used.borrow();
+        let mut unused: Vec<&String> = self.entries.keys().filter(|key| !used.contains(*key)).collect();
+        unused.sort();
+        for key in unused {
+            eprintln!("Warning: {} entry {} matched no reference in the file", self.path, key);
+        }
+    }
+}
+
+/// Which description of the target genome wins when the file has both.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum TargetSource {
+    /// The GDB of the second reference
+    #[default]
+    External,
+    /// The skeleton embedded in the .1aln
+    Embedded,
+}
+
+/// Where to look for referenced GDBs besides the recorded paths, and which
+/// target metadata to use when there is a choice.
+#[derive(Default)]
+pub struct ResolveOptions {
+    pub gdb_dirs: Vec<String>,
+    pub path_map: Option<PathMap>,
+    pub prefer: TargetSource,
+    /// Follow symlinks in the alignment and reference paths before searching (`--resolve-symlinks`)
+    pub resolve_symlinks: bool,
+}
+
+/// `path` with every symlink resolved. A path that doesn't exist keeps its
+/// file name under its resolved directory; when neither resolves, the
+/// path is returned unchanged with a warning.
+fn canonical_or_warn(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    if let (Ok(dir), Some(name)) = (fs::canonicalize(parent), path.file_name()) {
+        return dir.join(name);
+    }
+    eprintln!("Warning: --resolve-symlinks cannot canonicalize {}; using it as is", path.display());
+    path.to_path_buf()
+}
+
+/// Forward slashes, and no drive letter: `C:\data\genome.fa` becomes
+/// `/data/genome.fa`. On Windows the recorded path is already native, so
+/// it is kept as is.
+fn normalize_reference(reference: &str) -> String {
+    if cfg!(windows) {
+        return reference.to_string();
+    }
+    let path = reference.replace('\\', "/");
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => chars.as_str().to_string(),
+        _ => path,
+    }
+}
+
+/// GDB file names to look for when only the reference's file name is kept.
+fn gdb_file_names(reference: &Path) -> Vec<PathBuf> {
+    let Some(file_name) = reference.file_name() else {
+        return Vec::new();
+    };
+    if has_gdb_ext(reference) {
+        return vec![PathBuf::from(file_name)];
+    }
+    let stem = fasta_stem(reference).map(PathBuf::from).unwrap_or_else(|| PathBuf::from(file_name));
+    GDB_EXTENSIONS.iter().map(|ext| with_suffix(&stem, ext)).collect()
+}
+
+/// Candidate paths in the order they are tried.
+fn candidates(reference: &str, aln_dir: &Path, gdb_dirs: &[String]) -> Vec<(usize, PathBuf)> {
+    let reference = Path::new(reference);
+    let mut candidates = Vec::new();
+
+    // Strategy 1: Try as absolute path (as-is)
+    candidates.push((1, reference.to_path_buf()));
+
+    // Strategy 2: Try adding .1gdb or .gdb extension to the original path
+    for ext in GDB_EXTENSIONS {
+        candidates.push((2, with_suffix(reference, ext)));
+    }
+
+    if let Some(stem) = fasta_stem(reference) {
+        let base = reference.with_file_name(stem);
+        // Strategy 3: Strip fasta extension and try with GDB extensions (absolute path)
+        for ext in GDB_EXTENSIONS {
+            candidates.push((3, with_suffix(&base, ext)));
+        }
+        // Strategy 4: Try relative path with fasta extension stripped and GDB extension added
+        // (DO THIS BEFORE trying as-is, to avoid finding the fasta file itself)
+        for ext in GDB_EXTENSIONS {
+            candidates.push((4, aln_dir.join(with_suffix(&base, ext))));
+        }
+    }
+
+    // Strategy 5: Try relative path with .1gdb or .gdb extension
+    for ext in GDB_EXTENSIONS {
+        candidates.push((5, aln_dir.join(with_suffix(reference, ext))));
+    }
+
+    // Strategy 6: Try relative to alignment file directory (as-is)
+    // (Only as last resort, to avoid finding non-GDB files)
+    candidates.push((6, aln_dir.join(reference)));
+
+    // Strategies 7 and 8: the recorded directories may only exist on the machine that ran FastGA
+    let file_names = gdb_file_names(reference);
+    for name in &file_names {
+        candidates.push((7, aln_dir.join(name)));
+    }
+    for dir in gdb_dirs {
+        for name in &file_names {
+            candidates.push((8, Path::new(dir).join(name)));
+        }
+    }
+
+    candidates
+}
+
+/// FASTA is recognized by extension or by a leading '>'; GDBs are ONE files.
+fn looks_like_fasta(path: &Path) -> bool {
+    if fasta_stem(path).is_some() {
+        return true;
+    }
+    let mut first = [0u8; 1];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut first))
+        .is_ok_and(|_| first[0] == b'>')
+}
+
+/// Canonicalize a candidate and decide whether it can be the GDB.
+fn check_candidate(strategy: usize, candidate: &Path, aln_canonical: Option<&Path>) -> Attempt {
+    let path = candidate.to_string_lossy().to_string();
+    let canonical = match std::fs::canonicalize(candidate) {
+        Ok(canonical) => canonical,
+        Err(_) => {
+            // A symlink that points nowhere is as good as missing
+            let rejected = if candidate.symlink_metadata().is_ok() {
+                "dangling symlink"
+            } else {
+                "does not exist"
+            };
+            return Attempt {
+                strategy,
+                path,
+                canonical: None,
+                rejected: Some(rejected),
+            };
+        }
+    };
+    let rejected = if aln_canonical == Some(canonical.as_path()) {
+        Some("the alignment file itself")
+    } else if canonical.is_dir() {
+        Some("a directory")
+    } else if looks_like_fasta(&canonical) {
+        Some("a FASTA file, not a GDB")
+    } else {
+        None
+    };
+    Attempt {
+        strategy,
+        path,
+        canonical: Some(canonical),
+        rejected,
+    }
+}
+
+/// Find the GDB for `reference`, resolving relative paths against the
+/// directory of the alignment file `aln_path`, then by file name in the
+/// `--gdb-dir` directories. A `--paths-from` entry takes precedence: a GDB
+/// is used as is, and a FASTA stands in for the recorded path.
+pub fn resolve_gdb_path(reference: &str, aln_path: &str, options: &ResolveOptions) -> Resolution {
+    // Relative strategies then start from the directory the alignment really lives in
+    let aln_path = if options.resolve_symlinks {
+        canonical_or_warn(Path::new(aln_path))
+    } else {
+        PathBuf::from(aln_path)
+    };
+    // A bare file name has an empty parent
+    let aln_dir = aln_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    let aln_canonical = std::fs::canonicalize(&aln_path).ok();
+
+    let mut tried = Vec::new();
+    let mut reference = normalize_reference(reference);
+    if let Some(local) = options.path_map.as_ref().and_then(|map| map.lookup(&reference)) {
+        eprintln!("Using --paths-from entry: {} -> {}", reference, local);
+        let attempt = check_candidate(0, Path::new(local), aln_canonical.as_deref());
+        if attempt.rejected.is_none() {
+            let path = attempt.path.clone();
+            tried.push(attempt);
+            return Resolution {
+                found: Some((0, path)),
+                tried,
+            };
+        }
+        tried.push(attempt);
+        reference = local.to_string();
+    }
+    // Relative references are tried against the resolved alignment directory instead
+    if options.resolve_symlinks && Path::new(&reference).is_absolute() {
+        reference = canonical_or_warn(Path::new(&reference)).to_string_lossy().to_string();
+    }
+    for (strategy, candidate) in candidates(&reference, aln_dir, &options.gdb_dirs) {
+        let attempt = check_candidate(strategy, &candidate, aln_canonical.as_deref());
+        let usable = attempt.rejected.is_none();
+        let path = attempt.path.clone();
+        tried.push(attempt);
+        if usable {
+            return Resolution {
+                found: Some((strategy, path)),
+                tried,
+            };
+        }
+    }
+    Resolution { found: None, tried }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp directory.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("oneview-resolve-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn windows_references_lose_drive_and_backslashes() {
+        assert_eq!(normalize_reference(r"C:\data\genome.fa"), "/data/genome.fa");
+        assert_eq!(normalize_reference(r"d:\genome.fa"), "/genome.fa");
+        assert_eq!(normalize_reference(r"data\genome.fa"), "data/genome.fa");
+    }
+
+    #[test]
+    fn unix_references_are_kept() {
+        assert_eq!(normalize_reference("/data/genome.fa"), "/data/genome.fa");
+        assert_eq!(normalize_reference("genomes/genome.fa"), "genomes/genome.fa");
+        assert_eq!(normalize_reference("genome.fa"), "genome.fa");
+    }
+
+    #[test]
+    fn candidates_for_an_absolute_fasta_reference() {
+        let paths: Vec<(usize, PathBuf)> = candidates("/data/genome.fa.gz", Path::new("alns"), &["gdbs".to_string()]);
+        let expected = [
+            (1, "/data/genome.fa.gz"),
+            (2, "/data/genome.fa.gz.1gdb"),
+            (2, "/data/genome.fa.gz.gdb"),
+            (3, "/data/genome.1gdb"),
+            (3, "/data/genome.gdb"),
+            (4, "/data/genome.1gdb"),
+            (4, "/data/genome.gdb"),
+            (5, "/data/genome.fa.gz.1gdb"),
+            (5, "/data/genome.fa.gz.gdb"),
+            (6, "/data/genome.fa.gz"),
+            (7, "alns/genome.1gdb"),
+            (7, "alns/genome.gdb"),
+            (8, "gdbs/genome.1gdb"),
+            (8, "gdbs/genome.gdb"),
+        ];
+        let expected: Vec<(usize, PathBuf)> = expected.iter().map(|&(s, p)| (s, PathBuf::from(p))).collect();
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn relative_references_join_the_alignment_directory() {
+        let paths = candidates("genomes/genome.fa", Path::new("alns"), &[]);
+        assert!(paths.contains(&(4, PathBuf::from("alns/genomes/genome.1gdb"))));
+        assert!(paths.contains(&(6, PathBuf::from("alns/genomes/genome.fa"))));
+        assert!(paths.contains(&(7, PathBuf::from("alns/genome.1gdb"))));
+    }
+
+    #[test]
+    fn gdb_references_keep_their_name() {
+        assert_eq!(gdb_file_names(Path::new("/x/genome.1GDB")), vec![PathBuf::from("genome.1GDB")]);
+        assert_eq!(
+            gdb_file_names(Path::new("/x/genome")),
+            vec![PathBuf::from("genome.1gdb"), PathBuf::from("genome.gdb")]
+        );
+    }
+
+    #[test]
+    fn extensions_match_in_any_case() {
+        assert_eq!(fasta_stem(Path::new("/x/genome.FA.GZ")), Some(OsString::from("genome")));
+        assert_eq!(fasta_stem(Path::new("genome.fasta")), Some(OsString::from("genome")));
+        assert_eq!(fasta_stem(Path::new("genome.1gdb")), None);
+        assert_eq!(fasta_stem(Path::new(".fa")), None);
+        assert!(has_gdb_ext(Path::new("/x/genome.GDB")));
+        assert!(!has_gdb_ext(Path::new("/x/genome.fa")));
+        assert_eq!(with_suffix(Path::new("/x/genome.fa"), ".1gdb"), PathBuf::from("/x/genome.fa.1gdb"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_keep_their_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let reference = Path::new(OsStr::from_bytes(b"/data/gen\xffome.Fa.gz"));
+        let stem = fasta_stem(reference).unwrap();
+        assert_eq!(stem.as_bytes(), b"gen\xffome");
+        assert_eq!(with_suffix(Path::new(&stem), ".1gdb").as_os_str().as_bytes(), b"gen\xffome.1gdb");
+        assert_eq!(
+            gdb_file_names(reference),
+            vec![
+                PathBuf::from(OsStr::from_bytes(b"gen\xffome.1gdb")),
+                PathBuf::from(OsStr::from_bytes(b"gen\xffome.gdb")),
+            ]
+        );
+        assert!(has_gdb_ext(Path::new(OsStr::from_bytes(b"gen\xffome.1gdb"))));
+        assert!(looks_like_fasta(reference));
+    }
+
+    #[test]
+    fn resolves_a_relative_reference_next_to_the_alignment() {
+        let dir = scratch_dir("relative");
+        fs::create_dir_all(dir.join("genomes")).unwrap();
+        fs::write(dir.join("genomes/genome.1gdb"), "1 3 gdb 1 0\n").unwrap();
+        let aln = dir.join("x.1aln");
+        fs::write(&aln, "").unwrap();
+
+        let resolution = resolve_gdb_path("genomes/genome.fa", aln.to_str().unwrap(), &ResolveOptions::default());
+        let (strategy, path) = resolution.found.expect("resolved");
+        assert_eq!(strategy, 4);
+        assert_eq!(PathBuf::from(path), dir.join("genomes/genome.1gdb"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn resolves_a_windows_reference_by_file_name() {
+        let dir = scratch_dir("windows");
+        fs::write(dir.join("genome.1gdb"), "1 3 gdb 1 0\n").unwrap();
+        let aln = dir.join("x.1aln");
+        fs::write(&aln, "").unwrap();
+
+        let resolution = resolve_gdb_path(r"C:\Users\me\genome.fa", aln.to_str().unwrap(), &ResolveOptions::default());
+        let (strategy, path) = resolution.found.expect("resolved");
+        assert_eq!(strategy, 7);
+        assert_eq!(PathBuf::from(path), dir.join("genome.1gdb"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fasta_files_are_not_taken_for_gdbs() {
+        let dir = scratch_dir("fasta");
+        fs::write(dir.join("genome"), ">chr1\nACGT\n").unwrap();
+        let aln = dir.join("x.1aln");
+        fs::write(&aln, "").unwrap();
+
+        let reference = dir.join("genome");
+        let resolution = resolve_gdb_path(reference.to_str().unwrap(), aln.to_str().unwrap(), &ResolveOptions::default());
+        assert!(resolution.found.is_none());
+        assert_eq!(resolution.tried[0].rejected, Some("a FASTA file, not a GDB"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! Reading the line-type schema and provenance declared in a ONE file's header.
+//!
+//! Both ASCII and binary ONE files start with a plain-text header, and the
+//! schema is carried there on `~` lines such as
+//! `~ O A 6 3 INT 3 INT 3 INT 3 INT 3 INT 3 INT`: a kind letter, the line
+//! type, the number of fields, then each field type as a length-prefixed
+//! string. Anything after the fields is the writer's description. The
+//! first line, `1 3 aln 1 0`, gives the file type and the format's major
+//! and minor version, and each program that wrote the file adds a `!` line
+//! of four length-prefixed strings: program, version, command and date.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+
+/// Line types that can appear in a ONE header.
+const HEADER_LINE_TYPES: &[u8] = b"12#@+%!<>.~$";
+
+#[derive(Debug)]
+pub struct SchemaLine {
+    /// P primary file type, S secondary, O object, G group, D other data
+    pub kind: char,
+    pub line_type: String,
+    pub fields: Vec<String>,
+    pub description: String,
+}
+
+fn kind_label(kind: char) -> &'static str {
+    match kind {
+        'P' => "file type",
+        'S' => "subtype",
+        'O' => "object",
+        'G' => "group",
+        'D' => "data",
+        _ => "other",
+    }
+}
+
+fn parse_schema_line(text: &str) -> Option<SchemaLine> {
+    let mut tokens = text.split_whitespace();
+    let kind = tokens.next()?.chars().next()?;
+    let line_type = tokens.next()?.to_string();
+    if matches!(kind, 'P' | 'S') {
+        return Some(SchemaLine {
+            kind,
+            line_type,
+            fields: Vec::new(),
+            description: tokens.collect::<Vec<_>>().join(" "),
+        });
+    }
+
+    let field_count: usize = tokens.next()?.parse().ok()?;
+    let mut fields = Vec::with_capacity(field_count);
+    for _ in 0..field_count {
+        // Each type is "LEN NAME"; the length is redundant in the ASCII form
+        tokens.next()?;
+        fields.push(tokens.next()?.to_string());
+    }
+    Some(SchemaLine {
+        kind,
+        line_type,
+        fields,
+        description: tokens.collect::<Vec<_>>().join(" "),
+    })
+}
+
+/// Call `visit` with the type and the rest of every header line of `path`.
+fn for_each_header_line(path: &str, mut visit: impl FnMut(u8, &str)) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let Some(&line_type) = line.first() else { break };
+        if !HEADER_LINE_TYPES.contains(&line_type) {
+            break;
+        }
+        visit(line_type, String::from_utf8_lossy(&line[1..]).trim_end_matches(['\n', '\r']));
+        // Binary data follows the '$' line
+        if line_type == b'$' {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The `~` lines of the header of `path`, in file order.
+pub fn read_schema(path: &str) -> io::Result<Vec<SchemaLine>> {
+    let mut schema = Vec::new();
+    for_each_header_line(path, |line_type, text| {
+        if line_type == b'~'
+            && let Some(schema_line) = parse_schema_line(text)
+        {
+            schema.push(schema_line);
+        }
+    })?;
+    Ok(schema)
+}
+
+/// One `!` header line.
+#[derive(Debug)]
+pub struct Provenance {
+    pub program: String,
+    pub version: String,
+    pub command: String,
+    pub date: String,
+}
+
+/// The format details of a ONE file's header.
+#[derive(Debug, Default)]
+pub struct HeaderInfo {
+    pub file_type: Option<String>,
+    pub version: Option<(i64, i64)>,
+    /// Oldest first
+    pub provenance: Vec<Provenance>,
+}
+
+/// The first `count` length-prefixed strings of `text`, which may contain
+/// spaces, and the text after them.
+fn prefixed_strings(mut text: &str, count: usize) -> Option<(Vec<String>, &str)> {
+    let mut strings = Vec::with_capacity(count);
+    for _ in 0..count {
+        text = text.trim_start();
+        let (length, rest) = text.split_once(' ')?;
+        let length: usize = length.parse().ok()?;
+        strings.push(rest.get(..length)?.to_string());
+        text = &rest[length..];
+    }
+    Some((strings, text))
+}
+
+pub fn read_header(path: &str) -> io::Result<HeaderInfo> {
+    let mut header = HeaderInfo::default();
+    for_each_header_line(path, |line_type, text| match line_type {
+        b'1' => {
+            if let Some((mut strings, rest)) = prefixed_strings(text, 1) {
+                let mut numbers = rest.split_whitespace().map(|token| token.parse::<i64>().ok());
+                if let (Some(Some(major)), Some(Some(minor))) = (numbers.next(), numbers.next()) {
+                    header.version = Some((major, minor));
+                }
+                header.file_type = strings.pop();
+            }
+        }
+        b'!' => {
+            if let Some((strings, _)) = prefixed_strings(text, 4)
+                && let Ok([program, version, command, date]) = <[String; 4]>::try_from(strings)
+            {
+                header.provenance.push(Provenance {
+                    program,
+                    version,
+                    command,
+                    date,
+                });
+            }
+        }
+        _ => {}
+    })?;
+    Ok(header)
+}
+
+pub fn write_schema<W: Write>(out: &mut W, schema: &[SchemaLine]) -> io::Result<()> {
+    writeln!(out, "=== SCHEMA ===\n")?;
+    if schema.is_empty() {
+        writeln!(out, "No schema lines in the file header")?;
+        return Ok(());
+    }
+    for line in schema {
+        let fields = if line.fields.is_empty() {
+            "-".to_string()
+        } else {
+            line.fields.join(" ")
+        };
+        write!(out, "{}\t{}\t{}", line.line_type, kind_label(line.kind), fields)?;
+        if !line.description.is_empty() {
+            write!(out, "\t{}", line.description)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Versions of this build and of its ONE library, then the format details
+/// of `path`.
+pub fn write_version_info<W: Write>(out: &mut W, path: &str, header: &HeaderInfo) -> io::Result<()> {
+    writeln!(out, "=== VERSION ===\n")?;
+    writeln!(out, "oneview-rs: {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "onecode: {}", env!("ONEVIEW_ONECODE_VERSION"))?;
+    writeln!(out, "\nFile: {}", path)?;
+    writeln!(out, "File type: {}", header.file_type.as_deref().unwrap_or("unknown"))?;
+    match header.version {
+        Some((major, minor)) => writeln!(out, "ONE format version: {}.{}", major, minor)?,
+        None => writeln!(out, "ONE format version: unknown")?,
+    }
+    if header.provenance.is_empty() {
+        writeln!(out, "Provenance: none recorded")?;
+        return Ok(());
+    }
+    writeln!(out, "Provenance ({} steps, oldest first):", header.provenance.len())?;
+    for step in &header.provenance {
+        writeln!(out, "  {} {}  {}", step.program, step.version, step.date)?;
+        writeln!(out, "    {}", step.command)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! Alignment scores from an affine gap model.
+//!
+//! Without a CIGAR the edit composition has to be estimated from the spans
+//! and the difference count:
+//!
+//! * gap bases = |query_span - target_span|, the fewest indel bases that can
+//!   explain the length difference, opened as a single gap when non-zero;
+//! * mismatches = differences - gap bases (at least 0);
+//! * matches = min(query_span, target_span) - mismatches (at least 0).
+//!
+//! The score is then
+//! `match * matches - mismatch * mismatches - (gap_open * gap_opens + gap_extend * gap_bases)`,
+//! the same affine form as minimap2's AS, whose defaults (2, 4, 4, 2) are
+//! used here. Every difference beyond the length difference counts as a
+//! mismatch, so the estimate is optimistic about indels. Records realigned
+//! by `--exact-identity` are scored from their exact counts instead.
+
+use crate::AlignmentData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditEstimate {
+    pub matches: i64,
+    pub mismatches: i64,
+    pub gap_opens: i64,
+    pub gap_bases: i64,
+}
+
+pub fn estimate_edits(aln: &AlignmentData) -> EditEstimate {
+    let query_span = aln.query_span();
+    let target_span = aln.target_span();
+    let gap_bases = (query_span - target_span).abs();
+    let mismatches = (aln.differences - gap_bases).max(0);
+    EditEstimate {
+        matches: (query_span.min(target_span) - mismatches).max(0),
+        mismatches,
+        gap_opens: i64::from(gap_bases > 0),
+        gap_bases,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoringModel {
+    pub match_score: i64,
+    pub mismatch: i64,
+    pub gap_open: i64,
+    pub gap_extend: i64,
+}
+
+impl ScoringModel {
+    pub fn score(&self, edits: &EditEstimate) -> i64 {
+        self.match_score * edits.matches
+            - self.mismatch * edits.mismatches
+            - (self.gap_open * edits.gap_opens + self.gap_extend * edits.gap_bases)
+    }
+
+    pub fn score_alignment(&self, aln: &AlignmentData) -> i64 {
+        let edits = match &aln.exact {
+            Some(exact) => EditEstimate {
+                matches: exact.matches,
+                mismatches: exact.mismatches,
+                gap_opens: exact.gap_opens,
+                gap_bases: exact.insertions + exact.deletions,
+            },
+            None => estimate_edits(aln),
+        };
+        self.score(&edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAP2: ScoringModel = ScoringModel {
+        match_score: 2,
+        mismatch: 4,
+        gap_open: 4,
+        gap_extend: 2,
+    };
+
+    fn alignment(query_span: i64, target_span: i64, differences: i64) -> AlignmentData {
+        AlignmentData {
+            query_start: 100,
+            query_end: 100 + query_span,
+            target_start: 5000,
+            target_end: 5000 + target_span,
+            differences,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn equal_spans_are_all_mismatches() {
+        let edits = estimate_edits(&alignment(100, 100, 3));
+        assert_eq!(
+            edits,
+            EditEstimate {
+                matches: 97,
+                mismatches: 3,
+                gap_opens: 0,
+                gap_bases: 0,
+            }
+        );
+        // 2*97 - 4*3
+        assert_eq!(MINIMAP2.score(&edits), 182);
+    }
+
+    #[test]
+    fn length_difference_opens_one_gap() {
+        let edits = estimate_edits(&alignment(1000, 990, 25));
+        assert_eq!(
+            edits,
+            EditEstimate {
+                matches: 975,
+                mismatches: 15,
+                gap_opens: 1,
+                gap_bases: 10,
+            }
+        );
+        // 2*975 - 4*15 - (4 + 2*10)
+        assert_eq!(MINIMAP2.score(&edits), 1866);
+    }
+
+    #[test]
+    fn differences_below_the_length_difference() {
+        let edits = estimate_edits(&alignment(80, 100, 5));
+        assert_eq!(
+            edits,
+            EditEstimate {
+                matches: 80,
+                mismatches: 0,
+                gap_opens: 1,
+                gap_bases: 20,
+            }
+        );
+        // 2*80 - (4 + 2*20)
+        assert_eq!(MINIMAP2.score_alignment(&alignment(80, 100, 5)), 116);
+    }
+
+    #[test]
+    fn counts_never_go_negative() {
+        let edits = estimate_edits(&alignment(10, 10, 20));
+        assert_eq!((edits.matches, edits.mismatches), (0, 20));
+        assert_eq!(MINIMAP2.score(&edits), -80);
+    }
+
+    #[test]
+    fn exact_counts_replace_the_estimate() {
+        let mut aln = alignment(100, 100, 3);
+        aln.exact = Some(crate::align::EditCounts {
+            matches: 94,
+            mismatches: 1,
+            insertions: 2,
+            deletions: 2,
+            gap_opens: 2,
+        });
+        // 2*94 - 4*1 - (4*2 + 2*4), not the estimate's 182
+        assert_eq!(MINIMAP2.score_alignment(&aln), 168);
+    }
+}
@@ -0,0 +1,244 @@
+//! Cutting alignments at highly divergent or gapped tracepoint segments.
+//!
+//! Divergence is measured per segment, by [`trace::Segment::divergence`];
+//! the gap a segment implies is the difference of its query and target
+//! spans, [`trace::Segment::implied_gap`].
+//!
+//! * `--split-at-divergence` and `--max-gap` drop runs of at least
+//!   `min_bad_segments` segments beyond either threshold, and the segments
+//!   between them become separate records. Shorter runs stay in the piece
+//!   around them.
+//! * `--trim-ends` drops bad segments from either end only, leaving one
+//!   record.
+//!
+//! `--clip` cuts at the `--target-region` boundary instead, inside the
+//! segment the boundary falls in.
+
+use crate::trace;
+use crate::{AlignmentData, DifferencesSource};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentSplit {
+    pub max_divergence: Option<f64>,
+    pub max_gap: Option<i64>,
+    pub min_bad_segments: usize,
+}
+
+impl SegmentSplit {
+    /// The pieces `aln` splits into, or `None` when it is kept whole: no
+    /// bad run, or no usable trace to find one in. An alignment that is bad
+    /// throughout yields no pieces.
+    pub fn apply(&self, aln: &AlignmentData) -> Option<Vec<AlignmentData>> {
+        let segments = trace::segments(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        )?;
+        let bad: Vec<bool> = segments.iter().map(|segment| self.is_bad(segment)).collect();
+
+        // Half-open ranges of segment indices to keep
+        let mut kept = Vec::new();
+        let mut piece_start = 0;
+        let mut i = 0;
+        while i < bad.len() {
+            if !bad[i] {
+                i += 1;
+                continue;
+            }
+            let run_start = i;
+            while i < bad.len() && bad[i] {
+                i += 1;
+            }
+            if i - run_start >= self.min_bad_segments {
+                if run_start > piece_start {
+                    kept.push((piece_start, run_start));
+                }
+                piece_start = i;
+            }
+        }
+        if piece_start == 0 {
+            return None;
+        }
+        if piece_start < segments.len() {
+            kept.push((piece_start, segments.len()));
+        }
+
+        let pieces = kept
+            .into_iter()
+            .enumerate()
+            .map(|(piece, (first, last))| sub_alignment(aln, &segments[first..last], Some(piece)))
+            .collect();
+        Some(pieces)
+    }
+
+    fn is_bad(&self, segment: &trace::Segment) -> bool {
+        self.max_divergence.is_some_and(|max| segment.divergence() > max)
+            || self.max_gap.is_some_and(|max| segment.implied_gap() > max)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrimEnds {
+    pub max_divergence: Option<f64>,
+    pub max_diffs: Option<i64>,
+}
+
+pub enum Trim {
+    /// No end segment was bad, or there is no usable trace
+    Unchanged,
+    Shortened(Box<AlignmentData>),
+    /// Every segment was bad
+    Empty,
+}
+
+impl TrimEnds {
+    pub fn apply(&self, aln: &AlignmentData) -> Trim {
+        let Some(segments) = trace::segments(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        ) else {
+            return Trim::Unchanged;
+        };
+        let Some(first) = segments.iter().position(|segment| !self.is_bad(segment)) else {
+            return Trim::Empty;
+        };
+        let last = segments.iter().rposition(|segment| !self.is_bad(segment)).expect("a good segment");
+        if first == 0 && last == segments.len() - 1 {
+            return Trim::Unchanged;
+        }
+        Trim::Shortened(Box::new(sub_alignment(aln, &segments[first..=last], None)))
+    }
+
+    fn is_bad(&self, segment: &trace::Segment) -> bool {
+        self.max_divergence.is_some_and(|max| segment.divergence() > max)
+            || self.max_diffs.is_some_and(|max| segment.diffs > max)
+    }
+}
+
+/// `--clip`: the part of each alignment inside a `--target-region`. Target
+/// coordinates are cut exactly at the region boundary; in the segment the
+/// boundary falls in, the query cut, the target advance and the diffs are
+/// taken in proportion to the target bases kept.
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub target_name: String,
+    pub start: i64,
+    pub end: i64,
+}
+
+pub enum Clipping {
+    /// Inside the region, or not overlapping it at all
+    Unchanged,
+    Clipped(Box<AlignmentData>),
+    /// Crosses the region boundary but has no usable trace to cut along
+    Untraced,
+}
+
+impl Clip {
+    pub fn apply(&self, aln: &AlignmentData) -> Clipping {
+        if aln.target_name != self.target_name {
+            return Clipping::Unchanged;
+        }
+        // The region as target offsets in alignment orientation, like the segments'
+        let (lo, hi) = if aln.strand == '-' {
+            (aln.target_end - self.end, aln.target_end - self.start)
+        } else {
+            (self.start - aln.target_start, self.end - aln.target_start)
+        };
+        let (lo, hi) = (lo.max(0), hi.min(aln.target_span()));
+        if lo >= hi || (lo == 0 && hi == aln.target_span()) {
+            return Clipping::Unchanged;
+        }
+        let Some(segments) = trace::segments(
+            aln.query_start - aln.query_offset,
+            aln.query_end - aln.query_offset,
+            aln.trace_spacing,
+            &aln.tracepoints,
+            &aln.trace_diffs,
+        ) else {
+            return Clipping::Untraced;
+        };
+        let kept: Vec<trace::Segment> = segments.iter().filter_map(|segment| cut(segment, lo, hi)).collect();
+        if kept.is_empty() {
+            return Clipping::Untraced;
+        }
+        Clipping::Clipped(Box::new(sub_alignment(aln, &kept, None)))
+    }
+}
+
+/// The part of `segment` over target offsets `lo..hi`, or `None` when it
+/// has none. Segments advancing no target bases are kept when they touch
+/// the interval.
+fn cut(segment: &trace::Segment, lo: i64, hi: i64) -> Option<trace::Segment> {
+    let advance = segment.target_end - segment.target_start;
+    if advance == 0 {
+        return (lo <= segment.target_start && segment.target_start <= hi).then_some(*segment);
+    }
+    let target_start = segment.target_start.max(lo);
+    let target_end = segment.target_end.min(hi);
+    if target_start >= target_end {
+        return None;
+    }
+    if target_start == segment.target_start && target_end == segment.target_end {
+        return Some(*segment);
+    }
+    let query_span = segment.query_end - segment.query_start;
+    let query_at = |target: i64| segment.query_start + ((target - segment.target_start) * query_span + advance / 2) / advance;
+    // A cut segment keeps at least one query base, so the trace keeps its segment count
+    let query_start = query_at(target_start).min(segment.query_end - 1);
+    let query_end = query_at(target_end).max(query_start + 1);
+    Some(trace::Segment {
+        query_start,
+        query_end,
+        target_start,
+        target_end,
+        diffs: (segment.diffs * (target_end - target_start) + advance / 2) / advance,
+    })
+}
+
+/// The record covering `segments`, a consecutive run of `aln`'s segments
+/// of which only the first and last may have been cut short.
+fn sub_alignment(aln: &AlignmentData, segments: &[trace::Segment], piece: Option<usize>) -> AlignmentData {
+    let head = segments.first().expect("sub-alignment without segments");
+    let tail = segments.last().expect("sub-alignment without segments");
+    // Target offsets run in alignment orientation, so '-' pieces count back from the end
+    let (target_start, target_end) = if aln.strand == '-' {
+        (aln.target_end - tail.target_end, aln.target_end - head.target_start)
+    } else {
+        (aln.target_start + head.target_start, aln.target_start + tail.target_end)
+    };
+    AlignmentData {
+        index: aln.index,
+        query_id: aln.query_id,
+        target_id: aln.target_id,
+        query_name: aln.query_name.clone(),
+        query_offset: aln.query_offset,
+        target_offset: aln.target_offset,
+        cluster_size: aln.cluster_size,
+        query_length: aln.query_length,
+        query_start: aln.query_start + head.query_start,
+        query_end: aln.query_start + tail.query_end,
+        target_name: aln.target_name.clone(),
+        target_length: aln.target_length,
+        target_start,
+        target_end,
+        strand: aln.strand,
+        trace_spacing: aln.trace_spacing,
+        differences: segments.iter().map(|segment| segment.diffs).sum(),
+        tracepoints: segments.iter().map(|segment| segment.target_end - segment.target_start).collect(),
+        trace_diffs: segments.iter().map(|segment| segment.diffs).collect(),
+        mapq: aln.mapq,
+        // Realigned counts covered the whole record
+        exact: None,
+        differences_source: DifferencesSource::TraceDiffs,
+        out_of_bounds: aln.out_of_bounds,
+        anonymous: aln.anonymous,
+        piece,
+        raw: aln.raw.clone(),
+    }
+}
@@ -0,0 +1,762 @@
+//! Summary statistics over the alignments of a file.
+//!
+//! `--stats --json` writes the same totals, with per-sequence and per-pair
+//! breakdowns and an identity histogram, as one JSON object whose field
+//! names are stable across releases (a change bumps `schema_version`):
+//!
+//! * `schema_version` (1), `tool` (`name`, `version`), `input` (the path);
+//! * `summary`: `alignments`, `query_aligned_bp`, `target_aligned_bp`,
+//!   `query_span_n50`, `query_span_l50`, `mean_identity`,
+//!   `length_weighted_mean_identity`, and `approximate`, true when the
+//!   N50 and L50 are `--stats-approx` estimates;
+//! * `queries` and `targets`: one object per sequence with `name`,
+//!   `alignments`, `query_bp`, `target_bp` and `mean_identity`, by name;
+//! * `pairs`: the same per query/target pair, with `query` and `target`
+//!   instead of `name`;
+//! * `identity_histogram`: `bin_width` and `bins`, each with `start`, `end`
+//!   and `alignments`; bins are half-open except the last, which holds 1.0.
+
+use crate::AlignmentData;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Query spans for the N50 and L50: every span (`--stats`), or a streaming
+/// sketch of them (`--stats-approx`).
+#[derive(Debug)]
+enum SpanDistribution {
+    Exact(Vec<i64>),
+    Approximate(SpanSketch),
+}
+
+impl Default for SpanDistribution {
+    fn default() -> Self {
+        SpanDistribution::Exact(Vec::new())
+    }
+}
+
+/// Relative width of a [`SpanSketch`] bucket.
+const SKETCH_ACCURACY: f64 = 0.01;
+
+/// Span counts and bases in logarithmic buckets, each `1 + SKETCH_ACCURACY`
+/// times wider than the last. Memory is bounded by the number of buckets
+/// (about 2,100 up to 1 Gbp), not the number of alignments. An N50 estimate
+/// is the geometric middle of its bucket, so within half a percent of the
+/// exact value; the L50 estimate assumes the spans of that bucket share its
+/// bases evenly.
+#[derive(Debug, Default)]
+struct SpanSketch {
+    /// Bucket index -> (spans, bases)
+    buckets: BTreeMap<i32, (usize, i64)>,
+}
+
+impl SpanSketch {
+    fn add(&mut self, span: i64) {
+        // Spans of 0 and 1 share bucket 0
+        let bucket = (span.max(1) as f64).ln() / SKETCH_ACCURACY.ln_1p();
+        let entry = self.buckets.entry(bucket.floor() as i32).or_default();
+        entry.0 += 1;
+        entry.1 += span;
+    }
+
+    fn n50_l50(&self, total: i64) -> (i64, usize) {
+        let (mut cumulative, mut spans) = (0, 0);
+        for (&bucket, &(count, bases)) in self.buckets.iter().rev() {
+            if 2 * (cumulative + bases) >= total && bases > 0 {
+                let middle = ((bucket as f64 + 0.5) * SKETCH_ACCURACY.ln_1p()).exp().round() as i64;
+                let mean = bases as f64 / count as f64;
+                let needed = ((total as f64 / 2.0 - cumulative as f64) / mean).ceil().max(1.0) as usize;
+                return (middle, spans + needed.min(count));
+            }
+            cumulative += bases;
+            spans += count;
+        }
+        (0, 0)
+    }
+}
+
+/// Whole-file totals for `--stats`, accumulated in a single pass.
+#[derive(Debug, Default)]
+pub struct SummaryStats {
+    alignments: usize,
+    query_bp: i64,
+    target_bp: i64,
+    identity_sum: f64,
+    weighted_identity_sum: f64,
+    weight_sum: i64,
+    /// Query spans, for N50/L50
+    query_spans: SpanDistribution,
+    /// Breakdowns for the JSON report
+    queries: BTreeMap<String, PairAccumulator>,
+    targets: BTreeMap<String, PairAccumulator>,
+    pairs: PairStats,
+    identity_bins: [usize; IDENTITY_BINS],
+}
+
+const IDENTITY_BINS: usize = 20;
+
+/// `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Per-sequence entries keyed by a `"name"` member.
+fn by_name(sequences: &BTreeMap<String, PairAccumulator>) -> Vec<(String, &PairAccumulator)> {
+    sequences.iter().map(|(name, acc)| (format!("\"name\": {}", json_string(name)), acc)).collect()
+}
+
+/// A JSON array member of totals, each object led by its `keys` members.
+fn write_json_array<W: Write>(out: &mut W, field: &str, entries: Vec<(String, &PairAccumulator)>) -> io::Result<()> {
+    write!(out, "  \"{}\": [", field)?;
+    for (i, (keys, acc)) in entries.iter().enumerate() {
+        write!(
+            out,
+            "{}\n    {{{}, \"alignments\": {}, \"query_bp\": {}, \"target_bp\": {}, \"mean_identity\": {}}}",
+            if i == 0 { "" } else { "," },
+            keys,
+            acc.alignments,
+            acc.query_bp,
+            acc.target_bp,
+            acc.mean_identity()
+        )?;
+    }
+    writeln!(out, "{}],", if entries.is_empty() { "" } else { "\n  " })
+}
+
+impl SummaryStats {
+    /// Totals whose N50 and L50 are estimated in constant memory instead of
+    /// from every span.
+    pub fn approximate() -> Self {
+        SummaryStats {
+            query_spans: SpanDistribution::Approximate(SpanSketch::default()),
+            ..SummaryStats::default()
+        }
+    }
+
+    pub fn add(&mut self, aln: &AlignmentData) {
+        let identity = aln.identity();
+        // Weight by the PAF block length, the same span the identity is measured over
+        let span = aln.block_length();
+
+        self.alignments += 1;
+        self.query_bp += aln.query_span();
+        self.target_bp += aln.target_span();
+        match &mut self.query_spans {
+            SpanDistribution::Exact(spans) => spans.push(aln.query_span()),
+            SpanDistribution::Approximate(sketch) => sketch.add(aln.query_span()),
+        }
+        self.identity_sum += identity;
+        self.weighted_identity_sum += identity * span as f64;
+        self.weight_sum += span;
+
+        self.queries.entry(aln.query_name.clone()).or_default().add(aln);
+        self.targets.entry(aln.target_name.clone()).or_default().add(aln);
+        self.pairs.add(&aln.query_name, &aln.target_name, aln);
+        let bin = ((identity * IDENTITY_BINS as f64) as usize).min(IDENTITY_BINS - 1);
+        self.identity_bins[bin] += 1;
+    }
+
+    fn mean_identity(&self) -> f64 {
+        if self.alignments == 0 {
+            return 0.0;
+        }
+        self.identity_sum / self.alignments as f64
+    }
+
+    fn is_approximate(&self) -> bool {
+        matches!(self.query_spans, SpanDistribution::Approximate(_))
+    }
+
+    /// `sum(identity * span) / sum(span)`
+    fn weighted_mean_identity(&self) -> f64 {
+        if self.weight_sum == 0 {
+            return 0.0;
+        }
+        self.weighted_identity_sum / self.weight_sum as f64
+    }
+
+    /// N50 and L50 of the query spans: taking spans longest first, the span
+    /// at which the running total first reaches half of all aligned query
+    /// bases, and how many spans that took. Both are 0 without alignments.
+    fn query_n50_l50(&self) -> (i64, usize) {
+        let spans = match &self.query_spans {
+            SpanDistribution::Exact(spans) => spans,
+            SpanDistribution::Approximate(sketch) => return sketch.n50_l50(self.query_bp),
+        };
+        let mut spans = spans.clone();
+        spans.sort_unstable_by(|a, b| b.cmp(a));
+        let mut cumulative = 0;
+        for (i, &span) in spans.iter().enumerate() {
+            cumulative += span;
+            // Compare doubled values so an odd total needs no rounding
+            if 2 * cumulative >= self.query_bp {
+                return (span, i + 1);
+            }
+        }
+        (0, 0)
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let (n50, l50) = self.query_n50_l50();
+        let approximate = if self.is_approximate() { " (approximate)" } else { "" };
+        writeln!(out, "Alignments: {}", self.alignments)?;
+        writeln!(out, "Query aligned bp: {}", self.query_bp)?;
+        writeln!(out, "Target aligned bp: {}", self.target_bp)?;
+        writeln!(out, "Query span N50{}: {}", approximate, n50)?;
+        writeln!(out, "Query span L50{}: {}", approximate, l50)?;
+        writeln!(out, "Mean identity: {:.6}", self.mean_identity())?;
+        writeln!(
+            out,
+            "Length-weighted mean identity: {:.6}",
+            self.weighted_mean_identity()
+        )?;
+        Ok(())
+    }
+
+    /// The JSON report described in the module documentation.
+    pub fn write_json<W: Write>(&self, out: &mut W, input: &str) -> io::Result<()> {
+        let (n50, l50) = self.query_n50_l50();
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"schema_version\": 1,")?;
+        writeln!(
+            out,
+            "  \"tool\": {{\"name\": \"oneview-rs\", \"version\": {}}},",
+            json_string(env!("CARGO_PKG_VERSION"))
+        )?;
+        writeln!(out, "  \"input\": {},", json_string(input))?;
+        writeln!(out, "  \"summary\": {{")?;
+        writeln!(out, "    \"alignments\": {},", self.alignments)?;
+        writeln!(out, "    \"query_aligned_bp\": {},", self.query_bp)?;
+        writeln!(out, "    \"target_aligned_bp\": {},", self.target_bp)?;
+        writeln!(out, "    \"query_span_n50\": {},", n50)?;
+        writeln!(out, "    \"query_span_l50\": {},", l50)?;
+        writeln!(out, "    \"mean_identity\": {},", self.mean_identity())?;
+        writeln!(out, "    \"length_weighted_mean_identity\": {},", self.weighted_mean_identity())?;
+        writeln!(out, "    \"approximate\": {}", self.is_approximate())?;
+        writeln!(out, "  }},")?;
+
+        write_json_array(out, "queries", by_name(&self.queries))?;
+        write_json_array(out, "targets", by_name(&self.targets))?;
+        let pairs = self
+            .pairs
+            .pairs
+            .iter()
+            .map(|((query, target), acc)| {
+                (format!("\"query\": {}, \"target\": {}", json_string(query), json_string(target)), acc)
+            })
+            .collect();
+        write_json_array(out, "pairs", pairs)?;
+
+        let bin_width = 1.0 / IDENTITY_BINS as f64;
+        writeln!(out, "  \"identity_histogram\": {{")?;
+        writeln!(out, "    \"bin_width\": {},", bin_width)?;
+        write!(out, "    \"bins\": [")?;
+        for (i, count) in self.identity_bins.iter().enumerate() {
+            write!(
+                out,
+                "{}\n      {{\"start\": {}, \"end\": {}, \"alignments\": {}}}",
+                if i == 0 { "" } else { "," },
+                i as f64 / IDENTITY_BINS as f64,
+                (i + 1) as f64 / IDENTITY_BINS as f64,
+                count
+            )?;
+        }
+        writeln!(out, "\n    ]")?;
+        writeln!(out, "  }}")?;
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct PairAccumulator {
+    alignments: usize,
+    query_bp: i64,
+    target_bp: i64,
+    identity_sum: f64,
+}
+
+impl PairAccumulator {
+    fn add(&mut self, aln: &AlignmentData) {
+        self.alignments += 1;
+        self.query_bp += aln.query_span();
+        self.target_bp += aln.target_span();
+        self.identity_sum += aln.identity();
+    }
+
+    fn mean_identity(&self) -> f64 {
+        self.identity_sum / self.alignments as f64
+    }
+}
+
+/// Per (query, target) group totals for `--stats-pairs`.
+///
+/// Keys are whatever the caller groups by (full sequence names, PanSN
+/// samples, ...), so the same accumulator serves every grouping.
+#[derive(Debug, Default)]
+pub struct PairStats {
+    pairs: BTreeMap<(String, String), PairAccumulator>,
+}
+
+impl PairStats {
+    pub fn add(&mut self, query_key: &str, target_key: &str, aln: &AlignmentData) {
+        self.pairs
+            .entry((query_key.to_string(), target_key.to_string()))
+            .or_default()
+            .add(aln);
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "#query\ttarget\talignments\tquery_bp\ttarget_bp\tmean_identity"
+        )?;
+        for ((query, target), acc) in &self.pairs {
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{:.6}",
+                query,
+                target,
+                acc.alignments,
+                acc.query_bp,
+                acc.target_bp,
+                acc.mean_identity()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct SequenceAccumulator {
+    length: i64,
+    /// Aligned intervals on this sequence, merged when written
+    intervals: Vec<(i64, i64)>,
+    identity_sum: f64,
+    partners: HashSet<String>,
+    largest: i64,
+}
+
+/// One row per sequence of one side for `--stats-per-target` and
+/// `--stats-per-query`, and the covered intervals behind
+/// `--query-covered-bed` and `--min-covered-frac`. Sequences are seeded from the metadata, so those
+/// without any alignment still get a row of zeros.
+#[derive(Debug, Default)]
+pub struct SequenceStats {
+    sequences: HashMap<String, SequenceAccumulator>,
+}
+
+impl SequenceStats {
+    /// Rows for every `(name, length)`; contigs of one scaffold repeat it.
+    pub fn new(sequences: impl Iterator<Item = (String, i64)>) -> Self {
+        let mut stats = SequenceStats::default();
+        for (name, length) in sequences {
+            stats.sequences.entry(name).or_default().length = length;
+        }
+        stats
+    }
+
+    /// Record an alignment covering `[start, end)` of `name`, aligned to `partner`.
+    pub fn add(&mut self, name: &str, partner: &str, start: i64, end: i64, identity: f64) {
+        let acc = self.sequences.entry(name.to_string()).or_default();
+        acc.intervals.push((start, end));
+        acc.identity_sum += identity;
+        if !acc.partners.contains(partner) {
+            acc.partners.insert(partner.to_string());
+        }
+        acc.largest = acc.largest.max(end - start);
+    }
+
+    fn sorted_names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.sequences.keys().collect();
+        names.sort_by(|a, b| natural_cmp(a, b).then_with(|| a.cmp(b)));
+        names
+    }
+
+    /// Fraction of each sequence that alignments cover, 0 when its length is unknown.
+    pub fn covered_fractions(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.sequences.iter().map(|(name, acc)| {
+            let covered: i64 = merge(&acc.intervals).iter().map(|(start, end)| end - start).sum();
+            let fraction = if acc.length > 0 { covered as f64 / acc.length as f64 } else { 0.0 };
+            (name.as_str(), fraction)
+        })
+    }
+
+    /// The covered intervals as BED, merged so overlapping alignments count once.
+    pub fn write_bed<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for name in self.sorted_names() {
+            for (start, end) in merge(&self.sequences[name].intervals) {
+                writeln!(out, "{}\t{}\t{}", name, start, end)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "#sequence\tlength\talignments\taligned_bp\tcovered_percent\tmean_identity\tpartners\tlargest_alignment")?;
+        for name in self.sorted_names() {
+            let acc = &self.sequences[name];
+            let aligned_bp: i64 = merge(&acc.intervals).iter().map(|(start, end)| end - start).sum();
+            let alignments = acc.intervals.len();
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{:.2}\t{:.6}\t{}\t{}",
+                name,
+                acc.length,
+                alignments,
+                aligned_bp,
+                if acc.length > 0 { 100.0 * aligned_bp as f64 / acc.length as f64 } else { 0.0 },
+                if alignments > 0 { acc.identity_sum / alignments as f64 } else { 0.0 },
+                acc.partners.len(),
+                acc.largest
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The union of `intervals` as sorted, disjoint intervals.
+fn merge(intervals: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let mut intervals = intervals.to_vec();
+    intervals.sort_unstable();
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Compare names with runs of digits ordered by value, so chr2 sorts before chr10.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(a_first), Some(b_first)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let ordering = if a_first.is_ascii_digit() && b_first.is_ascii_digit() {
+            let a_digits = a.len() - a.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let b_digits = b.len() - b.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let (a_number, b_number) = (a[..a_digits].trim_start_matches('0'), b[..b_digits].trim_start_matches('0'));
+            let ordering = a_number.len().cmp(&b_number.len()).then_with(|| a_number.cmp(b_number));
+            a = &a[a_digits..];
+            b = &b[b_digits..];
+            ordering
+        } else {
+            let ordering = a_first.cmp(&b_first);
+            a = &a[a_first.len_utf8()..];
+            b = &b[b_first.len_utf8()..];
+            ordering
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Aligned query bases per identity bin and (query, target) group for
+/// `--identity-bins`. With `n` thresholds there are `n + 1` bins: below
+/// the first threshold, between consecutive ones, and at or above the last.
+/// Alignments are binned segment by segment where their trace allows, so a
+/// diverged stretch inside a long alignment lands in a lower bin instead of
+/// being averaged away.
+#[derive(Debug)]
+pub struct IdentityBins {
+    /// Ascending
+    thresholds: Vec<f64>,
+    pairs: BTreeMap<(String, String), Vec<i64>>,
+}
+
+impl IdentityBins {
+    pub fn new(mut thresholds: Vec<f64>) -> Self {
+        thresholds.sort_by(f64::total_cmp);
+        thresholds.dedup();
+        IdentityBins {
+            thresholds,
+            pairs: BTreeMap::new(),
+        }
+    }
+
+    /// Add `bases` aligned at `identity` to the group.
+    pub fn add(&mut self, query_key: &str, target_key: &str, identity: f64, bases: i64) {
+        let bins = self
+            .pairs
+            .entry((query_key.to_string(), target_key.to_string()))
+            .or_insert_with(|| vec![0; self.thresholds.len() + 1]);
+        let bin = self.thresholds.partition_point(|&threshold| threshold <= identity);
+        bins[bin] += bases;
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "#query\ttarget")?;
+        for bin in 0..=self.thresholds.len() {
+            match (bin.checked_sub(1).map(|i| self.thresholds[i]), self.thresholds.get(bin)) {
+                (None, Some(upper)) => write!(out, "\tbp_below_{}", upper)?,
+                (Some(lower), Some(upper)) => write!(out, "\tbp_{}_{}", lower, upper)?,
+                (Some(lower), None) => write!(out, "\tbp_from_{}", lower)?,
+                (None, None) => write!(out, "\tbp")?,
+            }
+        }
+        writeln!(out)?;
+
+        let mut totals = vec![0; self.thresholds.len() + 1];
+        for ((query, target), bins) in &self.pairs {
+            write!(out, "{}\t{}", query, target)?;
+            for (total, bases) in totals.iter_mut().zip(bins) {
+                *total += bases;
+                write!(out, "\t{}", bases)?;
+            }
+            writeln!(out)?;
+        }
+        // Genome-wide totals last, under names no sequence can have in a TSV column
+        write!(out, "*\t*")?;
+        for total in totals {
+            write!(out, "\t{}", total)?;
+        }
+        writeln!(out)
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueryAccumulator {
+    alignments: usize,
+    targets: HashSet<String>,
+    aligned_bp: i64,
+}
+
+/// Alignment count, distinct targets and aligned bp per query sequence for
+/// `--query-summary`, in one pass.
+#[derive(Debug, Default)]
+pub struct QuerySummary {
+    queries: HashMap<String, QueryAccumulator>,
+}
+
+impl QuerySummary {
+    pub fn add(&mut self, query: &str, target: &str, aln: &AlignmentData) {
+        let acc = self.queries.entry(query.to_string()).or_default();
+        acc.alignments += 1;
+        if !acc.targets.contains(target) {
+            acc.targets.insert(target.to_string());
+        }
+        acc.aligned_bp += aln.query_span();
+    }
+
+    /// Queries with at least `min_count` alignments, most alignments first.
+    pub fn write<W: Write>(&self, out: &mut W, min_count: usize) -> io::Result<()> {
+        let mut queries: Vec<(&String, &QueryAccumulator)> =
+            self.queries.iter().filter(|(_, acc)| acc.alignments >= min_count).collect();
+        queries.sort_by(|(name_a, a), (name_b, b)| {
+            b.alignments.cmp(&a.alignments).then_with(|| natural_cmp(name_a, name_b))
+        });
+        writeln!(out, "#query\talignments\ttargets\taligned_bp")?;
+        for (name, acc) in queries {
+            writeln!(out, "{}\t{}\t{}\t{}", name, acc.alignments, acc.targets.len(), acc.aligned_bp)?;
+        }
+        Ok(())
+    }
+}
+
+/// Alignments and aligned query bp on each strand, for `--orientation-stats`.
+#[derive(Debug, Default, Clone, Copy)]
+struct StrandCounts {
+    forward: usize,
+    reverse: usize,
+    forward_bp: i64,
+    reverse_bp: i64,
+}
+
+/// Strand balance per (query, target) pair and genome-wide. A pair's
+/// dominant orientation is the strand with more aligned bp; when the other
+/// strand holds at least `mixed_fraction` of them the pair is flagged as
+/// mixed, a hint of an inversion or misassembly, and otherwise a pair
+/// dominated by the reverse strand is flagged as inverted.
+#[derive(Debug)]
+pub struct OrientationStats {
+    mixed_fraction: f64,
+    /// Order pairs by total aligned bp, largest first, instead of by name
+    by_span: bool,
+    pairs: HashMap<(String, String), StrandCounts>,
+}
+
+impl OrientationStats {
+    pub fn new(mixed_fraction: f64, by_span: bool) -> Self {
+        OrientationStats {
+            mixed_fraction,
+            by_span,
+            pairs: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, query: &str, target: &str, aln: &AlignmentData) {
+        let counts = self.pairs.entry((query.to_string(), target.to_string())).or_default();
+        if aln.strand == '-' {
+            counts.reverse += 1;
+            counts.reverse_bp += aln.query_span();
+        } else {
+            counts.forward += 1;
+            counts.forward_bp += aln.query_span();
+        }
+    }
+
+    fn write_row<W: Write>(&self, out: &mut W, query: &str, target: &str, counts: &StrandCounts) -> io::Result<()> {
+        let total_bp = counts.forward_bp + counts.reverse_bp;
+        let (dominant, minority_bp) = if counts.reverse_bp > counts.forward_bp {
+            ('-', counts.forward_bp)
+        } else {
+            ('+', counts.reverse_bp)
+        };
+        let minority = if total_bp > 0 { minority_bp as f64 / total_bp as f64 } else { 0.0 };
+        let flag = if minority > 0.0 && minority >= self.mixed_fraction {
+            "mixed"
+        } else if dominant == '-' {
+            "inverted"
+        } else {
+            "."
+        };
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.4}\t{}",
+            query,
+            target,
+            counts.forward,
+            counts.reverse,
+            counts.forward_bp,
+            counts.reverse_bp,
+            dominant,
+            minority,
+            flag
+        )
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "#query\ttarget\tforward\treverse\tforward_bp\treverse_bp\tdominant\tminority_fraction\tflag"
+        )?;
+        let mut pairs: Vec<(&(String, String), &StrandCounts)> = self.pairs.iter().collect();
+        pairs.sort_by(|((query_a, target_a), counts_a), ((query_b, target_b), counts_b)| {
+            let by_name = || natural_cmp(query_a, query_b).then_with(|| natural_cmp(target_a, target_b));
+            if self.by_span {
+                let span = |counts: &StrandCounts| counts.forward_bp + counts.reverse_bp;
+                span(counts_b).cmp(&span(counts_a)).then_with(by_name)
+            } else {
+                by_name()
+            }
+        });
+        let mut total = StrandCounts::default();
+        for ((query, target), counts) in pairs {
+            total.forward += counts.forward;
+            total.reverse += counts.reverse;
+            total.forward_bp += counts.forward_bp;
+            total.reverse_bp += counts.reverse_bp;
+            self.write_row(out, query, target, counts)?;
+        }
+        // Genome-wide last
+        self.write_row(out, "*", "*", &total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn alignment(query: &str, target: &str, span: i64, differences: i64) -> AlignmentData {
+        AlignmentData {
+            query_name: query.to_string(),
+            target_name: target.to_string(),
+            query_end: span,
+            target_end: span,
+            differences,
+            ..Default::default()
+        }
+    }
+
+    fn json(stats: &SummaryStats) -> Value {
+        let mut out = Vec::new();
+        stats.write_json(&mut out, "in \"dir\"/x.1aln").unwrap();
+        serde_json::from_slice(&out).expect("valid JSON")
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn json_follows_the_documented_schema() {
+        let mut stats = SummaryStats::default();
+        stats.add(&alignment("q\t1", "t1", 1000, 0));
+        stats.add(&alignment("q2", "t1", 500, 200));
+        let report = json(&stats);
+
+        assert_eq!(
+            keys(&report),
+            ["identity_histogram", "input", "pairs", "queries", "schema_version", "summary", "targets", "tool"]
+        );
+        assert_eq!(report["schema_version"], 1);
+        assert_eq!(report["tool"]["name"], "oneview-rs");
+        assert_eq!(report["input"], "in \"dir\"/x.1aln");
+
+        let summary = &report["summary"];
+        assert_eq!(
+            keys(summary),
+            [
+                "alignments",
+                "approximate",
+                "length_weighted_mean_identity",
+                "mean_identity",
+                "query_aligned_bp",
+                "query_span_l50",
+                "query_span_n50",
+                "target_aligned_bp"
+            ]
+        );
+        assert_eq!(summary["alignments"], 2);
+        assert_eq!(summary["query_aligned_bp"], 1500);
+        assert_eq!(summary["query_span_n50"], 1000);
+        assert_eq!(summary["query_span_l50"], 1);
+        assert_eq!(summary["approximate"], false);
+
+        let queries = report["queries"].as_array().unwrap();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0]["name"], "q\t1");
+        assert_eq!(keys(&queries[0]), ["alignments", "mean_identity", "name", "query_bp", "target_bp"]);
+        assert_eq!(report["targets"][0]["alignments"], 2);
+        assert_eq!(report["pairs"][0]["query"], "q\t1");
+        assert_eq!(report["pairs"][0]["target"], "t1");
+
+        let bins = report["identity_histogram"]["bins"].as_array().unwrap();
+        assert_eq!(bins.len(), IDENTITY_BINS);
+        let counted: u64 = bins.iter().map(|bin| bin["alignments"].as_u64().unwrap()).sum();
+        assert_eq!(counted, 2);
+        // The exact alignment lands in the last bin, which holds 1.0
+        assert_eq!(bins[IDENTITY_BINS - 1]["alignments"], 1);
+    }
+
+    #[test]
+    fn json_without_alignments_is_valid() {
+        let report = json(&SummaryStats::approximate());
+        assert_eq!(report["summary"]["alignments"], 0);
+        assert_eq!(report["summary"]["approximate"], true);
+        assert_eq!(report["queries"], Value::Array(Vec::new()));
+        assert_eq!(report["pairs"], Value::Array(Vec::new()));
+    }
+}
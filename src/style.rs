@@ -0,0 +1,148 @@
+//! ANSI colors for human output (`--color`).
+//!
+//! Only the human format is styled, and only by wrapping text that is
+//! written anyway, so stripping the escapes gives the plain output back and
+//! PAF, MAF, BEDPE and template output never pass through here. The
+//! "Query:" and "Target:" labels take the color of the alignment's
+//! identity tier, a reverse strand is highlighted, and coordinates are
+//! dimmed.
+
+use clap::ValueEnum;
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const MAGENTA: &str = "\x1b[35m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            // https://no-color.org: any non-empty value disables color
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Identity thresholds of the color tiers: green from `high`, yellow from
+/// `low`, red below.
+#[derive(Copy, Clone, Debug)]
+pub struct Palette {
+    pub high: f64,
+    pub low: f64,
+}
+
+/// The colors in use, or none.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Style {
+    palette: Option<Palette>,
+}
+
+/// `value` wrapped in an escape sequence when styling is on.
+pub struct Painted<T> {
+    value: T,
+    code: Option<&'static str>,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}{}{}", code, self.value, RESET),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+impl Style {
+    pub fn new(palette: Option<Palette>) -> Self {
+        Style { palette }
+    }
+
+    fn paint<T>(&self, value: T, code: &'static str) -> Painted<T> {
+        Painted {
+            value,
+            code: self.palette.map(|_| code),
+        }
+    }
+
+    /// `value` in the color of `identity`'s tier.
+    pub fn identity<T>(&self, value: T, identity: f64) -> Painted<T> {
+        let code = match self.palette {
+            Some(palette) if identity >= palette.high => GREEN,
+            Some(palette) if identity >= palette.low => YELLOW,
+            _ => RED,
+        };
+        self.paint(value, code)
+    }
+
+    /// The strand, highlighted when reverse.
+    pub fn strand(&self, strand: char) -> Painted<char> {
+        match strand {
+            '-' => self.paint(strand, MAGENTA),
+            _ => Painted { value: strand, code: None },
+        }
+    }
+
+    pub fn dim<T>(&self, value: T) -> Painted<T> {
+        self.paint(value, DIM)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: Palette = Palette { high: 0.995, low: 0.95 };
+
+    #[test]
+    fn never_writes_no_escapes() {
+        assert!(!ColorChoice::Never.enabled());
+        let style = Style::new(None);
+        let text = format!(
+            "{} {} {} {}",
+            style.identity("Query:", 0.5),
+            style.identity("Target:", 1.0),
+            style.strand('-'),
+            style.dim("10-20")
+        );
+        assert_eq!(text, "Query: Target: - 10-20");
+        assert!(!text.contains('\x1b'));
+    }
+
+    #[test]
+    fn always_colors() {
+        assert!(ColorChoice::Always.enabled());
+    }
+
+    #[test]
+    fn identity_tiers() {
+        let style = Style::new(Some(PALETTE));
+        assert_eq!(style.identity("x", 0.999).to_string(), format!("{}x{}", GREEN, RESET));
+        assert_eq!(style.identity("x", 0.995).to_string(), format!("{}x{}", GREEN, RESET));
+        assert_eq!(style.identity("x", 0.97).to_string(), format!("{}x{}", YELLOW, RESET));
+        assert_eq!(style.identity("x", 0.9).to_string(), format!("{}x{}", RED, RESET));
+    }
+
+    #[test]
+    fn only_the_reverse_strand_is_highlighted() {
+        let style = Style::new(Some(PALETTE));
+        assert_eq!(style.strand('+').to_string(), "+");
+        assert_eq!(style.strand('-').to_string(), format!("{}-{}", MAGENTA, RESET));
+        assert_eq!(style.dim(42).to_string(), format!("{}42{}", DIM, RESET));
+    }
+}
@@ -0,0 +1,165 @@
+//! User-defined record layouts for `--format 'TEMPLATE'`.
+//!
+//! A template mixes literal text with `{column}` or `{column:.N}`
+//! placeholders naming entries of the column registry. `\t`, `\n` and `\\`
+//! are unescaped, and `{{`/`}}` produce literal braces. Each record ends with
+//! a newline unless the template already ends with one.
+
+use crate::columns::{COLUMNS, Column, Value};
+use crate::{AlignmentData, OutputConfig};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Literal(String),
+    Column {
+        column: Column,
+        precision: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(other) => return Err(format!("unknown escape sequence '\\{}'", other)),
+            None => return Err("template ends with a lone backslash".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_placeholder(spec: &str) -> Result<Piece, String> {
+    let (name, precision) = match spec.split_once(':') {
+        Some((name, format)) => {
+            let digits = format
+                .strip_prefix('.')
+                .ok_or_else(|| format!("invalid format '{}' for {{{}}}, expected .N", format, name))?;
+            let precision = digits
+                .parse()
+                .map_err(|_| format!("invalid precision '{}' for {{{}}}", digits, name))?;
+            (name, Some(precision))
+        }
+        None => (spec, None),
+    };
+
+    let column = Column::from_name(name).ok_or_else(|| {
+        let known: Vec<&str> = COLUMNS.iter().map(|(name, _, _)| *name).collect();
+        format!("unknown placeholder {{{}}} (available: {})", name, known.join(", "))
+    })?;
+    if precision.is_some() && !column.is_float() {
+        return Err(format!("precision is only supported for floating-point columns, not {{{}}}", name));
+    }
+    Ok(Piece::Column { column, precision })
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut rest = s;
+
+        while let Some(pos) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..pos]);
+            let brace = rest.as_bytes()[pos];
+            rest = &rest[pos + 1..];
+
+            if let Some(after) = rest.strip_prefix(brace as char) {
+                // Doubled brace is a literal
+                literal.push(brace as char);
+                rest = after;
+                continue;
+            }
+            if brace == b'}' {
+                return Err("unmatched '}' in template (use '}}' for a literal brace)".to_string());
+            }
+
+            let end = rest
+                .find('}')
+                .ok_or_else(|| "unclosed '{' in template".to_string())?;
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(unescape(&literal)?));
+                literal.clear();
+            }
+            pieces.push(parse_placeholder(&rest[..end])?);
+            rest = &rest[end + 1..];
+        }
+        literal.push_str(rest);
+
+        // Checked once unescaped: `\\n` is a backslash and an n, not a newline
+        let mut tail = unescape(&literal)?;
+        if !tail.ends_with('\n') {
+            tail.push('\n');
+        }
+        pieces.push(Piece::Literal(tail));
+
+        Ok(Template { pieces })
+    }
+}
+
+impl Template {
+    pub fn render<W: Write + ?Sized>(
+        &self,
+        out: &mut W,
+        aln: &AlignmentData,
+        output: &OutputConfig,
+    ) -> io::Result<()> {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(text) => out.write_all(text.as_bytes())?,
+                Piece::Column { column, precision } => match column.value(aln, output) {
+                    Value::Str(text) => write!(out, "{}", output.names.apply(text)?)?,
+                    Value::Char(c) => write!(out, "{}", c)?,
+                    Value::Int(n) => write!(out, "{}", n)?,
+                    Value::Float(x) => match precision {
+                        Some(precision) => write!(out, "{:.*}", precision, x)?,
+                        None => write!(out, "{}", x)?,
+                    },
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text written after the last placeholder.
+    fn tail(template: &str) -> String {
+        match template.parse::<Template>().unwrap().pieces.last() {
+            Some(Piece::Literal(text)) => text.clone(),
+            other => panic!("template ends with {:?}", other),
+        }
+    }
+
+    #[test]
+    fn records_end_with_one_newline() {
+        assert_eq!(tail("{qname}"), "\n");
+        assert_eq!(tail(r"{qname}\n"), "\n");
+        assert_eq!(tail(r"{qname}\tend"), "\tend\n");
+    }
+
+    #[test]
+    fn an_escaped_backslash_before_n_is_not_a_newline() {
+        assert_eq!(tail(r"{qname}\\n"), "\\n\n");
+        assert_eq!(tail(r"{qname}\\\n"), "\\\n");
+    }
+}
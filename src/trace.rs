@@ -0,0 +1,230 @@
+//! Walking the tracepoint segments of an alignment.
+//!
+//! FastGA splits the query (A) interval at every multiple of the trace
+//! spacing, so all segments except the first and last span exactly
+//! `trace_spacing` query bases. The T list holds how many target (B) bases
+//! each segment advances, in the alignment's orientation, and the X list the
+//! differences within it.
+
+/// One tracepoint segment, with coordinates relative to the alignment start.
+/// Target offsets are in alignment orientation: for reverse-strand
+/// alignments they run along the reverse-complemented target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub query_start: i64,
+    pub query_end: i64,
+    pub target_start: i64,
+    pub target_end: i64,
+    pub diffs: i64,
+}
+
+impl Segment {
+    /// Differences over the mean of the query and target spans, the
+    /// per-segment counterpart of the estimated identity.
+    pub fn divergence(&self) -> f64 {
+        let span = (self.query_end - self.query_start) + (self.target_end - self.target_start);
+        if span == 0 {
+            return 0.0;
+        }
+        (2 * self.diffs) as f64 / span as f64
+    }
+
+    /// Net indel the segment must contain: how much longer one side is than the other.
+    pub fn implied_gap(&self) -> i64 {
+        ((self.query_end - self.query_start) - (self.target_end - self.target_start)).abs()
+    }
+}
+
+/// Per-segment figures for the human output.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceSummary {
+    pub segments: usize,
+    pub min_divergence: f64,
+    pub mean_divergence: f64,
+    /// The most divergent segment (the first, on ties)
+    pub worst: Segment,
+    /// The longest run of segments without differences, as its segment
+    /// count and query interval relative to the alignment start
+    pub longest_exact_run: Option<(usize, i64, i64)>,
+}
+
+pub fn summarize(segments: &[Segment]) -> Option<TraceSummary> {
+    let first = *segments.first()?;
+    let mut summary = TraceSummary {
+        segments: segments.len(),
+        min_divergence: f64::INFINITY,
+        mean_divergence: 0.0,
+        worst: first,
+        longest_exact_run: None,
+    };
+    let mut divergence_sum = 0.0;
+    let mut run: Option<(usize, i64, i64)> = None;
+    for segment in segments {
+        let divergence = segment.divergence();
+        divergence_sum += divergence;
+        summary.min_divergence = summary.min_divergence.min(divergence);
+        if divergence > summary.worst.divergence() {
+            summary.worst = *segment;
+        }
+
+        run = match (run, segment.diffs) {
+            (Some((count, start, _)), 0) => Some((count + 1, start, segment.query_end)),
+            (None, 0) => Some((1, segment.query_start, segment.query_end)),
+            _ => None,
+        };
+        if let Some(current) = run
+            && summary.longest_exact_run.is_none_or(|(longest, _, _)| current.0 > longest)
+        {
+            summary.longest_exact_run = Some(current);
+        }
+    }
+    summary.mean_divergence = divergence_sum / segments.len() as f64;
+    Some(summary)
+}
+
+/// Query-side segment boundaries of `[contig_start, contig_end)`, in contig
+/// coordinates.
+pub fn query_boundaries(contig_start: i64, contig_end: i64, trace_spacing: i64) -> Vec<i64> {
+    let mut boundaries = vec![contig_start];
+    let mut next = (contig_start / trace_spacing + 1) * trace_spacing;
+    while next < contig_end {
+        boundaries.push(next);
+        next += trace_spacing;
+    }
+    if contig_end > contig_start {
+        boundaries.push(contig_end);
+    }
+    boundaries
+}
+
+/// Split an alignment into its tracepoint segments.
+///
+/// `query_contig_start`/`query_contig_end` are the query coordinates within
+/// the contig (before scaffold offsets), since segment boundaries fall on
+/// multiples of the trace spacing in contig space. Returns `None` when the
+/// trace lists don't match the number of segments the span implies.
+pub fn segments(
+    query_contig_start: i64,
+    query_contig_end: i64,
+    trace_spacing: i64,
+    tracepoints: &[i64],
+    trace_diffs: &[i64],
+) -> Option<Vec<Segment>> {
+    if trace_spacing <= 0 || tracepoints.is_empty() || tracepoints.len() != trace_diffs.len() {
+        return None;
+    }
+    let boundaries = query_boundaries(query_contig_start, query_contig_end, trace_spacing);
+    if boundaries.len() != tracepoints.len() + 1 {
+        return None;
+    }
+
+    let mut segments = Vec::with_capacity(tracepoints.len());
+    let mut target_pos = 0;
+    for (i, (&advance, &diffs)) in tracepoints.iter().zip(trace_diffs).enumerate() {
+        segments.push(Segment {
+            query_start: boundaries[i] - query_contig_start,
+            query_end: boundaries[i + 1] - query_contig_start,
+            target_start: target_pos,
+            target_end: target_pos + advance,
+            diffs,
+        });
+        target_pos += advance;
+    }
+    Some(segments)
+}
+
+/// Merge the segments of an alignment into those of a coarser spacing
+/// `new_spacing`, a multiple of `trace_spacing`, summing target advances and
+/// diffs. Every multiple of `new_spacing` is also a multiple of
+/// `trace_spacing`, so no segment straddles a new boundary; the partial
+/// first and last segments simply join the coarse segment they fall in.
+/// Returns `None` under the same conditions as [`segments`], and when
+/// `new_spacing` is not a positive multiple of `trace_spacing`.
+pub fn respace(
+    query_contig_start: i64,
+    query_contig_end: i64,
+    trace_spacing: i64,
+    new_spacing: i64,
+    tracepoints: &[i64],
+    trace_diffs: &[i64],
+) -> Option<(Vec<i64>, Vec<i64>)> {
+    if new_spacing <= 0 || trace_spacing <= 0 || new_spacing % trace_spacing != 0 {
+        return None;
+    }
+    let segments = segments(query_contig_start, query_contig_end, trace_spacing, tracepoints, trace_diffs)?;
+    let mut merged_tracepoints: Vec<i64> = Vec::new();
+    let mut merged_diffs: Vec<i64> = Vec::new();
+    let mut current_bucket = None;
+    for segment in segments {
+        let bucket = (query_contig_start + segment.query_start) / new_spacing;
+        if current_bucket == Some(bucket) {
+            *merged_tracepoints.last_mut().expect("open segment") += segment.target_end - segment.target_start;
+            *merged_diffs.last_mut().expect("open segment") += segment.diffs;
+        } else {
+            merged_tracepoints.push(segment.target_end - segment.target_start);
+            merged_diffs.push(segment.diffs);
+            current_bucket = Some(bucket);
+        }
+    }
+    Some((merged_tracepoints, merged_diffs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respace_merges_partial_first_and_last_segments() {
+        // Segments [50,100) [100,200) ... [800,900) [900,950)
+        let tracepoints = [50, 100, 100, 100, 100, 100, 100, 100, 100, 50];
+        let trace_diffs = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let (merged, diffs) = respace(50, 950, 100, 300, &tracepoints, &trace_diffs).unwrap();
+        // Coarse segments [50,300) [300,600) [600,900) [900,950)
+        assert_eq!(merged, vec![250, 300, 300, 50]);
+        assert_eq!(diffs, vec![6, 15, 24, 10]);
+        assert_eq!(
+            segments(50, 950, 300, &merged, &diffs).map(|segments| segments.len()),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn respace_to_the_same_spacing_changes_nothing() {
+        let (tracepoints, trace_diffs) = ([50, 98, 47], [1, 0, 2]);
+        let (merged, diffs) = respace(50, 250, 100, 100, &tracepoints, &trace_diffs).unwrap();
+        assert_eq!(merged, tracepoints);
+        assert_eq!(diffs, trace_diffs);
+    }
+
+    #[test]
+    fn respace_of_a_span_inside_one_bucket() {
+        // [120,200) [200,280), both inside [0,500)
+        let (merged, diffs) = respace(120, 280, 100, 500, &[81, 79], &[3, 4]).unwrap();
+        assert_eq!(merged, vec![160]);
+        assert_eq!(diffs, vec![7]);
+    }
+
+    #[test]
+    fn respace_rejects_spacings_that_are_not_multiples() {
+        let (tracepoints, trace_diffs) = ([50, 100, 50], [0, 0, 0]);
+        assert_eq!(respace(50, 250, 100, 150, &tracepoints, &trace_diffs), None);
+        assert_eq!(respace(50, 250, 100, 0, &tracepoints, &trace_diffs), None);
+        assert_eq!(respace(50, 250, 100, -200, &tracepoints, &trace_diffs), None);
+    }
+
+    #[test]
+    fn segments_need_one_trace_value_per_segment() {
+        assert_eq!(segments(50, 250, 100, &[50, 100], &[0, 0]), None);
+        let segments = segments(50, 250, 100, &[50, 100, 48], &[1, 2, 3]).unwrap();
+        assert_eq!(
+            segments[2],
+            Segment {
+                query_start: 150,
+                query_end: 200,
+                target_start: 150,
+                target_end: 198,
+                diffs: 3,
+            }
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Local identity along alignments, as bedGraph in target or query coordinates.
+//!
+//! Two resolutions are available:
+//!
+//! * with FASTA for both genomes, each alignment is realigned and tiled into
+//!   windows of a fixed number of bases, and each window reports
+//!   matches / alignment columns;
+//! * otherwise the tracepoint segments themselves are the windows, each
+//!   reporting `1 - diffs / segment length` from the X list. Partial first
+//!   and last segments use their true lengths.
+//!
+//! Where alignments overlap, the covering values are combined per base run
+//! by a sweep over interval endpoints, so the output never overlaps itself.
+
+use crate::AlignmentData;
+use crate::align::Op;
+use crate::trace::Segment;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Space {
+    /// Intervals on the target sequences
+    Target,
+    /// Intervals on the query sequences
+    Query,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Combine {
+    /// Highest identity among overlapping alignments
+    Max,
+    /// Mean identity of overlapping alignments
+    Mean,
+}
+
+/// Identity intervals collected over a whole file, written once at the end.
+pub struct IdentityTrack {
+    space: Space,
+    combine: Combine,
+    intervals: BTreeMap<String, Vec<(i64, i64, f64)>>,
+}
+
+impl IdentityTrack {
+    pub fn new(space: Space, combine: Combine) -> Self {
+        IdentityTrack {
+            space,
+            combine,
+            intervals: BTreeMap::new(),
+        }
+    }
+
+    /// The name the track is keyed by for `aln` in this space.
+    pub fn sequence_name<'a>(&self, aln: &'a AlignmentData) -> &'a str {
+        match self.space {
+            Space::Target => &aln.target_name,
+            Space::Query => &aln.query_name,
+        }
+    }
+
+    /// Map `[start, end)` along the alignment, relative to its start in this
+    /// space, to forward-strand coordinates. Target offsets run in alignment
+    /// orientation; the query is always forward.
+    fn forward_interval(&self, aln: &AlignmentData, start: i64, end: i64) -> (i64, i64) {
+        match self.space {
+            Space::Query => (aln.query_start + start, aln.query_start + end),
+            Space::Target if aln.strand == '-' => (aln.target_end - end, aln.target_end - start),
+            Space::Target => (aln.target_start + start, aln.target_start + end),
+        }
+    }
+
+    fn push(&mut self, name: &str, start: i64, end: i64, identity: f64) {
+        if end > start {
+            self.intervals
+                .entry(name.to_string())
+                .or_default()
+                .push((start, end, identity.clamp(0.0, 1.0)));
+        }
+    }
+
+    pub fn add_segments(&mut self, name: &str, aln: &AlignmentData, segments: &[Segment]) {
+        for segment in segments {
+            let (start, end) = match self.space {
+                Space::Target => (segment.target_start, segment.target_end),
+                Space::Query => (segment.query_start, segment.query_end),
+            };
+            if end <= start {
+                continue;
+            }
+            let identity = 1.0 - segment.diffs as f64 / (end - start) as f64;
+            let (start, end) = self.forward_interval(aln, start, end);
+            self.push(name, start, end, identity);
+        }
+    }
+
+    /// Tile a base-level alignment into windows of `window` bases in this space.
+    pub fn add_windows(&mut self, name: &str, aln: &AlignmentData, ops: &[Op], window: i64) {
+        let mut window_start = 0;
+        let mut pos = 0;
+        let mut matches = 0;
+        let mut columns = 0;
+        let flush = |track: &mut Self, from: i64, to: i64, matches: i64, columns: i64| {
+            if columns > 0 {
+                let (start, end) = track.forward_interval(aln, from, to);
+                track.push(name, start, end, matches as f64 / columns as f64);
+            }
+        };
+
+        for &op in ops {
+            columns += 1;
+            let advances = match op {
+                Op::Match => {
+                    matches += 1;
+                    true
+                }
+                Op::Mismatch => true,
+                Op::Insertion => self.space == Space::Query,
+                Op::Deletion => self.space == Space::Target,
+            };
+            if advances {
+                pos += 1;
+            }
+            if pos - window_start == window {
+                flush(self, window_start, pos, matches, columns);
+                window_start = pos;
+                matches = 0;
+                columns = 0;
+            }
+        }
+        flush(self, window_start, pos, matches, columns);
+    }
+
+    /// Write non-overlapping bedGraph lines, combining overlaps per `combine`.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (name, intervals) in &self.intervals {
+            let mut events: Vec<(i64, bool, f64)> = Vec::with_capacity(intervals.len() * 2);
+            for &(start, end, identity) in intervals {
+                events.push((start, true, identity));
+                events.push((end, false, identity));
+            }
+            events.sort_by_key(|&(pos, _, _)| pos);
+
+            // Identities are in [0, 1], where the bit patterns of f64 order like the values
+            let mut active: BTreeMap<u64, usize> = BTreeMap::new();
+            let mut active_count = 0usize;
+            let mut active_sum = 0.0;
+            let mut pending: Option<(i64, i64, f64)> = None;
+            let mut previous = 0;
+
+            let mut i = 0;
+            while i < events.len() {
+                let pos = events[i].0;
+                if active_count > 0 && pos > previous {
+                    let value = match self.combine {
+                        Combine::Max => f64::from_bits(*active.keys().next_back().expect("active interval")),
+                        Combine::Mean => active_sum / active_count as f64,
+                    };
+                    pending = match pending {
+                        // Merge contiguous runs with the same value
+                        Some((start, end, last)) if end == previous && last == value => Some((start, pos, value)),
+                        Some((start, end, last)) => {
+                            writeln!(out, "{}\t{}\t{}\t{:.6}", name, start, end, last)?;
+                            Some((previous, pos, value))
+                        }
+                        None => Some((previous, pos, value)),
+                    };
+                }
+                while i < events.len() && events[i].0 == pos {
+                    let (_, is_start, identity) = events[i];
+                    let key = identity.to_bits();
+                    if is_start {
+                        *active.entry(key).or_insert(0) += 1;
+                        active_count += 1;
+                        active_sum += identity;
+                    } else {
+                        if let Some(count) = active.get_mut(&key) {
+                            *count -= 1;
+                            if *count == 0 {
+                                active.remove(&key);
+                            }
+                        }
+                        active_count -= 1;
+                        active_sum -= identity;
+                        if active_count == 0 {
+                            active_sum = 0.0;
+                        }
+                    }
+                    i += 1;
+                }
+                previous = pos;
+            }
+            if let Some((start, end, value)) = pending {
+                writeln!(out, "{}\t{}\t{}\t{:.6}", name, start, end, value)?;
+            }
+        }
+        Ok(())
+    }
+}
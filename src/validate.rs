@@ -0,0 +1,141 @@
+//! Internal consistency checks for `--validate`.
+
+use crate::{AlignmentData, DifferencesSource};
+use crate::trace;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Kinds of inconsistency, in the order they are summarized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ViolationClass {
+    MissingTrace,
+    SegmentCount,
+    TraceTargetSum,
+    TraceDiffsLength,
+    TraceDiffsSum,
+    Coordinates,
+}
+
+impl ViolationClass {
+    fn label(self) -> &'static str {
+        match self {
+            ViolationClass::MissingTrace => "missing tracepoints",
+            ViolationClass::SegmentCount => "segment count vs query span",
+            ViolationClass::TraceTargetSum => "T sum vs target span",
+            ViolationClass::TraceDiffsLength => "X length vs T length",
+            ViolationClass::TraceDiffsSum => "X sum vs D",
+            ViolationClass::Coordinates => "coordinates vs sequence lengths",
+        }
+    }
+}
+
+/// Every inconsistency found in one alignment, with a description each.
+pub fn check(aln: &AlignmentData) -> Vec<(ViolationClass, String)> {
+    let mut violations = Vec::new();
+
+    if aln.tracepoints.is_empty() {
+        violations.push((ViolationClass::MissingTrace, "no T line".to_string()));
+    } else {
+        let query_contig_start = aln.query_start - aln.query_offset;
+        let query_contig_end = aln.query_end - aln.query_offset;
+        let trace_spacing = aln.trace_spacing;
+        if trace_spacing > 0 {
+            // The first and last segments stop at the alignment ends, not at multiples of the spacing
+            let expected = trace::query_boundaries(query_contig_start, query_contig_end, trace_spacing).len() - 1;
+            if expected != aln.tracepoints.len() {
+                violations.push((
+                    ViolationClass::SegmentCount,
+                    format!(
+                        "{} tracepoints, but query {}-{} at trace spacing {} implies {} segments",
+                        aln.tracepoints.len(),
+                        query_contig_start,
+                        query_contig_end,
+                        trace_spacing,
+                        expected
+                    ),
+                ));
+            }
+        }
+
+        let target_sum: i64 = aln.tracepoints.iter().sum();
+        if target_sum != aln.target_span() {
+            violations.push((
+                ViolationClass::TraceTargetSum,
+                format!(
+                    "tracepoints advance {} target bases, but the target span is {}",
+                    target_sum,
+                    aln.target_span()
+                ),
+            ));
+        }
+
+        if aln.trace_diffs.len() != aln.tracepoints.len() {
+            violations.push((
+                ViolationClass::TraceDiffsLength,
+                format!(
+                    "X list has {} entries, T list has {}",
+                    aln.trace_diffs.len(),
+                    aln.tracepoints.len()
+                ),
+            ));
+        }
+    }
+
+    // Without a D line the difference count was derived from X, so there is nothing to compare
+    if aln.differences_source == DifferencesSource::DLine && !aln.trace_diffs.is_empty() {
+        let diffs_sum: i64 = aln.trace_diffs.iter().sum();
+        if diffs_sum != aln.differences {
+            violations.push((
+                ViolationClass::TraceDiffsSum,
+                format!("X list sums to {}, but D is {}", diffs_sum, aln.differences),
+            ));
+        }
+    }
+
+    for (side, start, end, length) in [
+        ("query", aln.query_start, aln.query_end, aln.query_length),
+        ("target", aln.target_start, aln.target_end, aln.target_length),
+    ] {
+        if start < 0 || start > end || (length > 0 && end > length) {
+            violations.push((
+                ViolationClass::Coordinates,
+                format!("{} interval {}-{} is invalid for sequence length {}", side, start, end, length),
+            ));
+        }
+    }
+
+    violations
+}
+
+/// Violation counts per class over a whole file.
+#[derive(Debug, Default)]
+pub struct ValidationSummary {
+    alignments: usize,
+    invalid_alignments: usize,
+    counts: BTreeMap<ViolationClass, usize>,
+}
+
+impl ValidationSummary {
+    pub fn add(&mut self, violations: &[(ViolationClass, String)]) {
+        self.alignments += 1;
+        if !violations.is_empty() {
+            self.invalid_alignments += 1;
+        }
+        for (class, _) in violations {
+            *self.counts.entry(*class).or_insert(0) += 1;
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.invalid_alignments == 0
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "Alignments checked: {}", self.alignments)?;
+        writeln!(out, "Alignments with violations: {}", self.invalid_alignments)?;
+        for (class, count) in &self.counts {
+            writeln!(out, "  {}: {}", class.label(), count)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Naive variant calls against the target (`--call-vcf`).
+//!
+//! Each alignment is realigned base by base and walked along the forward
+//! target strand ('-' alignments are reverse-complemented on both sides
+//! first). Every mismatch column becomes an SNV, and every run of gap
+//! columns an indel anchored on the target base before it, as VCF expects.
+//! Calls are neither left-normalized nor genotyped.
+//!
+//! Overlapping alignments can call the same site: identical calls are kept
+//! once, counting the alignments that made them, and calls that share a
+//! position but not their alleles are all marked with the `conflict` filter.
+
+use crate::AlignmentData;
+use crate::align::Op;
+use crate::fasta::reverse_complement;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+/// Alignment columns over which the LID field is measured, centred on the call.
+const IDENTITY_WINDOW: usize = 100;
+
+struct Call {
+    /// First alignment making the call, its local identity and strand
+    alignment: usize,
+    identity: f64,
+    strand: char,
+    /// Alignments making this exact call
+    support: usize,
+}
+
+/// Calls collected over a whole file, written sorted once at the end.
+pub struct VariantCaller {
+    /// Target sequences in header order, with their lengths
+    contigs: Vec<(String, i64)>,
+    rank: HashMap<String, usize>,
+    /// Keyed by (contig rank, 0-based position, REF, ALT)
+    calls: BTreeMap<(usize, i64, String, String), Call>,
+}
+
+impl VariantCaller {
+    /// Start with the target sequences `contigs`, which give the header and record order.
+    pub fn new(contigs: Vec<(String, i64)>) -> Self {
+        let rank = contigs.iter().enumerate().map(|(i, (name, _))| (name.clone(), i)).collect();
+        VariantCaller {
+            contigs,
+            rank,
+            calls: BTreeMap::new(),
+        }
+    }
+
+    /// Call variants from `ops`, the path between `query` (forward) and
+    /// `target` (alignment orientation) of `aln`, on the target named `name`.
+    pub fn add(&mut self, name: &str, aln: &AlignmentData, query: &str, target: &str, ops: &[Op]) {
+        let rank = match self.rank.get(name) {
+            Some(&rank) => rank,
+            None => {
+                // Anonymous targets are missing from the metadata
+                self.contigs.push((name.to_string(), aln.target_length));
+                self.rank.insert(name.to_string(), self.contigs.len() - 1);
+                self.contigs.len() - 1
+            }
+        };
+
+        let (query, target, ops): (Vec<u8>, Vec<u8>, Vec<Op>) = if aln.strand == '-' {
+            (
+                reverse_complement(query).into_bytes(),
+                reverse_complement(target).into_bytes(),
+                ops.iter().rev().copied().collect(),
+            )
+        } else {
+            (query.as_bytes().to_vec(), target.as_bytes().to_vec(), ops.to_vec())
+        };
+        let (query, target) = (query.to_ascii_uppercase(), target.to_ascii_uppercase());
+
+        // Matches before each column, for the identity around a call
+        let mut matches_before = Vec::with_capacity(ops.len() + 1);
+        matches_before.push(0usize);
+        for &op in &ops {
+            matches_before.push(matches_before.last().unwrap() + usize::from(op == Op::Match));
+        }
+        let identity = |column: usize| {
+            let low = column.saturating_sub(IDENTITY_WINDOW / 2);
+            let high = (column + IDENTITY_WINDOW / 2).min(ops.len());
+            (matches_before[high] - matches_before[low]) as f64 / (high - low).max(1) as f64
+        };
+
+        let (mut i, mut j, mut column) = (0usize, 0usize, 0usize);
+        while column < ops.len() {
+            let op = ops[column];
+            // A run of one gap type is a single indel; mismatches are called column by column
+            let run = match op {
+                Op::Insertion | Op::Deletion => ops[column..].iter().take_while(|&&next| next == op).count(),
+                Op::Match | Op::Mismatch => 1,
+            };
+            let call = match op {
+                Op::Match => None,
+                Op::Mismatch => Some((j, vec![target[j]], vec![query[i]])),
+                // A gap at the very start has no target base to anchor on
+                Op::Insertion | Op::Deletion if j == 0 => None,
+                Op::Insertion => Some((j - 1, vec![target[j - 1]], [&target[j - 1..j], &query[i..i + run]].concat())),
+                Op::Deletion => Some((j - 1, target[j - 1..j + run].to_vec(), vec![target[j - 1]])),
+            };
+            if let Some(call) = call {
+                self.record(rank, aln, call, identity(column));
+            }
+            if op != Op::Deletion {
+                i += run;
+            }
+            if op != Op::Insertion {
+                j += run;
+            }
+            column += run;
+        }
+    }
+
+    fn record(&mut self, rank: usize, aln: &AlignmentData, call: (usize, Vec<u8>, Vec<u8>), identity: f64) {
+        let (position, reference, alternative) = call;
+        // An N is not evidence of a variant
+        if reference.iter().chain(&alternative).any(|&base| !matches!(base, b'A' | b'C' | b'G' | b'T')) {
+            return;
+        }
+        let key = (
+            rank,
+            aln.target_start + position as i64,
+            String::from_utf8_lossy(&reference).into_owned(),
+            String::from_utf8_lossy(&alternative).into_owned(),
+        );
+        self.calls
+            .entry(key)
+            .and_modify(|call| call.support += 1)
+            .or_insert(Call {
+                alignment: aln.index,
+                identity,
+                strand: aln.strand,
+                support: 1,
+            });
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "##fileformat=VCFv4.2")?;
+        writeln!(out, "##source=oneview")?;
+        for (name, length) in &self.contigs {
+            if *length > 0 {
+                writeln!(out, "##contig=<ID={},length={}>", name, length)?;
+            } else {
+                writeln!(out, "##contig=<ID={}>", name)?;
+            }
+        }
+        writeln!(out, "##INFO=<ID=ALN,Number=1,Type=Integer,Description=\"Index of the first alignment making the call\">")?;
+        writeln!(out, "##INFO=<ID=NALN,Number=1,Type=Integer,Description=\"Number of alignments making the identical call\">")?;
+        writeln!(
+            out,
+            "##INFO=<ID=LID,Number=1,Type=Float,Description=\"Identity of that alignment over the {} columns around the call\">",
+            IDENTITY_WINDOW
+        )?;
+        writeln!(out, "##INFO=<ID=STRAND,Number=1,Type=Character,Description=\"Strand of that alignment\">")?;
+        writeln!(out, "##FILTER=<ID=conflict,Description=\"Another call at this position has different alleles\">")?;
+        writeln!(out, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+
+        let mut calls_at: HashMap<(usize, i64), usize> = HashMap::new();
+        for &(rank, position, _, _) in self.calls.keys() {
+            *calls_at.entry((rank, position)).or_insert(0) += 1;
+        }
+        for ((rank, position, reference, alternative), call) in &self.calls {
+            let filter = if calls_at[&(*rank, *position)] > 1 { "conflict" } else { "PASS" };
+            writeln!(
+                out,
+                "{}\t{}\t.\t{}\t{}\t.\t{}\tALN={};NALN={};LID={:.4};STRAND={}",
+                self.contigs[*rank].0,
+                position + 1,
+                reference,
+                alternative,
+                filter,
+                call.alignment,
+                call.support,
+                call.identity,
+                call.strand
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op::{Deletion as D, Insertion as I, Match as M, Mismatch as X};
+
+    fn alignment(index: usize, strand: char, target_start: i64) -> AlignmentData {
+        AlignmentData {
+            index,
+            strand,
+            target_start,
+            ..Default::default()
+        }
+    }
+
+    /// The VCF records, without the header.
+    fn records(caller: &VariantCaller) -> Vec<String> {
+        let mut out = Vec::new();
+        caller.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap().lines().filter(|line| !line.starts_with('#')).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn snvs_and_indels_anchored_on_the_base_before() {
+        let mut caller = VariantCaller::new(vec![("t1".to_string(), 1000)]);
+        // Target ACGT--AC GT over query ACTTGGA-GT: G>T at 2, GG inserted after 3, C deleted at 5
+        let ops = [M, M, X, M, I, I, M, D, M, M];
+        caller.add("t1", &alignment(0, '+', 100), "ACTTGGAGT", "ACGTACGT", &ops);
+        assert_eq!(
+            records(&caller),
+            [
+                "t1\t103\t.\tG\tT\t.\tPASS\tALN=0;NALN=1;LID=0.6000;STRAND=+",
+                "t1\t104\t.\tT\tTGG\t.\tPASS\tALN=0;NALN=1;LID=0.6000;STRAND=+",
+                "t1\t105\t.\tAC\tA\t.\tPASS\tALN=0;NALN=1;LID=0.6000;STRAND=+",
+            ]
+        );
+    }
+
+    #[test]
+    fn reverse_strand_calls_are_on_the_forward_target() {
+        let mut caller = VariantCaller::new(vec![("t1".to_string(), 1000)]);
+        // The forward target GATTACA, given as TGTAATC; the query TTAATC skips its G, which is
+        // the forward C at offset 5, anchored on the A before it
+        caller.add("t1", &alignment(0, '-', 200), "TTAATC", "TGTAATC", &[M, D, M, M, M, M, M]);
+        // A mismatch at alignment offset 3 is forward offset 3, T read as G
+        caller.add("t1", &alignment(1, '-', 200), "TGTCATC", "TGTAATC", &[M, M, M, X, M, M, M]);
+        assert_eq!(
+            records(&caller),
+            [
+                "t1\t204\t.\tT\tG\t.\tPASS\tALN=1;NALN=1;LID=0.8571;STRAND=-",
+                "t1\t205\t.\tAC\tA\t.\tPASS\tALN=0;NALN=1;LID=0.8571;STRAND=-",
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_calls_are_counted_and_differing_ones_conflict() {
+        let mut caller = VariantCaller::new(vec![("t1".to_string(), 1000)]);
+        caller.add("t1", &alignment(4, '+', 0), "AAT", "AGT", &[M, X, M]);
+        caller.add("t1", &alignment(7, '+', 0), "AAT", "AGT", &[M, X, M]);
+        assert_eq!(records(&caller), ["t1\t2\t.\tG\tA\t.\tPASS\tALN=4;NALN=2;LID=0.6667;STRAND=+"]);
+
+        caller.add("t1", &alignment(9, '+', 0), "ACT", "AGT", &[M, X, M]);
+        assert_eq!(
+            records(&caller),
+            [
+                "t1\t2\t.\tG\tA\t.\tconflict\tALN=4;NALN=2;LID=0.6667;STRAND=+",
+                "t1\t2\t.\tG\tC\t.\tconflict\tALN=9;NALN=1;LID=0.6667;STRAND=+",
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_at_the_start_and_n_bases_are_not_called() {
+        let mut caller = VariantCaller::new(vec![("t1".to_string(), 1000)]);
+        caller.add("t1", &alignment(0, '+', 0), "GACGT", "ANGT", &[I, M, X, M, M]);
+        assert!(records(&caller).is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+//! Checking recorded difference counts against the sequences (`--verify-diffs`).
+//!
+//! Each checked alignment is realigned segment by segment between its
+//! tracepoints, as `--exact-identity` does, and the edit distance of that
+//! path is compared with the record's D value. Since FastGA computes D over
+//! the same segments, any disagreement points at either the file or the
+//! reconstruction.
+
+use std::io::{self, Write};
+
+/// How many of the largest disagreements the summary lists.
+const WORST_SHOWN: usize = 10;
+
+#[derive(Debug)]
+pub struct DiffCheck {
+    /// Largest |realigned - recorded| still counted as agreement
+    tolerance: i64,
+    checked: usize,
+    /// Alignments without a D line, a usable trace or valid coordinates
+    unverifiable: usize,
+    total_abs_delta: i64,
+    /// `(index, delta)` for every alignment whose counts differ at all
+    disagreements: Vec<(usize, i64)>,
+}
+
+impl DiffCheck {
+    pub fn new(tolerance: i64) -> Self {
+        DiffCheck {
+            tolerance,
+            checked: 0,
+            unverifiable: 0,
+            total_abs_delta: 0,
+            disagreements: Vec::new(),
+        }
+    }
+
+    /// Record one alignment and write its line.
+    pub fn add<W: Write>(&mut self, out: &mut W, index: usize, recorded: i64, realigned: i64) -> io::Result<()> {
+        let delta = realigned - recorded;
+        self.checked += 1;
+        self.total_abs_delta += delta.abs();
+        if delta != 0 {
+            self.disagreements.push((index, delta));
+        }
+        write!(out, "alignment {}: recorded {}, realigned {}, delta {:+}", index, recorded, realigned, delta)?;
+        if delta.abs() > self.tolerance {
+            write!(out, " (beyond tolerance)")?;
+        }
+        writeln!(out)
+    }
+
+    pub fn add_unverifiable(&mut self) {
+        self.unverifiable += 1;
+    }
+
+    /// Alignments whose delta exceeds the tolerance.
+    pub fn beyond_tolerance(&self) -> usize {
+        self.disagreements.iter().filter(|(_, delta)| delta.abs() > self.tolerance).count()
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "Alignments checked: {}", self.checked)?;
+        writeln!(out, "Not verifiable (no D line, no usable trace or out of bounds): {}", self.unverifiable)?;
+        let mean = if self.checked == 0 { 0.0 } else { self.total_abs_delta as f64 / self.checked as f64 };
+        writeln!(out, "Mean absolute error: {:.4}", mean)?;
+        writeln!(out, "Differing: {}", self.disagreements.len())?;
+        writeln!(out, "Beyond tolerance ({}): {}", self.tolerance, self.beyond_tolerance())?;
+        if !self.disagreements.is_empty() {
+            let mut worst = self.disagreements.clone();
+            worst.sort_by(|a, b| b.1.abs().cmp(&a.1.abs()).then(a.0.cmp(&b.0)));
+            writeln!(out, "Worst offenders:")?;
+            for (index, delta) in worst.iter().take(WORST_SHOWN) {
+                writeln!(out, "  alignment {}: delta {:+}", index, delta)?;
+            }
+        }
+        Ok(())
+    }
+}
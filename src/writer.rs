@@ -0,0 +1,89 @@
+//! Writing alignments back out as a `.1aln` file.
+//!
+//! The output is opened from the input file, so it inherits the schema,
+//! header and GDB references, and alignments are written in contig
+//! coordinates exactly as FastGA stores them.
+
+use crate::{AlignmentData, DifferencesSource, FileMetadata};
+use onecode::OneFile;
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct AlnWriter<'a> {
+    file: OneFile,
+    metadata: &'a FileMetadata,
+    /// Value of the last 't' line written
+    trace_spacing: i64,
+}
+
+impl<'a> AlnWriter<'a> {
+    /// Create `path` with the header of `source`, and write the trace spacing line.
+    pub fn create(
+        path: &str,
+        source: &OneFile,
+        metadata: &'a FileMetadata,
+        trace_spacing: i64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut file = OneFile::open_write_from(path, source, true, 1)?;
+        let command = std::env::args().collect::<Vec<_>>().join(" ");
+        file.add_provenance("oneview-rs", env!("CARGO_PKG_VERSION"), &command);
+        if trace_spacing > 0 {
+            file.set_int(0, trace_spacing);
+            file.write_line('t', 0, None);
+        }
+        Ok(AlnWriter {
+            file,
+            metadata,
+            trace_spacing,
+        })
+    }
+
+    /// Write one alignment with its R, D, T and X lines, preceded by a 't'
+    /// line when its trace spacing differs from the previous record's.
+    pub fn write(&mut self, aln: &AlignmentData) -> Result<(), Box<dyn Error>> {
+        if aln.trace_spacing != self.trace_spacing {
+            self.file.set_int(0, aln.trace_spacing);
+            self.file.write_line('t', 0, None);
+            self.trace_spacing = aln.trace_spacing;
+        }
+        // Anonymous sides have no metadata entry and were never translated out of contig space
+        let contig_offset = |offsets: &HashMap<i64, (i64, i64)>, id: i64| offsets.get(&id).copied().unwrap_or((0, 0));
+        let (query_offset, _) = contig_offset(&self.metadata.query_contig_offsets, aln.query_id);
+        let (target_offset, target_contig_len) = contig_offset(&self.metadata.target_contig_offsets, aln.target_id);
+        let mut target_contig_start = aln.target_start - target_offset;
+        let mut target_contig_end = aln.target_end - target_offset;
+        if aln.strand == '-' && target_contig_len > 0 {
+            // Back to the reverse-complement frame FastGA stores '-' targets in
+            (target_contig_start, target_contig_end) =
+                (target_contig_len - target_contig_end, target_contig_len - target_contig_start);
+        }
+
+        let fields = [
+            aln.query_id,
+            aln.query_start - query_offset,
+            aln.query_end - query_offset,
+            aln.target_id,
+            target_contig_start,
+            target_contig_end,
+        ];
+        for (i, value) in fields.into_iter().enumerate() {
+            self.file.set_int(i, value);
+        }
+        self.file.write_line('A', 0, None);
+
+        if aln.strand == '-' {
+            self.file.write_line('R', 0, None);
+        }
+        if aln.differences_source == DifferencesSource::DLine {
+            self.file.set_int(0, aln.differences);
+            self.file.write_line('D', 0, None);
+        }
+        if !aln.tracepoints.is_empty() {
+            self.file.write_line('T', aln.tracepoints.len() as i64, Some(&aln.tracepoints));
+        }
+        if !aln.trace_diffs.is_empty() {
+            self.file.write_line('X', aln.trace_diffs.len() as i64, Some(&aln.trace_diffs));
+        }
+        Ok(())
+    }
+}
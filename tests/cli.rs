@@ -0,0 +1,309 @@
+//! End-to-end runs of the binary on the small ASCII `.1aln` files in
+//! `tests/data`. Sequence names and lengths come from `.fai` files there,
+//! so no GDB is needed.
+
+use std::process::{Command, Output};
+
+fn data(name: &str) -> String {
+    format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// Run oneview-rs from `tests/data`, away from any user configuration.
+fn oneview(args: &[&str]) -> Output {
+    let dir = data("");
+    Command::new(env!("CARGO_BIN_EXE_oneview-rs"))
+        .args(args)
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .env_remove("ONEVIEW_DEFAULT_FORMAT")
+        .env_remove("NO_COLOR")
+        .output()
+        .expect("run oneview-rs")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "oneview-rs failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).expect("UTF-8 output")
+}
+
+/// `file` with the fixture genomes' `.fai` files.
+fn with_fai<'a>(file: &'a str, args: &[&'a str]) -> Vec<&'a str> {
+    let mut all = vec![file, "--query-fai", "query.fai", "--target-fai", "target.fai"];
+    all.extend_from_slice(args);
+    all
+}
+
+/// The expected file is oneview-rs's own output, checked by hand against ALNtoPAF's
+/// formulas rather than produced by ALNtoPAF, so this guards the layout against regressions.
+#[test]
+fn compat_alntopaf_output_is_unchanged() {
+    let output = oneview(&with_fai("pair.1aln", &["--compat-alntopaf"]));
+    let expected = std::fs::read_to_string(data("pair.compat.paf")).unwrap();
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn write_1aln_round_trips() {
+    let written = std::env::temp_dir().join(format!("oneview-roundtrip-{}.1aln", std::process::id()));
+    let written = written.to_str().unwrap();
+    stdout(&oneview(&with_fai("pair.1aln", &["--write-1aln", written])));
+
+    let original = stdout(&oneview(&with_fai("pair.1aln", &["--paf"])));
+    let reread = stdout(&oneview(&with_fai(written, &["--paf"])));
+    std::fs::remove_file(written).unwrap();
+    assert_eq!(original.lines().count(), 2);
+    assert_eq!(reread, original);
+}
+
+/// The human-output block of the alignment whose query line is `query`.
+fn record<'a>(human: &'a str, query: &str) -> &'a str {
+    let start = human.find(query).unwrap_or_else(|| panic!("no record for {}", query));
+    let end = human[start..].find("\n\n").map_or(human.len(), |end| start + end);
+    &human[start..end]
+}
+
+#[test]
+fn each_alignment_uses_the_last_trace_spacing_before_it() {
+    let output = oneview(&with_fai("two-spacings.1aln", &[]));
+    let human = stdout(&output);
+    assert!(record(&human, "Query: q1:50-250").contains("Trace spacing: 100\nTrace segments: 3"));
+    assert!(record(&human, "Query: q2:100-200").contains("Trace spacing: 50\nTrace segments: 2"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("several trace spacings (50, 100)"));
+}
+
+#[test]
+fn skipped_records_still_change_the_trace_spacing() {
+    // q1 is longer than 500 bp, so its record, which the 't' line follows, is skipped unread
+    let human = stdout(&oneview(&with_fai("two-spacings.1aln", &["--max-query-len", "500"])));
+    assert!(!human.contains("Query: q1"));
+    assert!(record(&human, "Query: q2:100-200").contains("Trace spacing: 50\nTrace segments: 2"));
+}
+
+#[test]
+fn alignments_after_a_group_line_are_read() {
+    // groups.1aln is pair.1aln with a `g` line before each alignment
+    let grouped = stdout(&oneview(&with_fai("groups.1aln", &["--paf"])));
+    assert_eq!(grouped.lines().count(), 2);
+    assert_eq!(grouped, stdout(&oneview(&with_fai("pair.1aln", &["--paf"]))));
+    let metadata = stdout(&oneview(&with_fai("groups.1aln", &["--metadata"])));
+    assert!(metadata.contains("Alignment groups: 2"));
+}
+
+#[test]
+fn out_of_bounds_coordinates_are_reported_by_index() {
+    // alignment 0 lies inside q1 (1000 bp); alignment 1 ends at 1200
+    let output = oneview(&with_fai("bounds.1aln", &["--paf"]));
+    assert_eq!(stdout(&output).lines().count(), 2);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(
+        "1 alignments had coordinates exceeding sequence bounds; first: alignment 1: query q1 end 1200 exceeds its length 1000"
+    ));
+
+    let strict = oneview(&with_fai("bounds.1aln", &["--paf", "--strict"]));
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stdout).starts_with("q1\t1000\t50\t250\t"));
+    assert!(
+        String::from_utf8_lossy(&strict.stderr)
+            .contains("Alignment 1 exceeds its sequence bounds: query q1 end 1200 exceeds its length 1000")
+    );
+
+    let in_bounds = oneview(&with_fai("pair.1aln", &["--paf"]));
+    assert!(!String::from_utf8_lossy(&in_bounds.stderr).contains("exceeding sequence bounds"));
+}
+
+#[test]
+fn a_file_without_alignments() {
+    // empty.1aln is pair.1aln's header and trace spacing with no records
+    assert_eq!(stdout(&oneview(&with_fai("empty.1aln", &["--paf"]))), "");
+    assert!(stdout(&oneview(&with_fai("empty.1aln", &[]))).contains("No alignments found"));
+
+    let first = oneview(&with_fai("empty.1aln", &["--alignment", "0"]));
+    assert!(!first.status.success());
+    assert!(String::from_utf8_lossy(&first.stderr).contains("file contains no alignments"));
+}
+
+#[test]
+fn clip_cuts_alignments_at_the_region_boundary() {
+    let clip = |region: &str| stdout(&oneview(&with_fai("pair.1aln", &["--paf", "--target-region", region, "--clip"])));
+
+    // q1:50-250 on t1:1000-1198 at trace spacing 100; both cuts fall inside a segment, whose
+    // query bases and diffs are kept in proportion to the target bases kept
+    assert_eq!(
+        clip("t1:1021-1120"),
+        "q1\t1000\t71\t170\t+\tt1\t5000\t1021\t1120\t96\t198\t255\tdf:i:5\ttp:Z:1,29;4,70\n"
+    );
+    // The reverse-strand q2:0-100 lies on t1:4400-4500; its trace runs from the target end
+    assert_eq!(
+        clip("t1:4421-4460"),
+        "q2\t300\t40\t79\t-\tt1\t5000\t4421\t4460\t39\t78\t255\tdf:i:0\ttp:Z:0,39\n"
+    );
+    // Alignments inside the region pass through unchanged
+    assert_eq!(clip("t1:0-5000"), stdout(&oneview(&with_fai("pair.1aln", &["--paf"]))));
+
+    assert!(!oneview(&with_fai("pair.1aln", &["--clip"])).status.success());
+}
+
+/// A configuration file with `contents`, removed when dropped.
+struct Config(std::path::PathBuf);
+
+impl Config {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("oneview-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        Config(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for Config {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn a_report_on_the_command_line_replaces_the_config_format() {
+    let config = Config::new("report", "paf = true\n");
+    let stats = stdout(&oneview(&with_fai("pair.1aln", &["--config", config.path(), "--stats"])));
+    assert!(stats.contains("Mean identity"));
+    let paf = stdout(&oneview(&with_fai("pair.1aln", &["--config", config.path()])));
+    assert_eq!(paf, stdout(&oneview(&with_fai("pair.1aln", &["--paf"]))));
+}
+
+#[test]
+fn no_config_ignores_the_config_file() {
+    let dir = std::env::temp_dir().join(format!("oneview-no-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(".oneview.toml"), "paf = true\n").unwrap();
+    let (input, query_fai, target_fai) = (data("pair.1aln"), data("query.fai"), data("target.fai"));
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_oneview-rs"))
+            .args([&input, "--query-fai", &query_fai, "--target-fai", &target_fai])
+            .args(extra)
+            .current_dir(&dir)
+            .env("HOME", &dir)
+            .env_remove("ONEVIEW_DEFAULT_FORMAT")
+            .output()
+            .expect("run oneview-rs")
+    };
+    let configured = stdout(&run(&[]));
+    let ignored = stdout(&run(&["--no-config"]));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(configured.lines().count(), 2);
+    assert!(ignored.contains("=== ALIGNMENTS ==="));
+}
+
+#[test]
+fn liftover_maps_bed_intervals_through_the_trace() {
+    let unmapped = std::env::temp_dir().join(format!("oneview-unmapped-{}.bed", std::process::id()));
+    let unmapped_path = unmapped.to_str().unwrap();
+    let lift = |split: bool| {
+        let mut args = vec!["liftover", "pair.1aln", "--input", "lift.bed", "--query-fai", "query.fai"];
+        args.extend(["--target-fai", "target.fai", "--unmapped", unmapped_path]);
+        if split {
+            args.push("--split");
+        }
+        let lifted = stdout(&oneview(&args));
+        (lifted, std::fs::read_to_string(&unmapped).unwrap())
+    };
+
+    // a and b fall in the first and last segments of q1:50-250 (T 50,100,48), c on the
+    // reverse-strand q2 counts back from the target end and flips the strand
+    let (lifted, rejected) = lift(false);
+    assert_eq!(
+        lifted,
+        "t1\t1010\t1020\ta\t60\t+\t0.9749\t1\n\
+         t1\t1188\t1198\tb\t60\t+\t0.9749\t1\n\
+         t1\t4480\t4490\tc\t60\t-\t1.0000\t1\n"
+    );
+    assert_eq!(
+        rejected,
+        "#no single alignment contains the interval\nq1\t0\t100\td\n#no alignment on this sequence\nq9\t0\t10\te\n"
+    );
+
+    // --split lifts the part of d the alignment covers
+    let (lifted, rejected) = lift(true);
+    std::fs::remove_file(&unmapped).unwrap();
+    assert!(lifted.ends_with("t1\t1000\t1050\td\t60\t+\t0.9749\t1\n"));
+    assert!(rejected.starts_with("#no alignment covers this part\nq1\t0\t50\td\n"));
+}
+
+#[test]
+fn a_region_without_alignments_reports_none_found() {
+    // Both records are on t1, neither within its first 100 bases
+    let human = stdout(&oneview(&with_fai("pair.1aln", &["--target-region", "t1:0-100"])));
+    assert!(human.ends_with("No alignments found\n"));
+}
+
+#[test]
+fn a_rewritten_file_of_the_same_size_invalidates_its_region_index() {
+    let dir = std::env::temp_dir().join(format!("oneview-ridx-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("pair.1aln");
+    std::fs::copy(data("pair.1aln"), &input).unwrap();
+    let input = input.to_str().unwrap();
+    stdout(&oneview(&["index", input]));
+
+    let query = || oneview(&with_fai(input, &["--paf", "--target-region", "t1"]));
+    let fresh = String::from_utf8_lossy(&query().stderr).into_owned();
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+    std::fs::File::options().write(true).open(input).unwrap().set_modified(later).unwrap();
+    let stale = String::from_utf8_lossy(&query().stderr).into_owned();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(!fresh.contains("ignoring it"), "{}", fresh);
+    assert!(stale.contains("was built for a different version"), "{}", stale);
+}
+
+#[test]
+fn json_default_format_applies_to_stats_only() {
+    let (input, query_fai, target_fai) = (data("pair.1aln"), data("query.fai"), data("target.fai"));
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_oneview-rs"))
+            .args([&input, "--query-fai", &query_fai, "--target-fai", &target_fai])
+            .args(extra)
+            .current_dir(data(""))
+            .env("HOME", data(""))
+            .env("ONEVIEW_DEFAULT_FORMAT", "json")
+            .output()
+            .expect("run oneview-rs")
+    };
+    let stats = run(&["--stats"]);
+    assert!(stats.status.success());
+    assert!(stdout(&stats).trim_start().starts_with('{'));
+    let view = run(&[]);
+    assert!(view.status.success());
+    assert!(stdout(&view).contains("=== ALIGNMENTS ==="));
+}
+
+#[cfg(unix)]
+#[test]
+fn reads_alignments_from_a_fifo() {
+    let fifo = std::env::temp_dir().join(format!("oneview-fifo-{}.1aln", std::process::id()));
+    let _ = std::fs::remove_file(&fifo);
+    let status = Command::new("mkfifo").arg(&fifo).status().expect("run mkfifo");
+    assert!(status.success());
+    let fifo_path = fifo.to_str().unwrap().to_string();
+
+    // --alignment is refused before the FIFO is opened, so nothing needs to write to it
+    let refused = oneview(&with_fai(&fifo_path, &["--alignment", "0"]));
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("cannot read from a pipe"));
+
+    let contents = std::fs::read(data("pair.1aln")).unwrap();
+    let writer_path = fifo.clone();
+    let writer = std::thread::spawn(move || std::fs::write(writer_path, contents).unwrap());
+    let streamed = stdout(&oneview(&with_fai(&fifo_path, &["--paf"])));
+    writer.join().unwrap();
+    std::fs::remove_file(&fifo).unwrap();
+
+    assert_eq!(streamed, stdout(&oneview(&with_fai("pair.1aln", &["--paf"]))));
+}